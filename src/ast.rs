@@ -1,15 +1,60 @@
 //! AST types for RavensOne.
 
+use crate::token::Span;
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum AttrValue {
     Str(String),
-    Expr(String),
+    Expr(Expr),
+    /// A bare attribute such as `disabled`, which is `true`.
+    Bool,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Attr {
-    pub name: String,
-    pub value: AttrValue,
+pub enum Attr {
+    Named {
+        name: String,
+        value: AttrValue,
+        /// From the name to the end of the value.
+        span: Span,
+    },
+    /// `{...props}`
+    Spread {
+        value: Expr,
+        span: Span,
+    },
+    Directive(Directive),
+}
+
+impl Attr {
+    pub fn span(&self) -> Span {
+        match self {
+            Attr::Named { span, .. } | Attr::Spread { span, .. } => *span,
+            Attr::Directive(directive) => directive.span,
+        }
+    }
+}
+
+/// `on:click={handler}`, `class:active={cond}` or `bind:value={state}`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Directive {
+    pub kind: DirectiveKind,
+    /// The part after the `:`, e.g. `click`.
+    pub name: Ident,
+    /// The bare `class:active` and `bind:value` forms stand for `={active}`
+    /// and `={value}`.
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirectiveKind {
+    /// `on:` attaches an event handler.
+    On,
+    /// `class:` toggles a class name on a condition.
+    Class,
+    /// `bind:` keeps a form property and a state variable in sync.
+    Bind,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -18,14 +63,749 @@ pub enum Node {
         name: String,
         attrs: Vec<Attr>,
         children: Vec<Node>,
+        /// From the `<` of the opening tag to the `>` of the closing one.
+        span: Span,
     },
-    Text(String),
-    Expr(String),
+    /// `<>...</>`
+    Fragment {
+        children: Vec<Node>,
+        span: Span,
+    },
+    Text {
+        text: String,
+        span: Span,
+    },
+    Expr(Expr),
+    /// `{if cond { ... } else { ... }}`. An `else if` is stored as a lone `If`
+    /// in `otherwise`.
+    If {
+        cond: Expr,
+        then: Vec<Node>,
+        otherwise: Option<Vec<Node>>,
+        span: Span,
+    },
+    /// `{for item in items { ... }}`
+    For {
+        /// The loop variable, or an object or array destructuring pattern.
+        binding: Expr,
+        iterable: Expr,
+        body: Vec<Node>,
+        span: Span,
+    },
+    /// Markup the parser skipped over after an error.
+    Error {
+        span: Span,
+    },
+}
+
+impl Node {
+    /// The source range the node was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Node::Element { span, .. }
+            | Node::Fragment { span, .. }
+            | Node::Text { span, .. }
+            | Node::If { span, .. }
+            | Node::For { span, .. }
+            | Node::Error { span } => *span,
+            Node::Expr(expr) => expr.span,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Document {
     pub children: Vec<Node>,
+    pub span: Span,
+}
+
+/// A whole `.raven` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceFile {
+    pub items: Vec<Item>,
+    pub span: Span,
+}
+
+impl SourceFile {
+    /// The component or page declared as `name`.
+    pub fn component(&self, name: &str) -> Option<&Component> {
+        self.items.iter().find_map(|item| match item {
+            Item::Component(c) | Item::Page(c) if c.name.name == name => Some(c),
+            _ => None,
+        })
+    }
+
+    /// The file's page, which is its module's default export.
+    pub fn page(&self) -> Option<&Component> {
+        self.items.iter().find_map(|item| match item {
+            Item::Page(page) => Some(page),
+            _ => None,
+        })
+    }
+
+    /// The import that binds `name`, and the name as written in it.
+    pub fn import_of(&self, name: &str) -> Option<(&Import, &Ident)> {
+        self.items.iter().find_map(|item| match item {
+            Item::Import(import) => import
+                .default
+                .iter()
+                .chain(&import.names)
+                .find(|ident| ident.name == name)
+                .map(|ident| (import, ident)),
+            _ => None,
+        })
+    }
+}
+
+/// A top-level declaration.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Component(Component),
+    /// A routable component, declared with `page` instead of `component`.
+    Page(Component),
+    ServerFn(ServerFn),
+    Import(Import),
+    TypeDecl(TypeDecl),
+}
+
+/// `import { A, B } from "path"` or `import A from "path"`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub default: Option<Ident>,
+    pub names: Vec<Ident>,
+    pub source: String,
+    pub source_span: Span,
+    pub span: Span,
+}
+
+/// `type Name { field: Type, ... }` or `type Name = Type`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeDecl {
+    pub keyword: Span,
+    pub name: Ident,
+    pub body: TypeDeclBody,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeDeclBody {
+    Fields(Vec<Field>),
+    Alias(Type),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: Ident,
+    pub ty: Type,
+    pub span: Span,
+}
+
+/// `component Name(params) { body }` or `page Name(params) { body }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub keyword: Span,
+    pub name: Ident,
+    pub params: Vec<Param>,
+    pub body: Block,
+    pub span: Span,
+}
+
+impl Component {
+    /// `component Name(param: Type, ...)` as written in `source`, the text
+    /// the component was parsed from.
+    pub fn signature(&self, source: &str) -> String {
+        let text = |span: Span| {
+            source
+                .get(span.offset..span.end_offset())
+                .unwrap_or_default()
+        };
+        let params: Vec<&str> = self.params.iter().map(|param| text(param.span)).collect();
+        format!(
+            "{} {}({})",
+            text(self.keyword),
+            self.name.name,
+            params.join(", ")
+        )
+    }
+}
+
+/// `server fn name(params) -> Type { body }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerFn {
+    /// Covers both `server` and `fn`.
+    pub keyword: Span,
+    pub name: Ident,
+    pub params: Vec<Param>,
+    pub ret: Option<Type>,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: Ident,
+    /// The `?` of `name?: T`.
+    pub optional: Option<Span>,
+    pub ty: Option<Type>,
+    /// The expression after `=`.
+    pub default: Option<Expr>,
+    pub span: Span,
+}
+
+impl Param {
+    /// Whether callers may leave this parameter out: it is marked `?`, has a
+    /// default, or has an optional type.
+    pub fn is_optional(&self) -> bool {
+        self.optional.is_some()
+            || self.default.is_some()
+            || matches!(
+                self.ty,
+                Some(Type {
+                    kind: TypeKind::Optional(_),
+                    ..
+                })
+            )
+    }
+}
+
+/// A RavensOne type annotation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Type {
+    pub kind: TypeKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypeKind {
+    /// `string`, `Todo` or a generic such as `Result<User, Error>`.
+    Named { name: String, args: Vec<Type> },
+    /// `[T]`
+    Array(Box<Type>),
+    /// `T?`
+    Optional(Box<Type>),
+    /// `fn(A, B) -> R`; a missing return type means `void`.
+    Function {
+        params: Vec<Type>,
+        ret: Option<Box<Type>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Block {
+    pub stmts: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    VarDecl(Box<VarDecl>),
+    Expr(Expr),
+    Return(Return),
+    If(Box<IfStmt>),
+    For(Box<ForStmt>),
+    While(Box<WhileStmt>),
+    Switch(Box<SwitchStmt>),
+    Try(Box<TryStmt>),
+    /// `throw value`
+    Throw(Throw),
+    /// A statement the compiler does not model yet, such as `function` or
+    /// `break`.
+    Code(Code),
+}
+
+impl Stmt {
+    /// The source range the statement was parsed from.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::VarDecl(decl) => decl.span,
+            Stmt::Expr(expr) => expr.span,
+            Stmt::Return(ret) => ret.span,
+            Stmt::If(stmt) => stmt.span,
+            Stmt::For(stmt) => stmt.span,
+            Stmt::While(stmt) => stmt.span,
+            Stmt::Switch(stmt) => stmt.span,
+            Stmt::Try(stmt) => stmt.span,
+            Stmt::Throw(throw) => throw.span,
+            Stmt::Code(code) => code.span,
+        }
+    }
+}
+
+/// `const name: Type = value`, or the same with `let` or `var`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VarDecl {
+    /// `const`, `let` or `var`.
+    pub keyword: Ident,
+    /// The bound name, or an object or array destructuring pattern.
+    pub target: Expr,
+    pub ty: Option<Type>,
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Return {
+    pub value: Option<Expr>,
+    pub span: Span,
+}
+
+/// `if (cond) { ... } else { ... }`. A body written without braces is parsed
+/// as a block of its one statement.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IfStmt {
+    pub cond: Expr,
+    pub then: Block,
+    pub otherwise: Option<Else>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Else {
+    /// `else if (...) { ... }`
+    If(Box<IfStmt>),
+    Block(Block),
+}
+
+/// `for (...) { ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForStmt {
+    pub head: ForHead,
+    pub body: Block,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ForHead {
+    /// `init; test; update`, each of which may be left out. `init` is a
+    /// variable declaration or an expression statement.
+    Steps {
+        init: Option<Box<Stmt>>,
+        test: Option<Expr>,
+        update: Option<Expr>,
+    },
+    /// `const item of items`, or `in` when `of` is false.
+    Each {
+        /// `const`, `let` or `var`, if the binding is declared here.
+        keyword: Option<Ident>,
+        /// The loop variable, or an object or array destructuring pattern.
+        binding: Expr,
+        of: bool,
+        iterable: Expr,
+    },
+}
+
+/// `while (cond) { ... }`, or `do { ... } while (cond)` when `is_do`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhileStmt {
+    pub is_do: bool,
+    pub cond: Expr,
+    pub body: Block,
+    pub span: Span,
+}
+
+/// `switch (value) { case a: ... default: ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchStmt {
+    pub value: Expr,
+    pub cases: Vec<Case>,
+    pub span: Span,
+}
+
+/// One `case test:` of a `switch`, or its `default:` when `test` is `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    pub test: Option<Expr>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+/// `try { ... } catch (err) { ... } finally { ... }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TryStmt {
+    pub block: Block,
+    pub catch: Option<Catch>,
+    pub finally: Option<Block>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catch {
+    /// The caught error's name or destructuring pattern; `catch { ... }` has
+    /// none.
+    pub param: Option<Expr>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Throw {
+    pub value: Expr,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Ident(String),
+    /// A string literal's value, escapes resolved.
+    Str(String),
+    /// A number literal as written.
+    Number(String),
+    Bool(bool),
+    Null,
+    /// A regular expression literal as written, e.g. `/a+/g`.
+    Regex(String),
+    Template(Vec<TemplatePart>),
+    Array(Vec<Expr>),
+    Object(Vec<Prop>),
+    /// `...value` inside an array, object or argument list.
+    Spread(Box<Expr>),
+    /// `object.property`, or `object?.property` when `optional`.
+    Member {
+        object: Box<Expr>,
+        property: Ident,
+        optional: bool,
+    },
+    /// `object[index]`, or `object?.[index]` when `optional`.
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        optional: bool,
+    },
+    /// `callee(args)`, or `callee?.(args)` when `optional`.
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Expr>,
+        optional: bool,
+    },
+    /// `(params): ret => body`; parameters are names or destructuring
+    /// patterns, possibly [`ExprKind::Param`]s, and `ret` is the TypeScript
+    /// return type as written.
+    Arrow {
+        is_async: bool,
+        params: Vec<Expr>,
+        ret: Option<String>,
+        body: ArrowBody,
+    },
+    /// `function name(params): ret { body }` in expression position.
+    Function(Box<Function>),
+    /// A function parameter with a `?` marker or a TypeScript type, such as
+    /// `x?: number`.
+    Param {
+        target: Box<Expr>,
+        optional: bool,
+        ty: Option<String>,
+    },
+    /// `callee<types>`, the type arguments of a generic call as written.
+    TypeArgs {
+        callee: Box<Expr>,
+        types: String,
+    },
+    /// `value as Type`, with the type as written.
+    As {
+        value: Box<Expr>,
+        ty: String,
+    },
+    /// A prefix operator: `!x`, `-x`, `typeof x`, `await x`, `new X()`...
+    Unary {
+        op: String,
+        operand: Box<Expr>,
+    },
+    /// `x++`, `x--` or the non-null assertion `x!`.
+    Postfix {
+        op: String,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: String,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// `target = value`, or a compound assignment such as `+=`.
+    Assign {
+        op: String,
+        target: Box<Expr>,
+        value: Box<Expr>,
+    },
+    Conditional {
+        test: Box<Expr>,
+        consequent: Box<Expr>,
+        alternate: Box<Expr>,
+    },
+    Paren(Box<Expr>),
+    /// An element written in expression position.
+    Markup(Box<Node>),
 }
 
-// Optional: a tiny placeholder function can live at module scope (not inside a struct)
+#[derive(Debug, Clone, PartialEq)]
+pub enum TemplatePart {
+    /// Literal text exactly as written, escapes included.
+    Text(String),
+    Expr(Expr),
+}
+
+/// One entry of an object literal.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Prop {
+    KeyValue {
+        key: PropKey,
+        value: Expr,
+    },
+    /// `{ id }`
+    Shorthand(Ident),
+    /// `{ ...rest }`
+    Spread(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropKey {
+    Ident(Ident),
+    Str(String),
+    Number(String),
+    /// `[expr]: value`
+    Computed(Box<Expr>),
+}
+
+/// A `function` expression, possibly `async`, with its TypeScript return type
+/// as written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Function {
+    pub is_async: bool,
+    pub name: Option<Ident>,
+    pub params: Vec<Expr>,
+    pub ret: Option<String>,
+    pub body: Block,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArrowBody {
+    Expr(Box<Expr>),
+    Block(Block),
+}
+
+/// A name together with where it was written.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+/// Source text the compiler passes through verbatim.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Code {
+    pub text: String,
+    pub span: Span,
+}
+
+/// The root nodes each iteration of a `for` body renders, with fragments
+/// unwrapped so that every root can carry its own `key`.
+pub fn loop_roots(body: &[Node]) -> Vec<&Node> {
+    body.iter()
+        .flat_map(|node| match node {
+            Node::Fragment { children, .. } => children.iter().collect(),
+            other => vec![other],
+        })
+        .collect()
+}
+
+/// Calls `f` on every markup node in `block`, including elements nested inside
+/// expressions, attributes, other nodes and nested statements. Raw [`Code`]
+/// statements are skipped.
+pub fn visit_nodes<F: FnMut(&Node)>(block: &Block, f: &mut F) {
+    block.stmts.iter().for_each(|stmt| visit_stmt(stmt, f));
+}
+
+fn visit_stmt<F: FnMut(&Node)>(stmt: &Stmt, f: &mut F) {
+    match stmt {
+        Stmt::VarDecl(decl) => {
+            visit_expr(&decl.target, f);
+            if let Some(value) = &decl.value {
+                visit_expr(value, f);
+            }
+        }
+        Stmt::Expr(expr) | Stmt::Throw(Throw { value: expr, .. }) => visit_expr(expr, f),
+        Stmt::Return(ret) => {
+            if let Some(value) = &ret.value {
+                visit_expr(value, f);
+            }
+        }
+        Stmt::If(stmt) => visit_if(stmt, f),
+        Stmt::For(stmt) => {
+            match &stmt.head {
+                ForHead::Steps { init, test, update } => {
+                    if let Some(init) = init {
+                        visit_stmt(init, f);
+                    }
+                    test.iter()
+                        .chain(update)
+                        .for_each(|expr| visit_expr(expr, f));
+                }
+                ForHead::Each {
+                    binding, iterable, ..
+                } => {
+                    visit_expr(binding, f);
+                    visit_expr(iterable, f);
+                }
+            }
+            visit_nodes(&stmt.body, f);
+        }
+        Stmt::While(stmt) => {
+            visit_expr(&stmt.cond, f);
+            visit_nodes(&stmt.body, f);
+        }
+        Stmt::Switch(stmt) => {
+            visit_expr(&stmt.value, f);
+            for case in &stmt.cases {
+                if let Some(test) = &case.test {
+                    visit_expr(test, f);
+                }
+                case.body.iter().for_each(|stmt| visit_stmt(stmt, f));
+            }
+        }
+        Stmt::Try(stmt) => {
+            visit_nodes(&stmt.block, f);
+            if let Some(catch) = &stmt.catch {
+                visit_nodes(&catch.body, f);
+            }
+            if let Some(finally) = &stmt.finally {
+                visit_nodes(finally, f);
+            }
+        }
+        Stmt::Code(_) => {}
+    }
+}
+
+fn visit_if<F: FnMut(&Node)>(stmt: &IfStmt, f: &mut F) {
+    visit_expr(&stmt.cond, f);
+    visit_nodes(&stmt.then, f);
+    match &stmt.otherwise {
+        Some(Else::If(next)) => visit_if(next, f),
+        Some(Else::Block(block)) => visit_nodes(block, f),
+        None => {}
+    }
+}
+
+fn visit_expr<F: FnMut(&Node)>(expr: &Expr, f: &mut F) {
+    match &expr.kind {
+        ExprKind::Ident(_)
+        | ExprKind::Str(_)
+        | ExprKind::Number(_)
+        | ExprKind::Bool(_)
+        | ExprKind::Null
+        | ExprKind::Regex(_) => {}
+        ExprKind::Template(parts) => {
+            for part in parts {
+                if let TemplatePart::Expr(expr) = part {
+                    visit_expr(expr, f);
+                }
+            }
+        }
+        ExprKind::Array(items) => items.iter().for_each(|item| visit_expr(item, f)),
+        ExprKind::Object(props) => {
+            for prop in props {
+                match prop {
+                    Prop::KeyValue { key, value } => {
+                        if let PropKey::Computed(key) = key {
+                            visit_expr(key, f);
+                        }
+                        visit_expr(value, f);
+                    }
+                    Prop::Shorthand(_) => {}
+                    Prop::Spread(value) => visit_expr(value, f),
+                }
+            }
+        }
+        ExprKind::Spread(inner)
+        | ExprKind::Paren(inner)
+        | ExprKind::Member { object: inner, .. }
+        | ExprKind::Unary { operand: inner, .. }
+        | ExprKind::Postfix { operand: inner, .. }
+        | ExprKind::Param { target: inner, .. }
+        | ExprKind::TypeArgs { callee: inner, .. }
+        | ExprKind::As { value: inner, .. } => visit_expr(inner, f),
+        ExprKind::Index { object, index, .. } => {
+            visit_expr(object, f);
+            visit_expr(index, f);
+        }
+        ExprKind::Call { callee, args, .. } => {
+            visit_expr(callee, f);
+            args.iter().for_each(|arg| visit_expr(arg, f));
+        }
+        ExprKind::Arrow { params, body, .. } => {
+            params.iter().for_each(|param| visit_expr(param, f));
+            match body {
+                ArrowBody::Expr(body) => visit_expr(body, f),
+                ArrowBody::Block(block) => visit_nodes(block, f),
+            }
+        }
+        ExprKind::Function(function) => {
+            function
+                .params
+                .iter()
+                .for_each(|param| visit_expr(param, f));
+            visit_nodes(&function.body, f);
+        }
+        ExprKind::Binary { left, right, .. } => {
+            visit_expr(left, f);
+            visit_expr(right, f);
+        }
+        ExprKind::Assign { target, value, .. } => {
+            visit_expr(target, f);
+            visit_expr(value, f);
+        }
+        ExprKind::Conditional {
+            test,
+            consequent,
+            alternate,
+        } => {
+            visit_expr(test, f);
+            visit_expr(consequent, f);
+            visit_expr(alternate, f);
+        }
+        ExprKind::Markup(node) => visit_node(node, f),
+    }
+}
+
+fn visit_node<F: FnMut(&Node)>(node: &Node, f: &mut F) {
+    f(node);
+    match node {
+        Node::Element {
+            attrs, children, ..
+        } => {
+            for attr in attrs {
+                match attr {
+                    Attr::Named {
+                        value: AttrValue::Expr(expr),
+                        ..
+                    }
+                    | Attr::Spread { value: expr, .. }
+                    | Attr::Directive(Directive { value: expr, .. }) => visit_expr(expr, f),
+                    Attr::Named { .. } => {}
+                }
+            }
+            children.iter().for_each(|child| visit_node(child, f));
+        }
+        Node::Fragment { children, .. } => children.iter().for_each(|child| visit_node(child, f)),
+        Node::Text { .. } | Node::Error { .. } => {}
+        Node::Expr(expr) => visit_expr(expr, f),
+        Node::If {
+            cond,
+            then,
+            otherwise,
+            ..
+        } => {
+            visit_expr(cond, f);
+            then.iter()
+                .chain(otherwise.iter().flatten())
+                .for_each(|child| visit_node(child, f));
+        }
+        Node::For { iterable, body, .. } => {
+            visit_expr(iterable, f);
+            body.iter().for_each(|child| visit_node(child, f));
+        }
+    }
+}
@@ -1,25 +1,861 @@
 //! TSX codegen
 
-use crate::ast::{Attr, AttrValue, Document, Node};
+use crate::ast::{
+    loop_roots, ArrowBody, Attr, AttrValue, Block, Component, Directive, DirectiveKind, Document,
+    Else, Expr, ExprKind, ForHead, Function, IfStmt, Import, Item, Node, Param, Prop, PropKey,
+    ServerFn, Stmt, TemplatePart, Type, TypeDecl, TypeDeclBody, TypeKind,
+};
+use crate::codegen_enhanced;
+use crate::module::Module;
+use crate::sourcemap::Output;
+
+/// How markup returned from a component is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Everything on one line.
+    Compact,
+    /// Broken across indented lines wherever a line would exceed `width`
+    /// columns; see [`codegen_enhanced`].
+    Pretty { width: usize },
+}
 
 pub fn to_tsx(doc: &Document) -> String {
-    let mut out = String::new();
+    let mut out = Output::new();
     for node in &doc.children {
         render_node(node, &mut out);
     }
+    out.into_string()
+}
+
+/// Renders a whole module: its imports first, then every declaration in
+/// source order, each exported.
+pub fn render_module(module: &Module, layout: Layout) -> Output {
+    let mut imports = Output::new();
+    let mut decls = Vec::new();
+    for item in &module.file.items {
+        match item {
+            Item::Import(import) => {
+                imports.map(import.span);
+                imports.push_str(&render_import(import));
+            }
+            Item::Component(component) => decls.push(render_component(component, layout)),
+            Item::Page(page) => decls.push(render_page(page, layout)),
+            Item::ServerFn(func) => decls.push(render_server_fn(func, layout)),
+            Item::TypeDecl(decl) => {
+                let mut output = Output::new();
+                output.map(decl.span);
+                output.push_str(&render_type_decl(decl));
+                decls.push(output);
+            }
+        }
+    }
+    let mut output = Output::new();
+    if !imports.as_str().is_empty() {
+        output.append(imports);
+        output.push('\n');
+    }
+    for (idx, decl) in decls.into_iter().enumerate() {
+        if idx > 0 {
+            output.push('\n');
+        }
+        output.append(decl);
+    }
+    output
+}
+
+fn render_import(import: &Import) -> String {
+    let mut bindings = Vec::new();
+    if let Some(default) = &import.default {
+        bindings.push(default.name.clone());
+    }
+    if !import.names.is_empty() || import.default.is_none() {
+        let names: Vec<&str> = import.names.iter().map(|n| n.name.as_str()).collect();
+        bindings.push(format!("{{ {} }}", names.join(", ")));
+    }
+    format!(
+        "import {} from \"{}\";\n",
+        bindings.join(", "),
+        import.source
+    )
+}
+
+/// Renders a type declaration as an exported interface or type alias.
+pub fn render_type_decl(decl: &TypeDecl) -> String {
+    match &decl.body {
+        TypeDeclBody::Fields(fields) => {
+            let mut output = format!("export interface {} {{\n", decl.name.name);
+            for field in fields {
+                output.push_str(&format!("  {}: {};\n", field.name.name, ts_type(&field.ty)));
+            }
+            output.push_str("}\n");
+            output
+        }
+        TypeDeclBody::Alias(ty) => format!("export type {} = {};\n", decl.name.name, ts_type(ty)),
+    }
+}
+
+/// Renders a component declaration as an exported React function component.
+pub fn render_component(component: &Component, layout: Layout) -> Output {
+    render_function("export function", component, layout)
+}
+
+/// Renders a page as the module's default-exported component, followed by the
+/// route it is served under. Page parameters become dynamic path segments.
+pub fn render_page(page: &Component, layout: Layout) -> Output {
+    let mut output = render_function("export default function", page, layout);
+    let params: Vec<String> = page
+        .params
+        .iter()
+        .map(|param| format!("\"{}\"", param.name.name))
+        .collect();
+    output.push_str(&format!(
+        "\nexport const route = {{ path: \"{}\", params: [{}] }};\n",
+        route_path(page),
+        params.join(", ")
+    ));
+    output
+}
+
+/// `Index` is served at `/`; any other page at its kebab-cased name, so
+/// `TeamDashboard(teamId)` becomes `/team-dashboard/:teamId`.
+fn route_path(page: &Component) -> String {
+    let name = &page.name.name;
+    let mut path = String::from("/");
+    if name != "Index" {
+        for (idx, ch) in name.char_indices() {
+            if ch.is_ascii_uppercase() {
+                if idx > 0 {
+                    path.push('-');
+                }
+                path.push(ch.to_ascii_lowercase());
+            } else {
+                path.push(ch);
+            }
+        }
+    }
+    for param in &page.params {
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        path.push(':');
+        path.push_str(&param.name.name);
+        if param.is_optional() {
+            path.push('?');
+        }
+    }
+    path
+}
+
+/// Renders a component (or page) function. Parameters are destructured from
+/// a props object whose shape is declared as a `<Name>Props` interface.
+fn render_function(export: &str, component: &Component, layout: Layout) -> Output {
+    let name = &component.name.name;
+    let mut output = Output::new();
+    let params_binding = if component.params.is_empty() {
+        "()".to_string()
+    } else {
+        let props = format!("{}Props", name);
+        output.push_str(&format!("interface {} {{\n", props));
+        for param in &component.params {
+            let optional = if param.is_optional() { "?" } else { "" };
+            output.push_str(&format!(
+                "  {}{}: {};\n",
+                param.name.name,
+                optional,
+                param_ts_type(param)
+            ));
+        }
+        output.push_str("}\n\n");
+        let bindings: Vec<String> = component
+            .params
+            .iter()
+            .map(|param| match &param.default {
+                Some(default) => format!("{} = {}", param.name.name, expr_to_ts(default)),
+                None => param.name.name.clone(),
+            })
+            .collect();
+        format!("({{ {} }}: {})", bindings.join(", "), props)
+    };
+
+    output.map(component.span);
+    output.push_str(&format!("{} {}{} {{\n", export, name, params_binding));
+    render_block(&component.body, false, layout, &mut output);
+    output.push_str("}\n");
+    output
+}
+
+/// Renders a server function as an exported async function. Every returned
+/// value is awaited so callers always receive the resolved result, and a
+/// declared `-> T` becomes `Promise<T>`.
+pub fn render_server_fn(func: &ServerFn, layout: Layout) -> Output {
+    let params: Vec<String> = func.params.iter().map(render_ts_param).collect();
+
+    let mut output = Output::new();
+    output.map(func.span);
+    output.push_str(&format!(
+        "export async function {}({}){} {{\n",
+        func.name.name,
+        params.join(", "),
+        promise_type(func)
+    ));
+    render_block(&func.body, true, layout, &mut output);
+    output.push_str("}\n");
+    output
+}
+
+/// The `: Promise<T>` annotation for a server function's declared return
+/// type, or nothing when it has none.
+pub fn promise_type(func: &ServerFn) -> String {
+    func.ret
+        .as_ref()
+        .map_or_else(String::new, |ret| format!(": Promise<{}>", ts_type(ret)))
+}
+
+/// Renders a positional TypeScript parameter: `name?: T`, `name: T = value`
+/// or plain `name`.
+pub fn render_ts_param(param: &Param) -> String {
+    let name = &param.name.name;
+    let ty = param.ty.as_ref().map(|_| param_ts_type(param));
+    match (&param.default, ty) {
+        (Some(default), Some(ty)) => format!("{}: {} = {}", name, ty, expr_to_ts(default)),
+        (Some(default), None) => format!("{} = {}", name, expr_to_ts(default)),
+        (None, Some(ty)) if param.is_optional() => format!("{}?: {}", name, ty),
+        (None, Some(ty)) => format!("{}: {}", name, ty),
+        (None, None) if param.is_optional() => format!("{}?", name),
+        (None, None) => name.clone(),
+    }
+}
+
+/// The TypeScript type of a parameter with any optionality left to the `?`
+/// marker. Unannotated parameters take their type from a literal default.
+fn param_ts_type(param: &Param) -> String {
+    match &param.ty {
+        Some(Type {
+            kind: TypeKind::Optional(inner),
+            ..
+        }) => ts_type(inner),
+        Some(ty) => ts_type(ty),
+        None => {
+            let kind = param.default.as_ref().map(|default| &default.kind);
+            match kind {
+                Some(ExprKind::Str(_) | ExprKind::Template(_)) => "string".to_string(),
+                Some(ExprKind::Bool(_)) => "boolean".to_string(),
+                Some(ExprKind::Number(_)) => "number".to_string(),
+                Some(ExprKind::Unary { op, operand })
+                    if op == "-" && matches!(operand.kind, ExprKind::Number(_)) =>
+                {
+                    "number".to_string()
+                }
+                _ => "unknown".to_string(),
+            }
+        }
+    }
+}
+
+fn render_block(block: &Block, await_returns: bool, layout: Layout, output: &mut Output) {
+    for stmt in &block.stmts {
+        output.push_str("  ");
+        render_stmt(stmt, Some(2), await_returns, layout, output);
+        output.push('\n');
+    }
+}
+
+/// Renders a block on one line, as used for arrow function bodies.
+fn render_inline_block(block: &Block, layout: Layout, out: &mut Output) {
+    render_body(block, None, false, layout, out);
+}
+
+/// Renders a braced block nested in a statement starting at column `indent`:
+/// one statement per line, indented a level deeper, or everything on one line
+/// when `indent` is `None`.
+fn render_body(
+    block: &Block,
+    indent: Option<usize>,
+    await_returns: bool,
+    layout: Layout,
+    out: &mut Output,
+) {
+    if block.stmts.is_empty() {
+        out.push_str("{}");
+        return;
+    }
+    out.push('{');
+    render_stmts(
+        &block.stmts,
+        indent.map(|i| i + 2),
+        await_returns,
+        layout,
+        out,
+    );
+    line_break(indent, out);
+    out.push('}');
+}
+
+fn render_stmts(
+    stmts: &[Stmt],
+    indent: Option<usize>,
+    await_returns: bool,
+    layout: Layout,
+    out: &mut Output,
+) {
+    for stmt in stmts {
+        line_break(indent, out);
+        render_stmt(stmt, indent, await_returns, layout, out);
+    }
+}
+
+/// Starts a new line at column `indent`, or just separates with a space when
+/// printing on one line.
+fn line_break(indent: Option<usize>, out: &mut Output) {
+    match indent {
+        Some(indent) => {
+            out.push('\n');
+            out.push_str(&" ".repeat(indent));
+        }
+        None => out.push(' '),
+    }
+}
+
+/// Renders a statement starting at column `indent`; see [`render_body`].
+fn render_stmt(
+    stmt: &Stmt,
+    indent: Option<usize>,
+    await_returns: bool,
+    layout: Layout,
+    out: &mut Output,
+) {
+    out.map(stmt.span());
+    match stmt {
+        Stmt::VarDecl(decl) => {
+            out.push_str(&decl.keyword.name);
+            out.push(' ');
+            render_expr_in(&decl.target, layout, out);
+            if let Some(ty) = &decl.ty {
+                out.push_str(": ");
+                out.push_str(&ts_type(ty));
+            }
+            if let Some(value) = &decl.value {
+                out.push_str(" = ");
+                render_expr_in(value, layout, out);
+            }
+        }
+        Stmt::Expr(expr) => render_expr_in(expr, layout, out),
+        Stmt::Code(code) => out.push_str(&code.text),
+        Stmt::Throw(throw) => {
+            out.push_str("throw ");
+            render_expr_in(&throw.value, layout, out);
+        }
+        Stmt::If(stmt) => return render_if(stmt, indent, await_returns, layout, out),
+        Stmt::For(stmt) => {
+            out.push_str("for (");
+            match &stmt.head {
+                ForHead::Steps { init, test, update } => {
+                    // A declaration or expression statement brings its own `;`.
+                    match init {
+                        Some(init) => render_stmt(init, None, false, Layout::Compact, out),
+                        None => out.push(';'),
+                    }
+                    if let Some(test) = test {
+                        out.push(' ');
+                        render_expr_in(test, layout, out);
+                    }
+                    out.push(';');
+                    if let Some(update) = update {
+                        out.push(' ');
+                        render_expr_in(update, layout, out);
+                    }
+                }
+                ForHead::Each {
+                    keyword,
+                    binding,
+                    of,
+                    iterable,
+                } => {
+                    if let Some(keyword) = keyword {
+                        out.push_str(&keyword.name);
+                        out.push(' ');
+                    }
+                    render_expr_in(binding, layout, out);
+                    out.push_str(if *of { " of " } else { " in " });
+                    render_expr_in(iterable, layout, out);
+                }
+            }
+            out.push_str(") ");
+            return render_body(&stmt.body, indent, await_returns, layout, out);
+        }
+        Stmt::While(stmt) if stmt.is_do => {
+            out.push_str("do ");
+            render_body(&stmt.body, indent, await_returns, layout, out);
+            out.push_str(" while (");
+            render_expr_in(&stmt.cond, layout, out);
+            out.push(')');
+        }
+        Stmt::While(stmt) => {
+            out.push_str("while (");
+            render_expr_in(&stmt.cond, layout, out);
+            out.push_str(") ");
+            return render_body(&stmt.body, indent, await_returns, layout, out);
+        }
+        Stmt::Switch(stmt) => {
+            out.push_str("switch (");
+            render_expr_in(&stmt.value, layout, out);
+            out.push_str(") {");
+            for case in &stmt.cases {
+                line_break(indent.map(|i| i + 2), out);
+                out.map(case.span);
+                match &case.test {
+                    Some(test) => {
+                        out.push_str("case ");
+                        render_expr_in(test, layout, out);
+                    }
+                    None => out.push_str("default"),
+                }
+                out.push(':');
+                render_stmts(
+                    &case.body,
+                    indent.map(|i| i + 4),
+                    await_returns,
+                    layout,
+                    out,
+                );
+            }
+            if stmt.cases.is_empty() {
+                out.push('}');
+            } else {
+                line_break(indent, out);
+                out.push('}');
+            }
+            return;
+        }
+        Stmt::Try(stmt) => {
+            out.push_str("try ");
+            render_body(&stmt.block, indent, await_returns, layout, out);
+            if let Some(catch) = &stmt.catch {
+                out.push_str(" catch ");
+                if let Some(param) = &catch.param {
+                    out.push('(');
+                    render_expr_in(param, layout, out);
+                    out.push_str(") ");
+                }
+                render_body(&catch.body, indent, await_returns, layout, out);
+            }
+            if let Some(finally) = &stmt.finally {
+                out.push_str(" finally ");
+                render_body(finally, indent, await_returns, layout, out);
+            }
+            return;
+        }
+        Stmt::Return(ret) => {
+            out.push_str("return");
+            if let Some(value) = &ret.value {
+                out.push(' ');
+                match &value.kind {
+                    // `return (\n  <div>...</div>\n)` only needs its parentheses
+                    // in the source layout.
+                    ExprKind::Paren(inner) if matches!(inner.kind, ExprKind::Markup(_)) => {
+                        render_expr_in(inner, layout, out)
+                    }
+                    ExprKind::Unary { op, .. } if op == "await" => {
+                        render_expr_in(value, layout, out)
+                    }
+                    ExprKind::Binary { .. }
+                    | ExprKind::Conditional { .. }
+                    | ExprKind::Assign { .. }
+                    | ExprKind::Arrow { .. }
+                        if await_returns =>
+                    {
+                        out.push_str("await (");
+                        render_expr_in(value, layout, out);
+                        out.push(')');
+                    }
+                    _ => {
+                        if await_returns {
+                            out.push_str("await ");
+                        }
+                        render_expr_in(value, layout, out);
+                    }
+                }
+            }
+        }
+    }
+    out.push(';');
+}
+
+fn render_if(
+    stmt: &IfStmt,
+    indent: Option<usize>,
+    await_returns: bool,
+    layout: Layout,
+    out: &mut Output,
+) {
+    out.push_str("if (");
+    render_expr_in(&stmt.cond, layout, out);
+    out.push_str(") ");
+    render_body(&stmt.then, indent, await_returns, layout, out);
+    match &stmt.otherwise {
+        Some(Else::If(next)) => {
+            out.push_str(" else ");
+            out.map(next.span);
+            render_if(next, indent, await_returns, layout, out);
+        }
+        Some(Else::Block(block)) => {
+            out.push_str(" else ");
+            render_body(block, indent, await_returns, layout, out);
+        }
+        None => {}
+    }
+}
+
+/// Prints an expression as TypeScript. Parentheses are kept exactly where the
+/// source had them, so printing needs no precedence rules of its own.
+pub fn render_expr(expr: &Expr, out: &mut Output) {
+    render_expr_in(expr, Layout::Compact, out);
+}
+
+/// Like [`render_expr`], laying out any markup inside the expression as
+/// `layout` asks.
+pub(crate) fn render_expr_in(expr: &Expr, layout: Layout, out: &mut Output) {
+    out.map(expr.span);
+    match &expr.kind {
+        ExprKind::Ident(name) | ExprKind::Number(name) => out.push_str(name),
+        ExprKind::Str(value) => {
+            out.push('"');
+            out.push_str(&escape_string(value));
+            out.push('"');
+        }
+        ExprKind::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+        ExprKind::Null => out.push_str("null"),
+        ExprKind::Regex(raw) => out.push_str(raw),
+        ExprKind::Template(parts) => {
+            out.push('`');
+            for part in parts {
+                match part {
+                    TemplatePart::Text(text) => out.push_str(text),
+                    TemplatePart::Expr(expr) => {
+                        out.push_str("${");
+                        render_expr_in(expr, layout, out);
+                        out.push('}');
+                    }
+                }
+            }
+            out.push('`');
+        }
+        ExprKind::Array(items) => {
+            out.push('[');
+            render_expr_list(items, layout, out);
+            out.push(']');
+        }
+        ExprKind::Object(props) if props.is_empty() => out.push_str("{}"),
+        ExprKind::Object(props) => {
+            out.push_str("{ ");
+            for (idx, prop) in props.iter().enumerate() {
+                if idx > 0 {
+                    out.push_str(", ");
+                }
+                render_prop(prop, layout, out);
+            }
+            out.push_str(" }");
+        }
+        ExprKind::Spread(inner) => {
+            out.push_str("...");
+            render_expr_in(inner, layout, out);
+        }
+        ExprKind::Member {
+            object,
+            property,
+            optional,
+        } => {
+            render_expr_in(object, layout, out);
+            out.push_str(if *optional { "?." } else { "." });
+            out.push_str(&property.name);
+        }
+        ExprKind::Index {
+            object,
+            index,
+            optional,
+        } => {
+            render_expr_in(object, layout, out);
+            out.push_str(if *optional { "?.[" } else { "[" });
+            render_expr_in(index, layout, out);
+            out.push(']');
+        }
+        ExprKind::Call {
+            callee,
+            args,
+            optional,
+        } => {
+            render_expr_in(callee, layout, out);
+            out.push_str(if *optional { "?.(" } else { "(" });
+            render_expr_list(args, layout, out);
+            out.push(')');
+        }
+        ExprKind::Arrow {
+            is_async,
+            params,
+            ret,
+            body,
+        } => {
+            if *is_async {
+                out.push_str("async ");
+            }
+            render_params(params, ret.as_deref(), layout, out);
+            out.push_str(" => ");
+            match body {
+                ArrowBody::Expr(body) => render_expr_in(body, layout, out),
+                ArrowBody::Block(block) => render_inline_block(block, layout, out),
+            }
+        }
+        ExprKind::Function(function) => {
+            let Function {
+                is_async,
+                name,
+                params,
+                ret,
+                body,
+            } = &**function;
+            if *is_async {
+                out.push_str("async ");
+            }
+            out.push_str("function ");
+            if let Some(name) = name {
+                out.push_str(&name.name);
+            }
+            render_params(params, ret.as_deref(), layout, out);
+            out.push(' ');
+            render_inline_block(body, layout, out);
+        }
+        ExprKind::Param {
+            target,
+            optional,
+            ty,
+        } => {
+            render_expr_in(target, layout, out);
+            if *optional {
+                out.push('?');
+            }
+            if let Some(ty) = ty {
+                out.push_str(": ");
+                out.push_str(ty);
+            }
+        }
+        ExprKind::TypeArgs { callee, types } => {
+            render_expr_in(callee, layout, out);
+            out.push('<');
+            out.push_str(types);
+            out.push('>');
+        }
+        ExprKind::As { value, ty } => {
+            render_expr_in(value, layout, out);
+            out.push_str(" as ");
+            out.push_str(ty);
+        }
+        ExprKind::Unary { op, operand } => {
+            let mut rendered = Output::new();
+            render_expr_in(operand, layout, &mut rendered);
+            out.push_str(op);
+            // Keep `typeof x` apart, and `- -x` from turning into `--x`.
+            if op.ends_with(|c: char| c.is_ascii_alphabetic())
+                || op.ends_with(['+', '-'])
+                    && rendered.as_str().starts_with(op.chars().last().unwrap())
+            {
+                out.push(' ');
+            }
+            out.append(rendered);
+        }
+        ExprKind::Postfix { op, operand } => {
+            render_expr_in(operand, layout, out);
+            out.push_str(op);
+        }
+        ExprKind::Binary { op, left, right } => {
+            render_expr_in(left, layout, out);
+            out.push(' ');
+            out.push_str(op);
+            out.push(' ');
+            render_expr_in(right, layout, out);
+        }
+        ExprKind::Assign { op, target, value } => {
+            render_expr_in(target, layout, out);
+            out.push(' ');
+            out.push_str(op);
+            out.push(' ');
+            render_expr_in(value, layout, out);
+        }
+        ExprKind::Conditional {
+            test,
+            consequent,
+            alternate,
+        } => {
+            render_expr_in(test, layout, out);
+            out.push_str(" ? ");
+            render_expr_in(consequent, layout, out);
+            out.push_str(" : ");
+            render_expr_in(alternate, layout, out);
+        }
+        // Pretty markup brings its own parentheses when it breaks.
+        ExprKind::Paren(inner)
+            if layout != Layout::Compact && matches!(inner.kind, ExprKind::Markup(_)) =>
+        {
+            render_expr_in(inner, layout, out)
+        }
+        ExprKind::Paren(inner) => {
+            out.push('(');
+            render_expr_in(inner, layout, out);
+            out.push(')');
+        }
+        ExprKind::Markup(node) => match layout {
+            Layout::Compact => render_node(node, out),
+            Layout::Pretty { width } => {
+                let markup = codegen_enhanced::markup_value(node, out.as_str(), width);
+                out.append(markup);
+            }
+        },
+    }
+}
+
+pub fn expr_to_ts(expr: &Expr) -> String {
+    let mut out = Output::new();
+    render_expr(expr, &mut out);
+    out.into_string()
+}
+
+fn render_expr_list(items: &[Expr], layout: Layout, out: &mut Output) {
+    for (idx, item) in items.iter().enumerate() {
+        if idx > 0 {
+            out.push_str(", ");
+        }
+        render_expr_in(item, layout, out);
+    }
+}
+
+/// Prints `(params)` and the `: ret` return type if there is one.
+fn render_params(params: &[Expr], ret: Option<&str>, layout: Layout, out: &mut Output) {
+    out.push('(');
+    render_expr_list(params, layout, out);
+    out.push(')');
+    if let Some(ret) = ret {
+        out.push_str(": ");
+        out.push_str(ret);
+    }
+}
+
+fn render_prop(prop: &Prop, layout: Layout, out: &mut Output) {
+    match prop {
+        Prop::KeyValue { key, value } => {
+            match key {
+                PropKey::Ident(ident) => out.push_str(&ident.name),
+                PropKey::Str(key) => {
+                    out.push('"');
+                    out.push_str(&escape_string(key));
+                    out.push('"');
+                }
+                PropKey::Number(key) => out.push_str(key),
+                PropKey::Computed(key) => {
+                    out.push('[');
+                    render_expr_in(key, layout, out);
+                    out.push(']');
+                }
+            }
+            out.push_str(": ");
+            render_expr_in(value, layout, out);
+        }
+        Prop::Shorthand(ident) => out.push_str(&ident.name),
+        Prop::Spread(value) => {
+            out.push_str("...");
+            render_expr_in(value, layout, out);
+        }
+    }
+}
+
+/// RavensOne primitive types and the TypeScript types they compile to. Any
+/// other name is assumed to be a user-declared type and kept as-is.
+///
+/// Composite types map structurally:
+///
+/// | RavensOne       | TypeScript                                          |
+/// |-----------------|-----------------------------------------------------|
+/// | `T?`            | `T \| undefined`                                    |
+/// | `[T]`           | `T[]`                                               |
+/// | `fn(A) -> R`    | `(arg0: A) => R`                                    |
+/// | `Result<T, E>`  | `{ ok: true; value: T } \| { ok: false; error: E }` |
+pub(crate) const PRIMITIVE_TYPES: &[(&str, &str)] = &[
+    ("string", "string"),
+    ("uuid", "string"),
+    ("int", "number"),
+    ("float", "number"),
+    ("number", "number"),
+    ("bool", "boolean"),
+    ("datetime", "Date"),
+    ("void", "void"),
+];
+
+/// Maps a RavensOne type annotation to TypeScript.
+pub fn ts_type(ty: &Type) -> String {
+    match &ty.kind {
+        TypeKind::Named { name, args } if name == "Result" && args.len() == 2 => format!(
+            "{{ ok: true; value: {} }} | {{ ok: false; error: {} }}",
+            ts_type(&args[0]),
+            ts_type(&args[1])
+        ),
+        TypeKind::Named { name, args } => {
+            let base = PRIMITIVE_TYPES
+                .iter()
+                .find(|(raven, _)| raven == name)
+                .map_or(name.as_str(), |(_, ts)| ts);
+            if args.is_empty() {
+                base.to_string()
+            } else {
+                let args: Vec<String> = args.iter().map(ts_type).collect();
+                format!("{}<{}>", base, args.join(", "))
+            }
+        }
+        TypeKind::Array(inner) => format!("{}[]", ts_operand(inner)),
+        TypeKind::Optional(inner) => format!("{} | undefined", ts_operand(inner)),
+        TypeKind::Function { params, ret } => {
+            let params: Vec<String> = params
+                .iter()
+                .enumerate()
+                .map(|(idx, param)| format!("arg{}: {}", idx, ts_type(param)))
+                .collect();
+            let ret = ret.as_deref().map_or_else(|| "void".to_string(), ts_type);
+            format!("({}) => {}", params.join(", "), ret)
+        }
+    }
+}
+
+/// Like [`ts_type`], parenthesized where the result would otherwise bind
+/// loosely inside `T[]` or `T | undefined`.
+fn ts_operand(ty: &Type) -> String {
+    let loose = match &ty.kind {
+        TypeKind::Function { .. } | TypeKind::Optional(_) => true,
+        TypeKind::Named { name, args } => name == "Result" && args.len() == 2,
+        TypeKind::Array(_) => false,
+    };
+    if loose {
+        format!("({})", ts_type(ty))
+    } else {
+        ts_type(ty)
+    }
+}
+
+/// Renders a markup node on one line.
+pub(crate) fn node_to_tsx(node: &Node) -> Output {
+    let mut out = Output::new();
+    render_node(node, &mut out);
     out
 }
 
-fn render_node(node: &Node, out: &mut String) {
+fn render_node(node: &Node, out: &mut Output) {
     match node {
         Node::Element {
             name,
             attrs,
             children,
+            span,
         } => {
+            out.map(*span);
             out.push('<');
             out.push_str(name);
-            render_attrs(attrs, out);
+            render_attrs(name, attrs, out);
             if children.is_empty() {
                 out.push_str(" />");
             } else {
@@ -32,35 +868,452 @@ fn render_node(node: &Node, out: &mut String) {
                 out.push('>');
             }
         }
-        Node::Text(text) => out.push_str(text),
-        Node::Expr(code) => {
+        Node::Fragment { children, span } => {
+            out.map(*span);
+            out.push_str("<>");
+            for child in children {
+                render_node(child, out);
+            }
+            out.push_str("</>");
+        }
+        Node::Text { text, .. } => out.push_str(&jsx_text(text, false, false)),
+        Node::Expr(expr) => {
             out.push('{');
-            out.push_str(code);
+            render_expr(expr, out);
             out.push('}');
         }
+        Node::If { .. } | Node::For { .. } => {
+            out.push('{');
+            render_node_value(node, out);
+            out.push('}');
+        }
+        // Only partial documents from a recovering parse contain these.
+        Node::Error { .. } => {}
     }
 }
 
-fn render_attrs(attrs: &[Attr], out: &mut String) {
-    for attr in attrs {
+/// Renders a node where an expression is expected, on one line.
+pub(crate) fn node_value_to_tsx(node: &Node) -> Output {
+    let mut out = Output::new();
+    render_node_value(node, &mut out);
+    out
+}
+
+/// Renders a node where an expression is expected. A markup `if` lowers to a
+/// ternary (with `null` for a missing `else`) and a `for` to a `.map(...)`.
+fn render_node_value(node: &Node, out: &mut Output) {
+    match node {
+        Node::Element { .. } | Node::Fragment { .. } => render_node(node, out),
+        Node::Text { text, .. } => {
+            out.push('"');
+            out.push_str(&escape_string(text));
+            out.push('"');
+        }
+        Node::Expr(expr) => render_wrapped(expr, binds_loosely(expr), out),
+        Node::Error { .. } => out.push_str("null"),
+        Node::If {
+            cond,
+            then,
+            otherwise,
+            ..
+        } => {
+            out.append(condition(cond));
+            out.push_str(" ? ");
+            render_branch(then, out);
+            out.push_str(" : ");
+            render_branch(otherwise.as_deref().unwrap_or_default(), out);
+        }
+        Node::For {
+            binding,
+            iterable,
+            body,
+            ..
+        } => {
+            out.append(map_head(iterable, binding));
+            match loop_roots(body).as_slice() {
+                [node] => render_node_value(node, out),
+                nodes => {
+                    // Several roots per item become a keyed array rather than a
+                    // fragment, which could not carry a key.
+                    out.push('[');
+                    for (idx, node) in nodes.iter().enumerate() {
+                        if idx > 0 {
+                            out.push_str(", ");
+                        }
+                        render_node_value(node, out);
+                    }
+                    out.push(']');
+                }
+            }
+            out.push(')');
+        }
+    }
+}
+
+/// The condition of a lowered `if`, parenthesized when it would bind looser
+/// than the `?` that follows it.
+pub(crate) fn condition(cond: &Expr) -> Output {
+    let mut out = Output::new();
+    render_wrapped(cond, binds_loosely(cond), &mut out);
+    out
+}
+
+/// The start of a lowered `for`: `iterable.map((binding) => `.
+pub(crate) fn map_head(iterable: &Expr, binding: &Expr) -> Output {
+    let operand = matches!(
+        iterable.kind,
+        ExprKind::Ident(_)
+            | ExprKind::Member { .. }
+            | ExprKind::Index { .. }
+            | ExprKind::Call { .. }
+            | ExprKind::Paren(_)
+            | ExprKind::Array(_)
+    );
+    let mut out = Output::new();
+    render_wrapped(iterable, !operand, &mut out);
+    out.push_str(".map((");
+    render_expr(binding, &mut out);
+    out.push_str(") => ");
+    out
+}
+
+/// Renders the nodes of an `if` branch as one expression.
+fn render_branch(nodes: &[Node], out: &mut Output) {
+    match nodes {
+        [] => out.push_str("null"),
+        [node] => render_node_value(node, out),
+        nodes => {
+            out.push_str("<>");
+            for node in nodes {
+                render_node(node, out);
+            }
+            out.push_str("</>");
+        }
+    }
+}
+
+fn binds_loosely(expr: &Expr) -> bool {
+    matches!(
+        expr.kind,
+        ExprKind::Conditional { .. } | ExprKind::Assign { .. } | ExprKind::Arrow { .. }
+    )
+}
+
+fn render_wrapped(expr: &Expr, wrap: bool, out: &mut Output) {
+    if wrap {
+        out.push('(');
+        render_expr(expr, out);
+        out.push(')');
+    } else {
+        render_expr(expr, out);
+    }
+}
+
+fn render_attrs(element: &str, attrs: &[Attr], out: &mut Output) {
+    for prop in props(element, attrs) {
         out.push(' ');
-        out.push_str(&attr.name);
-        out.push('=');
-        match &attr.value {
-            AttrValue::Str(value) => {
-                out.push('"');
-                out.push_str(&escape_string(value));
-                out.push('"');
-            }
-            AttrValue::Expr(code) => {
-                out.push('{');
-                out.push_str(code);
+        out.push_str(&prop);
+    }
+}
+
+/// Renders an element's attributes as React props, one string per prop. On
+/// intrinsic elements HTML attribute names are translated (see
+/// [`react_attr_name`]) and inline `style` strings become style objects.
+pub(crate) fn props(element: &str, attrs: &[Attr]) -> Vec<String> {
+    let intrinsic = is_intrinsic(element);
+    let toggles_classes = attrs
+        .iter()
+        .any(|attr| matches!(attr, Attr::Directive(d) if d.kind == DirectiveKind::Class));
+    let mut class_rendered = false;
+    let mut props = Vec::new();
+    for attr in attrs {
+        if toggles_classes && is_class_attr(attr) {
+            // Every class source merges into the first one's position.
+            if !class_rendered {
+                props.push(class_name_prop(attrs));
+                class_rendered = true;
+            }
+            continue;
+        }
+        let mut out = Output::new();
+        match attr {
+            Attr::Named { name, value, .. } => {
+                if intrinsic {
+                    out.push_str(&react_attr_name(name));
+                } else {
+                    out.push_str(name);
+                }
+                match value {
+                    AttrValue::Str(value) if intrinsic && name == "style" => {
+                        out.push_str("={");
+                        out.push_str(&style_object(value));
+                        out.push('}');
+                    }
+                    AttrValue::Str(value) => {
+                        out.push('=');
+                        out.push_str(&jsx_attr_string(value));
+                    }
+                    AttrValue::Expr(expr) => {
+                        out.push_str("={");
+                        render_expr(expr, &mut out);
+                        out.push('}');
+                    }
+                    AttrValue::Bool => {}
+                }
+            }
+            Attr::Spread { value, .. } => {
+                out.push_str("{...");
+                render_expr(value, &mut out);
                 out.push('}');
             }
+            Attr::Directive(directive) => {
+                props.extend(directive_props(directive));
+                continue;
+            }
         }
+        props.push(out.into_string());
     }
+    props
+}
+
+/// Lowercase tags such as `div` are DOM elements; capitalized tags are
+/// components and hyphenated tags are custom elements, both of which receive
+/// their attributes unchanged.
+fn is_intrinsic(element: &str) -> bool {
+    element.starts_with(|c: char| c.is_ascii_lowercase()) && !element.contains('-')
 }
 
+/// HTML attributes whose React prop name is not just the camel-cased name.
+const REACT_ATTRS: &[(&str, &str)] = &[
+    ("class", "className"),
+    ("for", "htmlFor"),
+    ("accesskey", "accessKey"),
+    ("allowfullscreen", "allowFullScreen"),
+    ("autocomplete", "autoComplete"),
+    ("autofocus", "autoFocus"),
+    ("autoplay", "autoPlay"),
+    ("cellpadding", "cellPadding"),
+    ("cellspacing", "cellSpacing"),
+    ("charset", "charSet"),
+    ("colspan", "colSpan"),
+    ("contenteditable", "contentEditable"),
+    ("crossorigin", "crossOrigin"),
+    ("datetime", "dateTime"),
+    ("enctype", "encType"),
+    ("enterkeyhint", "enterKeyHint"),
+    ("formaction", "formAction"),
+    ("frameborder", "frameBorder"),
+    ("inputmode", "inputMode"),
+    ("maxlength", "maxLength"),
+    ("minlength", "minLength"),
+    ("novalidate", "noValidate"),
+    ("playsinline", "playsInline"),
+    ("readonly", "readOnly"),
+    ("referrerpolicy", "referrerPolicy"),
+    ("rowspan", "rowSpan"),
+    ("spellcheck", "spellCheck"),
+    ("srcset", "srcSet"),
+    ("tabindex", "tabIndex"),
+    ("usemap", "useMap"),
+    ("viewbox", "viewBox"),
+];
+
+/// Maps an HTML attribute name to its React prop: `class` to `className`,
+/// `tabindex` to `tabIndex`, hyphenated SVG names such as `stroke-width` to
+/// `strokeWidth` and namespaced ones such as `xlink:href` to `xlinkHref`.
+/// `data-*` and `aria-*` attributes are passed through as-is.
+fn react_attr_name(name: &str) -> String {
+    if name.starts_with("data-") || name.starts_with("aria-") {
+        return name.to_string();
+    }
+    if let Some((namespace, local)) = name.split_once(':') {
+        return format!("{}{}", namespace, capitalize(&camel_case(local)));
+    }
+    if let Some((_, react)) = REACT_ATTRS.iter().find(|(html, _)| *html == name) {
+        return react.to_string();
+    }
+    camel_case(name)
+}
+
+/// `stroke-width` to `strokeWidth`.
+fn camel_case(name: &str) -> String {
+    let mut parts = name.split('-');
+    let mut out = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        out.push_str(&capitalize(part));
+    }
+    out
+}
+
+/// Converts an inline CSS declaration list into a React style object:
+/// `color: red; font-size: 12px` becomes `{ color: "red", fontSize: "12px" }`.
+fn style_object(css: &str) -> String {
+    let mut entries = Vec::new();
+    for declaration in split_css(css) {
+        let Some((property, value)) = declaration.split_once(':') else {
+            continue;
+        };
+        let (property, value) = (property.trim(), value.trim());
+        if property.is_empty() {
+            continue;
+        }
+        let key = if property.starts_with("--") {
+            // Custom properties keep their name and need quoting.
+            format!("\"{}\"", property)
+        } else if let Some(vendor) = property.strip_prefix("-ms-") {
+            // React spells the `-ms-` prefix in lower case, the others
+            // capitalized: `msTransform` but `WebkitTransform`.
+            format!("ms{}", capitalize(&camel_case(vendor)))
+        } else if let Some(vendor) = property.strip_prefix('-') {
+            capitalize(&camel_case(vendor))
+        } else {
+            camel_case(property)
+        };
+        entries.push(format!("{}: \"{}\"", key, escape_string(value)));
+    }
+    if entries.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{ {} }}", entries.join(", "))
+    }
+}
+
+/// Splits CSS declarations on `;`, ignoring semicolons inside quotes or
+/// parentheses such as `url("data:image/png;base64,...")`.
+fn split_css(css: &str) -> Vec<&str> {
+    let mut declarations = Vec::new();
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut start = 0;
+    for (idx, ch) in css.char_indices() {
+        match (quote, ch) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(ch),
+            (None, '(') => depth += 1,
+            (None, ')') => depth = depth.saturating_sub(1),
+            (None, ';') if depth == 0 => {
+                declarations.push(&css[start..idx]);
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    declarations.push(&css[start..]);
+    declarations
+}
+
+fn is_class_attr(attr: &Attr) -> bool {
+    match attr {
+        Attr::Named { name, .. } => name == "class" || name == "className",
+        Attr::Directive(d) => d.kind == DirectiveKind::Class,
+        Attr::Spread { .. } => false,
+    }
+}
+
+/// Merges static classes and `class:` toggles into one `className`, e.g.
+/// `className={["btn", active && "active"].filter(Boolean).join(" ")}`.
+fn class_name_prop(attrs: &[Attr]) -> String {
+    let mut parts = Vec::new();
+    for attr in attrs {
+        match attr {
+            Attr::Named { name, value, .. } if name == "class" || name == "className" => {
+                match value {
+                    AttrValue::Str(value) => parts.push(format!("\"{}\"", escape_string(value))),
+                    AttrValue::Expr(expr) => parts.push(expr_to_ts(expr)),
+                    AttrValue::Bool => {}
+                }
+            }
+            Attr::Directive(d) if d.kind == DirectiveKind::Class => {
+                let mut part = Output::new();
+                let loose = binds_loosely(&d.value)
+                    || matches!(&d.value.kind, ExprKind::Binary { op, .. } if op == "||" || op == "??");
+                render_wrapped(&d.value, loose, &mut part);
+                part.push_str(&format!(" && \"{}\"", escape_string(&d.name.name)));
+                parts.push(part.into_string());
+            }
+            _ => {}
+        }
+    }
+    format!(
+        "className={{[{}].filter(Boolean).join(\" \")}}",
+        parts.join(", ")
+    )
+}
+
+/// Renders `on:` and `bind:` directives as React props. `bind:value={name}`
+/// follows the `useState` convention of a `setName` setter next to `name`.
+fn directive_props(directive: &Directive) -> Vec<String> {
+    let name = &directive.name.name;
+    let value = expr_to_ts(&directive.value);
+    match directive.kind {
+        DirectiveKind::On => vec![format!("{}={{{}}}", react_event(name), value)],
+        DirectiveKind::Bind => vec![
+            format!("{}={{{}}}", name, value),
+            format!(
+                "onChange={{(e) => set{}(e.target.{})}}",
+                capitalize(&value),
+                name
+            ),
+        ],
+        // Merged into `className` by `class_name_prop`.
+        DirectiveKind::Class => Vec::new(),
+    }
+}
+
+/// DOM event names whose React prop is not simply the capitalized name.
+const REACT_EVENTS: &[(&str, &str)] = &[
+    ("dblclick", "onDoubleClick"),
+    ("contextmenu", "onContextMenu"),
+    ("mousedown", "onMouseDown"),
+    ("mouseup", "onMouseUp"),
+    ("mousemove", "onMouseMove"),
+    ("mouseenter", "onMouseEnter"),
+    ("mouseleave", "onMouseLeave"),
+    ("mouseover", "onMouseOver"),
+    ("mouseout", "onMouseOut"),
+    ("pointerdown", "onPointerDown"),
+    ("pointerup", "onPointerUp"),
+    ("pointermove", "onPointerMove"),
+    ("keydown", "onKeyDown"),
+    ("keyup", "onKeyUp"),
+    ("keypress", "onKeyPress"),
+    ("focusin", "onFocus"),
+    ("focusout", "onBlur"),
+    ("touchstart", "onTouchStart"),
+    ("touchend", "onTouchEnd"),
+    ("touchmove", "onTouchMove"),
+    ("dragstart", "onDragStart"),
+    ("dragend", "onDragEnd"),
+    ("dragenter", "onDragEnter"),
+    ("dragleave", "onDragLeave"),
+    ("dragover", "onDragOver"),
+    ("animationend", "onAnimationEnd"),
+    ("transitionend", "onTransitionEnd"),
+];
+
+/// Maps a DOM event name to its React prop: `click` to `onClick` and
+/// `my-event` to `onMyEvent`.
+fn react_event(event: &str) -> String {
+    REACT_EVENTS
+        .iter()
+        .find(|(dom, _)| *dom == event)
+        .map_or_else(
+            || format!("on{}", event.split('-').map(capitalize).collect::<String>()),
+            |(_, react)| react.to_string(),
+        )
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Escapes text for a double-quoted JavaScript string literal. Control
+/// characters other than line breaks and tabs become `\uXXXX` escapes.
 fn escape_string(input: &str) -> String {
     input
         .chars()
@@ -68,16 +1321,353 @@ fn escape_string(input: &str) -> String {
             '"' => "\\\"".to_string(),
             '\\' => "\\\\".to_string(),
             '\n' => "\\n".to_string(),
+            '\r' => "\\r".to_string(),
             '\t' => "\\t".to_string(),
+            c if c.is_control() || c == '\u{2028}' || c == '\u{2029}' => {
+                format!("\\u{:04x}", c as u32)
+            }
             other => other.to_string(),
         })
         .collect()
 }
 
+/// Escapes decoded text for use as a JSX child. JSX text does not know
+/// backslash escapes: braces would open an expression, `<` and `>` are syntax
+/// errors and `&` would start a character reference.
+fn escape_jsx_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '{' => out.push_str("{\"{\"}"),
+            '}' => out.push_str("{\"}\"}"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            // JSX would collapse a raw line break, e.g. one decoded from `&#10;`.
+            '\n' => out.push_str("{\"\\n\"}"),
+            '\r' => out.push_str("{\"\\r\"}"),
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+/// Renders a text child. Spaces at the edges of JSX text only survive while
+/// they share a line with a neighbouring tag or expression, so an edge that
+/// starts or ends an output line is written as a `{" "}` literal instead.
+pub(crate) fn jsx_text(text: &str, line_start: bool, line_end: bool) -> String {
+    let is_space = |c: char| c == ' ' || c == '\t';
+    let body = text.trim_matches(is_space);
+    if body.is_empty() {
+        return if line_start || line_end {
+            format!("{{\"{}\"}}", escape_string(text))
+        } else {
+            text.to_string()
+        };
+    }
+    let leading = &text[..text.len() - text.trim_start_matches(is_space).len()];
+    let trailing = &text[text.trim_end_matches(is_space).len()..];
+    let edge = |spaces: &str, at_break: bool| {
+        if at_break && !spaces.is_empty() {
+            format!("{{\"{}\"}}", escape_string(spaces))
+        } else {
+            spaces.to_string()
+        }
+    };
+    format!(
+        "{}{}{}",
+        edge(leading, line_start),
+        escape_jsx_text(body),
+        edge(trailing, line_end)
+    )
+}
+
+/// Renders an attribute string. JSX attribute strings cannot escape a double
+/// quote and keep line breaks raw, so such values become `{"..."}` literals;
+/// otherwise only `&` needs escaping.
+fn jsx_attr_string(value: &str) -> String {
+    if value.contains(['"', '\n', '\r', '\t']) {
+        format!("{{\"{}\"}}", escape_string(value))
+    } else {
+        format!("\"{}\"", value.replace('&', "&amp;"))
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::items_after_test_module)]
 mod tests {
     use super::*;
+    use crate::ast::Item;
+    use crate::lexer::lex_source;
+    use crate::parser::parse_source;
+
+    fn param_type(ty: &str) -> Type {
+        let src = format!("component C(x: {}) {{ return <i /> }}", ty);
+        let tokens = lex_source(&src).unwrap();
+        let file = parse_source(&tokens, &src).unwrap();
+        let Item::Component(component) = &file.items[0] else {
+            panic!("expected component");
+        };
+        component.params[0].ty.clone().unwrap()
+    }
+
+    #[test]
+    fn maps_types_to_typescript() {
+        let cases = [
+            ("uuid", "string"),
+            ("int", "number"),
+            ("bool", "boolean"),
+            ("Todo", "Todo"),
+            ("string?", "string | undefined"),
+            ("[Todo]", "Todo[]"),
+            ("[string?]", "(string | undefined)[]"),
+            ("fn() -> void", "() => void"),
+            (
+                "fn(string, int) -> bool",
+                "(arg0: string, arg1: number) => boolean",
+            ),
+            ("[fn()]", "(() => void)[]"),
+            (
+                "Result<User, Error>",
+                "{ ok: true; value: User } | { ok: false; error: Error }",
+            ),
+            ("Map<string, [int]>", "Map<string, number[]>"),
+        ];
+        for (raven, ts) in cases {
+            assert_eq!(ts_type(&param_type(raven)), ts, "mapping {}", raven);
+        }
+    }
+
+    #[test]
+    fn prints_expressions_back_to_typescript() {
+        // (source, printed) pairs; most expressions print exactly as written.
+        let cases = [
+            ("{ where: { id } }", "{ where: { id } }"),
+            ("a + b * (c - 1)", "a + b * (c - 1)"),
+            (
+                "!done && count >= 2 ? \"many\" : 'one'",
+                "!done && count >= 2 ? \"many\" : \"one\"",
+            ),
+            ("user?.profile?.[key]?.()", "user?.profile?.[key]?.()"),
+            ("items.map(item => item.id)", "items.map((item) => item.id)"),
+            (
+                "async (e) => { await save(e); count++ }",
+                "async (e) => { await save(e); count++; }",
+            ),
+            ("[1, ...rest]", "[1, ...rest]"),
+            ("`hi ${name.first}!`", "`hi ${name.first}!`"),
+            ("typeof x === \"string\"", "typeof x === \"string\""),
+            ("-(-x) - - -y", "-(-x) - - -y"),
+            ("new Date(at)", "new Date(at)"),
+            ("open = !open", "open = !open"),
+            (r#""a\rb\x41""#, r#""a\rbA""#),
+            (r#"'\u00e9\u{1F600}\0'"#, r#""é😀\u0000""#),
+        ];
+        for (src, printed) in cases {
+            let markup = format!("<p>{{{}}}</p>", src);
+            let tokens = crate::lexer::lex(&markup).unwrap();
+            let doc = crate::parser::parse(&tokens, &markup).unwrap();
+            assert_eq!(
+                to_tsx(&doc),
+                format!("<p>{{{}}}</p>", printed),
+                "printing {}",
+                src
+            );
+        }
+    }
+
+    fn markup(src: &str) -> String {
+        let tokens = crate::lexer::lex(src).unwrap();
+        to_tsx(&crate::parser::parse(&tokens, src).unwrap())
+    }
+
+    #[test]
+    fn lowers_markup_if_to_ternary() {
+        assert_eq!(
+            markup("<p>{if open { <A /> } else { <B /> }}</p>"),
+            "<p>{open ? <A /> : <B />}</p>"
+        );
+        assert_eq!(
+            markup("<p>{if user.admin { <Admin /> <Audit /> }}</p>"),
+            "<p>{user.admin ? <><Admin /><Audit /></> : null}</p>"
+        );
+        assert_eq!(
+            markup("<p>{if a { \"one\" } else if b || c { <Two /> } else { {count} }}</p>"),
+            "<p>{a ? \"one\" : b || c ? <Two /> : count}</p>"
+        );
+    }
+
+    #[test]
+    fn lowers_markup_for_to_map() {
+        assert_eq!(
+            markup("<ul>{for item in items { <li key={item.id}>{item.name}</li> }}</ul>"),
+            "<ul>{items.map((item) => <li key={item.id}>{item.name}</li>)}</ul>"
+        );
+        assert_eq!(
+            markup("<dl>{for { id, term } in terms ?? [] { <dt key={id} /> <dd key={`d${id}`} /> }}</dl>"),
+            "<dl>{(terms ?? []).map(({ id, term }) => [<dt key={id} />, <dd key={`d${id}`} />])}</dl>"
+        );
+    }
+
+    #[test]
+    fn renders_fragments_and_attribute_forms() {
+        assert_eq!(
+            markup("<><input disabled value={v} {...rest} /><p>a</p></>"),
+            "<><input disabled value={v} {...rest} /><p>a</p></>"
+        );
+        assert_eq!(
+            markup("<ul>{for row in rows { <><dt key={row.k} /><dd key={row.v} /></> }}</ul>"),
+            "<ul>{rows.map((row) => [<dt key={row.k} />, <dd key={row.v} />])}</ul>"
+        );
+    }
+
+    /// Reads back what a JSX consumer sees for rendered text or attribute
+    /// values: `{"..."}` literals are unescaped and the rest entity-decoded.
+    fn jsx_value(rendered: &str) -> String {
+        let mut out = String::new();
+        let mut raw = String::new();
+        let mut chars = rendered.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '{' {
+                raw.push(ch);
+                continue;
+            }
+            out.push_str(&crate::entities::decode(&raw));
+            raw.clear();
+            assert_eq!(chars.next(), Some('"'), "expression in {:?}", rendered);
+            while let Some(ch) = chars.next() {
+                match ch {
+                    '"' => break,
+                    '\\' => out.push(match chars.next().unwrap() {
+                        'n' => '\n',
+                        'r' => '\r',
+                        't' => '\t',
+                        other => other,
+                    }),
+                    other => out.push(other),
+                }
+            }
+            assert_eq!(chars.next(), Some('}'));
+        }
+        out.push_str(&crate::entities::decode(&raw));
+        out
+    }
+
+    #[test]
+    fn escapes_text_children_for_jsx() {
+        assert_eq!(
+            markup("<p>if (a &gt; b) &lbrace; x &rbrace; &amp;amp;</p>"),
+            "<p>if (a &gt; b) {\"{\"} x {\"}\"} &amp;amp;</p>"
+        );
+        assert_eq!(
+            markup("<q>She said \"hi\" & left</q>"),
+            "<q>She said \"hi\" &amp; left</q>"
+        );
+        assert_eq!(
+            markup("<p>{if a { \"{b}\" }}</p>"),
+            "<p>{a ? \"{b}\" : null}</p>"
+        );
+    }
+
+    #[test]
+    fn escapes_attribute_strings_for_jsx() {
+        assert_eq!(
+            markup("<a title=\"say \\\"hi\\\"\" href=\"?a=1&amp;b=2\" alt=\"C:\\\\dir\" />"),
+            "<a title={\"say \\\"hi\\\"\"} href=\"?a=1&amp;b=2\" alt=\"C:\\dir\" />"
+        );
+        assert_eq!(markup("<p data-x=\"a\\nb\" />"), "<p data-x={\"a\\nb\"} />");
+    }
+
+    #[test]
+    fn rendered_text_round_trips_to_source_text() {
+        let texts = [
+            "plain words",
+            "braces { and } inside",
+            "a < b > c",
+            "quotes \" and ' apostrophes",
+            "ampersands & and &amp; entity",
+            "&lt;tag&gt; &copy; &#123;&#x7D; &bogus;",
+            "{}{}{",
+            "back\\slash",
+        ];
+        for text in texts {
+            let decoded = crate::entities::decode(text);
+            assert_eq!(
+                jsx_value(&escape_jsx_text(&decoded)),
+                decoded,
+                "text {:?}",
+                text
+            );
+            // Quoted attribute values are only entity-decoded.
+            let attr = jsx_attr_string(&decoded);
+            let value = match attr.strip_prefix('"').and_then(|a| a.strip_suffix('"')) {
+                Some(inner) => crate::entities::decode(inner),
+                None => jsx_value(&attr),
+            };
+            assert_eq!(value, decoded, "attribute {:?}", text);
+        }
+        // Text that reaches the markup parser comes out the same way.
+        let rendered = markup("<p>Tom &amp; Jerry's {\"{\"}fun{\"}\"}&gt;1</p>");
+        let children = &rendered["<p>".len()..rendered.len() - "</p>".len()];
+        assert_eq!(jsx_value(children), "Tom & Jerry's {fun}>1");
+    }
+
+    #[test]
+    fn keeps_edge_spaces_at_line_breaks() {
+        assert_eq!(jsx_text(" and ", false, false), " and ");
+        assert_eq!(jsx_text(" and ", true, false), "{\" \"}and ");
+        assert_eq!(jsx_text("Hello ", false, true), "Hello{\" \"}");
+        assert_eq!(jsx_text(" ", false, false), " ");
+        assert_eq!(jsx_text(" ", true, true), "{\" \"}");
+        assert_eq!(jsx_text("a{b}", true, true), "a{\"{\"}b{\"}\"}");
+        assert_eq!(escape_jsx_text("one\ntwo"), "one{\"\\n\"}two");
+    }
+
+    #[test]
+    fn converts_inline_styles() {
+        let cases = [
+            ("color: red", "{ color: \"red\" }"),
+            (
+                "font-size: 12px; -webkit-line-clamp: 2;",
+                "{ fontSize: \"12px\", WebkitLineClamp: \"2\" }",
+            ),
+            (
+                "-ms-transform: none; --gap: 4px",
+                "{ msTransform: \"none\", \"--gap\": \"4px\" }",
+            ),
+            (
+                "background: url(\"a;b.png\") no-repeat",
+                "{ background: \"url(\\\"a;b.png\\\") no-repeat\" }",
+            ),
+            ("", "{}"),
+        ];
+        for (css, object) in cases {
+            assert_eq!(style_object(css), object, "converting {:?}", css);
+        }
+    }
+
+    #[test]
+    fn renders_directives_as_react_props() {
+        assert_eq!(
+            markup("<button on:click={onClick} on:dblclick={zoom}>x</button>"),
+            "<button onClick={onClick} onDoubleClick={zoom}>x</button>"
+        );
+        assert_eq!(
+            markup("<my-picker on:value-change={pick} />"),
+            "<my-picker onValueChange={pick} />"
+        );
+        assert_eq!(
+            markup("<li class=\"item\" id=\"a\" class:done={todo.done} class:is-new={a || b} />"),
+            "<li className={[\"item\", todo.done && \"done\", (a || b) && \"is-new\"].filter(Boolean).join(\" \")} id=\"a\" />"
+        );
+        assert_eq!(
+            markup("<input bind:value={name} /><input type=\"checkbox\" bind:checked />"),
+            "<input value={name} onChange={(e) => setName(e.target.value)} /><input type=\"checkbox\" checked={checked} onChange={(e) => setChecked(e.target.checked)} />"
+        );
+    }
+
     use crate::ast::{Document, Node};
+    use crate::token::Span;
 
     #[test]
     fn renders_simple_tree() {
@@ -85,8 +1675,34 @@ mod tests {
             children: vec![Node::Element {
                 name: "div".into(),
                 attrs: vec![],
-                children: vec![Node::Text("Hello".into())],
+                children: vec![Node::Text {
+                    text: "Hello".into(),
+                    span: Span {
+                        line: 1,
+                        col: 6,
+                        offset: 5,
+                        len: 5,
+                        end_line: 1,
+                        end_col: 11,
+                    },
+                }],
+                span: Span {
+                    line: 1,
+                    col: 1,
+                    offset: 0,
+                    len: 16,
+                    end_line: 1,
+                    end_col: 17,
+                },
             }],
+            span: Span {
+                line: 1,
+                col: 1,
+                offset: 0,
+                len: 16,
+                end_line: 1,
+                end_col: 17,
+            },
         };
         assert_eq!(to_tsx(&doc), "<div>Hello</div>");
     }
@@ -98,7 +1714,23 @@ mod tests {
                 name: "img".into(),
                 attrs: vec![],
                 children: vec![], // no children -> self-closing
+                span: Span {
+                    line: 1,
+                    col: 1,
+                    offset: 0,
+                    len: 7,
+                    end_line: 1,
+                    end_col: 8,
+                },
             }],
+            span: Span {
+                line: 1,
+                col: 1,
+                offset: 0,
+                len: 7,
+                end_line: 1,
+                end_col: 8,
+            },
         };
         assert_eq!(to_tsx(&doc), "<img />");
     }
@@ -1,6 +1,279 @@
-//! Placeholder module for RavensOne.
+//! Layout-aware TSX printer for markup.
+//!
+//! [`codegen`](crate::codegen) prints markup on a single line, which keeps
+//! tests short but makes a real page unreadable in review. This printer keeps
+//! anything that fits the line width on one line and otherwise breaks it the
+//! way Prettier lays out JSX: one child per line, one prop per line once the
+//! opening tag is too long, and multi-line markup wrapped in `(...)`.
 
-/// Initializes module-specific resources.
-pub fn init() -> &'static str {
-    "initialized"
+use crate::ast::{loop_roots, Node};
+use crate::codegen::{
+    condition, jsx_text, map_head, node_to_tsx, node_value_to_tsx, props, render_expr_in, Layout,
+};
+use crate::sourcemap::Output;
+use crate::token::Span;
+
+/// Line width used by `raven build --pretty`.
+pub const DEFAULT_WIDTH: usize = 80;
+
+/// Renders markup that continues the last line of `preceding`, such as
+/// `return <markup>` or `const x = <markup>`: parenthesized and broken across
+/// lines indented a level deeper than that line if the markup does not fit on
+/// it.
+pub fn markup_value(node: &Node, preceding: &str, width: usize) -> Output {
+    let line = preceding.rsplit('\n').next().unwrap_or_default();
+    let indent = line.len() - line.trim_start_matches(' ').len();
+    let compact = node_to_tsx(node);
+    // Room for the `;` or `)` that usually follows.
+    if fits(line.chars().count(), compact.as_str(), 1, width) {
+        return compact;
+    }
+    let printer = Printer { width };
+    let mut out = Output::new();
+    out.push_str(&format!("(\n{}", pad(indent + 2)));
+    out.append(printer.node(node, indent + 2));
+    out.push_str(&format!("\n{})", pad(indent)));
+    out
+}
+
+struct Printer {
+    width: usize,
+}
+
+impl Printer {
+    /// Prints `node` starting at column `indent`; continuation lines carry
+    /// their own indentation.
+    fn node(&self, node: &Node, indent: usize) -> Output {
+        let compact = node_to_tsx(node);
+        if fits(indent, compact.as_str(), 0, self.width) {
+            return compact;
+        }
+        let mut out = Output::new();
+        match node {
+            Node::Element {
+                name,
+                attrs,
+                children,
+                span,
+            } => {
+                out.map(*span);
+                let props = props(name, attrs);
+                out.push_str(&self.open_tag(name, &props, children.is_empty(), indent));
+                if !children.is_empty() {
+                    self.children(children, indent, &mut out);
+                    out.push_str(&format!("</{}>", name));
+                }
+            }
+            Node::Fragment { children, span } => {
+                out.map(*span);
+                out.push_str("<>");
+                self.children(children, indent, &mut out);
+                out.push_str("</>");
+            }
+            Node::If { .. } | Node::For { .. } => {
+                out.push('{');
+                out.append(self.value(node, indent));
+                out.push('}');
+            }
+            Node::Text { .. } | Node::Expr(_) | Node::Error { .. } => return compact,
+        }
+        out
+    }
+
+    /// `<name a="1" b={2}>` on one line when it fits, otherwise with every
+    /// prop on its own line.
+    fn open_tag(&self, name: &str, props: &[String], empty: bool, indent: usize) -> String {
+        let end = if empty { " />" } else { ">" };
+        let inline = props.iter().fold(format!("<{}", name), |tag, prop| {
+            format!("{} {}", tag, prop)
+        });
+        if props.is_empty() || fits(indent, &inline, end.len(), self.width) {
+            return inline + end;
+        }
+        let mut out = format!("<{}", name);
+        for prop in props {
+            out.push_str(&format!("\n{}{}", pad(indent + 2), prop));
+        }
+        out.push_str(&format!("\n{}{}", pad(indent), end.trim_start()));
+        out
+    }
+
+    /// Children one per line, each indented a level deeper, followed by the
+    /// line break before the closing tag.
+    fn children(&self, children: &[Node], indent: usize, out: &mut Output) {
+        for child in children {
+            out.push_str(&format!("\n{}", pad(indent + 2)));
+            match child {
+                // Each text child now starts and ends a line, so spaces at its
+                // edges must be spelled out to survive.
+                Node::Text { text, .. } => out.push_str(&jsx_text(text, true, true)),
+                // Rendered in place so that markup inside the expression knows
+                // which column it starts at.
+                Node::Expr(expr) => {
+                    out.push('{');
+                    render_expr_in(expr, Layout::Pretty { width: self.width }, out);
+                    out.push('}');
+                }
+                _ => out.append(self.node(child, indent + 2)),
+            }
+        }
+        out.push_str(&format!("\n{}", pad(indent)));
+    }
+
+    /// Prints a lowered `if` or `for` where an expression is expected,
+    /// breaking a ternary's branches or a `map` callback body onto their own
+    /// lines.
+    fn value(&self, node: &Node, indent: usize) -> Output {
+        let compact = node_value_to_tsx(node);
+        // Room for the `{` and `}` around the value.
+        if fits(indent + 1, compact.as_str(), 1, self.width) {
+            return compact;
+        }
+        let mut out = Output::new();
+        match node {
+            Node::If {
+                cond,
+                then,
+                otherwise,
+                ..
+            } => {
+                let otherwise = otherwise.as_deref().unwrap_or_default();
+                out.append(condition(cond));
+                out.push_str(" ? ");
+                out.append(self.branch(then, indent));
+                out.push_str(" : ");
+                out.append(self.branch(otherwise, indent));
+            }
+            Node::For {
+                binding,
+                iterable,
+                body,
+                ..
+            } => {
+                out.append(map_head(iterable, binding));
+                match loop_roots(body).as_slice() {
+                    [root] => {
+                        out.push_str(&format!("(\n{}", pad(indent + 2)));
+                        out.append(self.node(root, indent + 2));
+                        out.push_str(&format!("\n{}))", pad(indent)));
+                    }
+                    roots => {
+                        out.push('[');
+                        for root in roots {
+                            out.push_str(&format!("\n{}", pad(indent + 2)));
+                            out.append(self.node(root, indent + 2));
+                            out.push(',');
+                        }
+                        out.push_str(&format!("\n{}])", pad(indent)));
+                    }
+                }
+            }
+            _ => return self.node(node, indent),
+        }
+        out
+    }
+
+    /// One arm of a ternary: markup goes in parentheses on its own lines,
+    /// anything else stays inline.
+    fn branch(&self, nodes: &[Node], indent: usize) -> Output {
+        let fragment;
+        let node = match nodes {
+            [] => {
+                let mut out = Output::new();
+                out.push_str("null");
+                return out;
+            }
+            [node @ (Node::Element { .. } | Node::Fragment { .. })] => node,
+            [node] => return self.value(node, indent),
+            nodes => {
+                fragment = Node::Fragment {
+                    children: nodes.to_vec(),
+                    span: Span::merge(nodes.iter().map(Node::span)).expect("two or more nodes"),
+                };
+                &fragment
+            }
+        };
+        let mut out = Output::new();
+        out.push_str(&format!("(\n{}", pad(indent + 2)));
+        out.append(self.node(node, indent + 2));
+        out.push_str(&format!("\n{})", pad(indent)));
+        out
+    }
+}
+
+/// Whether `text` fits on one line starting at column `indent` with `trailing`
+/// more columns after it.
+fn fits(indent: usize, text: &str, trailing: usize, width: usize) -> bool {
+    !text.contains('\n') && indent + text.chars().count() + trailing <= width
+}
+
+fn pad(indent: usize) -> String {
+    " ".repeat(indent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+    use crate::parser::parse;
+
+    fn pretty(src: &str, width: usize) -> String {
+        let tokens = lex(src).unwrap();
+        let doc = parse(&tokens, src).unwrap();
+        markup_value(&doc.children[0], "  return ", width).into_string()
+    }
+
+    #[test]
+    fn keeps_short_markup_on_the_return_line() {
+        assert_eq!(
+            pretty("<p>Hello <b>world</b></p>", 80),
+            "<p>Hello <b>world</b></p>"
+        );
+    }
+
+    #[test]
+    fn breaks_children_and_wraps_long_opening_tags() {
+        let src = "<form class=\"signup\" onSubmit={save} aria-label=\"Sign up\"><label for=\"email\">Email</label><input id=\"email\" type=\"email\" required /></form>";
+        assert_eq!(
+            pretty(src, 50),
+            "(\n\
+             \x20   <form\n\
+             \x20     className=\"signup\"\n\
+             \x20     onSubmit={save}\n\
+             \x20     aria-label=\"Sign up\"\n\
+             \x20   >\n\
+             \x20     <label htmlFor=\"email\">Email</label>\n\
+             \x20     <input id=\"email\" type=\"email\" required />\n\
+             \x20   </form>\n\
+             \x20 )"
+        );
+    }
+
+    #[test]
+    fn spells_out_edge_spaces_of_broken_text() {
+        assert_eq!(
+            pretty("<p>Hello <b>world</b> and welcome back</p>", 30),
+            "(\n    <p>\n      Hello{\" \"}\n      <b>world</b>\n      {\" \"}and welcome back\n    </p>\n  )"
+        );
+    }
+
+    #[test]
+    fn breaks_lowered_if_and_for() {
+        let src = "<ul>{for todo in todos { <li key={todo.id} class=\"todo\">{todo.title}</li> }}{if todos.length == 0 { <li class=\"empty\">Nothing to do</li> }}</ul>";
+        assert_eq!(
+            pretty(src, 50),
+            "(\n\
+             \x20   <ul>\n\
+             \x20     {todos.map((todo) => (\n\
+             \x20       <li key={todo.id} className=\"todo\">\n\
+             \x20         {todo.title}\n\
+             \x20       </li>\n\
+             \x20     ))}\n\
+             \x20     {todos.length == 0 ? (\n\
+             \x20       <li className=\"empty\">Nothing to do</li>\n\
+             \x20     ) : null}\n\
+             \x20   </ul>\n\
+             \x20 )"
+        );
+    }
 }
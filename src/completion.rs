@@ -0,0 +1,536 @@
+//! Completions for markup: component and element names after `<`, and props,
+//! attributes and directives inside a tag.
+//!
+//! The context comes from lexing the text before the cursor, which is whole
+//! up to there even while the rest of the file is half typed. Components and
+//! their props come from the recovering parser's view of the whole file.
+
+use crate::ast::{Component, SourceFile};
+use crate::lexer::may_start_expression;
+use crate::module::{Module, SymbolKind};
+use crate::token::{Token, TokenKind};
+use crate::{lex_source, parse_source_recovering};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    Component,
+    Prop,
+    /// An HTML element.
+    Element,
+    /// An HTML attribute.
+    Attribute,
+    /// An `on:`, `class:` or `bind:` directive.
+    Directive,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    /// A component's signature or a prop's declaration, as written.
+    pub detail: Option<String>,
+}
+
+/// The completions at a position and where the word they replace starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completions {
+    /// Byte offset of the partly typed word before the cursor.
+    pub start: usize,
+    pub items: Vec<Completion>,
+}
+
+/// Completes the word before byte `offset` of `source`. Imported components
+/// are offered by name only, since their files are not read.
+pub fn complete(source: &str, offset: usize) -> Completions {
+    complete_with(source, offset, &|_| None)
+}
+
+/// Like [`complete`], with `resolve` returning the source of the file an
+/// import path such as `"./Card"` names, to look up imported components.
+pub(crate) fn complete_with(
+    source: &str,
+    offset: usize,
+    resolve: &dyn Fn(&str) -> Option<String>,
+) -> Completions {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let start = source[..offset].trim_end_matches(is_word_char).len();
+    let word = &source[start..offset];
+    let mut items = match lex_source(&source[..start]) {
+        Ok(tokens) => {
+            let tokens: Vec<&Token> = tokens
+                .iter()
+                .filter(|t| {
+                    !matches!(
+                        t.kind,
+                        TokenKind::Whitespace | TokenKind::Comment(_) | TokenKind::Eof
+                    )
+                })
+                .collect();
+            let file = parse_file(source).or_else(|| parse_file(&source[..start]));
+            match (context(&tokens), file) {
+                (Some(Context::TagName), Some(file)) => tag_names(&file, source, resolve),
+                (Some(Context::Attribute { tag, given }), Some(file)) => {
+                    attributes(&file, source, resolve, &tag, &given)
+                }
+                _ => Vec::new(),
+            }
+        }
+        Err(_) => Vec::new(),
+    };
+    items.retain(|item| {
+        item.label.len() >= word.len()
+            && item.label.as_bytes()[..word.len()].eq_ignore_ascii_case(word.as_bytes())
+    });
+    Completions { start, items }
+}
+
+fn is_word_char(ch: char) -> bool {
+    ch.is_alphanumeric() || matches!(ch, '_' | '$' | '-' | ':')
+}
+
+fn parse_file(source: &str) -> Option<SourceFile> {
+    let tokens = lex_source(source).ok()?;
+    Some(parse_source_recovering(&tokens, source).0)
+}
+
+/// Where in markup the cursor is.
+#[derive(Debug, PartialEq, Eq)]
+enum Context {
+    /// Right after a `<`.
+    TagName,
+    /// Between attributes of the opening tag of `tag`, where `given` are
+    /// already set.
+    Attribute { tag: String, given: Vec<String> },
+}
+
+/// The context after `tokens`, the text before the word being completed.
+fn context(tokens: &[&Token]) -> Option<Context> {
+    let last = tokens.last()?;
+    match &last.kind {
+        TokenKind::LAngle => return Some(Context::TagName),
+        // `<` at the end of code is lexed as an operator, since no tag name
+        // follows it yet.
+        TokenKind::Op(op)
+            if op == "<"
+                && may_start_expression(tokens.len().checked_sub(2).map(|i| &tokens[i].kind)) =>
+        {
+            return Some(Context::TagName)
+        }
+        // An attribute value is being typed.
+        TokenKind::Equals | TokenKind::Colon => return None,
+        _ => {}
+    }
+    // Walk back to the `<` of the open tag, stepping over `{...}` values.
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().rev() {
+        match token.kind {
+            TokenKind::RBrace => depth += 1,
+            // Inside an expression.
+            TokenKind::LBrace if depth == 0 => return None,
+            TokenKind::LBrace => depth -= 1,
+            TokenKind::RAngle if depth == 0 => return None,
+            TokenKind::LAngle if depth == 0 => {
+                let TokenKind::Ident(tag) = &tokens.get(i + 1)?.kind else {
+                    return None;
+                };
+                let mut given = Vec::new();
+                let mut depth = 0;
+                for (j, token) in tokens.iter().enumerate().skip(i + 2) {
+                    match &token.kind {
+                        TokenKind::LBrace => depth += 1,
+                        TokenKind::RBrace => depth -= 1,
+                        TokenKind::Ident(name)
+                            if depth == 0 && tokens[j - 1].kind != TokenKind::Colon =>
+                        {
+                            given.push(name.clone())
+                        }
+                        _ => {}
+                    }
+                }
+                return Some(Context::Attribute {
+                    tag: tag.clone(),
+                    given,
+                });
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Components in scope, then HTML elements.
+fn tag_names(
+    file: &SourceFile,
+    source: &str,
+    resolve: &dyn Fn(&str) -> Option<String>,
+) -> Vec<Completion> {
+    let symbols = Module::new(file.clone()).map_or_else(|_| Vec::new(), |module| module.symbols);
+    let mut items: Vec<Completion> = symbols
+        .iter()
+        .filter(|symbol| matches!(symbol.kind, SymbolKind::Component | SymbolKind::Import))
+        .filter(|symbol| symbol.name.starts_with(|ch: char| ch.is_ascii_uppercase()))
+        .map(|symbol| Completion {
+            label: symbol.name.clone(),
+            kind: CompletionKind::Component,
+            detail: with_component(file, source, resolve, &symbol.name, |component, source| {
+                component.signature(source)
+            }),
+        })
+        .collect();
+    items.extend(HTML_ELEMENTS.iter().map(|name| Completion {
+        label: name.to_string(),
+        kind: CompletionKind::Element,
+        detail: None,
+    }));
+    items
+}
+
+/// Unset props of a component, or the attributes and directives of an HTML
+/// element.
+fn attributes(
+    file: &SourceFile,
+    source: &str,
+    resolve: &dyn Fn(&str) -> Option<String>,
+    tag: &str,
+    given: &[String],
+) -> Vec<Completion> {
+    if tag.starts_with(|ch: char| ch.is_ascii_uppercase()) {
+        return with_component(file, source, resolve, tag, |component, source| {
+            component
+                .params
+                .iter()
+                .filter(|param| !given.contains(&param.name.name))
+                .map(|param| Completion {
+                    label: param.name.name.clone(),
+                    kind: CompletionKind::Prop,
+                    detail: source
+                        .get(param.span.offset..param.span.end_offset())
+                        .map(str::to_string),
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    }
+    let specific = ELEMENT_ATTRIBUTES
+        .iter()
+        .filter(|(elements, _)| elements.contains(&tag))
+        .flat_map(|(_, attributes)| attributes.iter());
+    let mut items: Vec<Completion> = specific
+        .chain(GLOBAL_ATTRIBUTES)
+        .filter(|name| !given.iter().any(|given| given == *name))
+        .map(|name| Completion {
+            label: name.to_string(),
+            kind: CompletionKind::Attribute,
+            detail: None,
+        })
+        .collect();
+    let bindable = match tag {
+        "input" => &["value", "checked"][..],
+        "select" | "textarea" => &["value"][..],
+        _ => &[][..],
+    };
+    let directives = EVENTS
+        .iter()
+        .map(|event| format!("on:{}", event))
+        .chain(std::iter::once("class:".to_string()))
+        .chain(bindable.iter().map(|name| format!("bind:{}", name)));
+    items.extend(directives.map(|label| Completion {
+        label,
+        kind: CompletionKind::Directive,
+        detail: None,
+    }));
+    items
+}
+
+/// Calls `f` with component `name` and the source it was declared in: this
+/// file, or the file the import of it names.
+fn with_component<T>(
+    file: &SourceFile,
+    source: &str,
+    resolve: &dyn Fn(&str) -> Option<String>,
+    name: &str,
+    f: impl FnOnce(&Component, &str) -> T,
+) -> Option<T> {
+    if let Some(component) = file.component(name) {
+        return Some(f(component, source));
+    }
+    let (import, ident) = file.import_of(name)?;
+    let imported = resolve(&import.source)?;
+    let imported_file = parse_file(&imported)?;
+    let component = if import.default.as_ref() == Some(ident) {
+        imported_file.page()
+    } else {
+        imported_file.component(name)
+    }?;
+    Some(f(component, &imported))
+}
+
+const HTML_ELEMENTS: &[&str] = &[
+    "a",
+    "abbr",
+    "article",
+    "aside",
+    "audio",
+    "b",
+    "blockquote",
+    "br",
+    "button",
+    "canvas",
+    "caption",
+    "code",
+    "dd",
+    "details",
+    "dialog",
+    "div",
+    "dl",
+    "dt",
+    "em",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "i",
+    "iframe",
+    "img",
+    "input",
+    "label",
+    "legend",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "optgroup",
+    "option",
+    "p",
+    "pre",
+    "progress",
+    "section",
+    "select",
+    "small",
+    "span",
+    "strong",
+    "summary",
+    "svg",
+    "table",
+    "tbody",
+    "td",
+    "textarea",
+    "tfoot",
+    "th",
+    "thead",
+    "time",
+    "tr",
+    "ul",
+    "video",
+];
+
+/// Attributes every HTML element accepts.
+const GLOBAL_ATTRIBUTES: &[&str] = &[
+    "class", "id", "style", "title", "hidden", "tabindex", "role", "lang", "dir", "key",
+];
+
+/// Attributes specific to some elements.
+const ELEMENT_ATTRIBUTES: &[(&[&str], &[&str])] = &[
+    (&["a"], &["href", "target", "rel", "download"]),
+    (
+        &["img"],
+        &["src", "alt", "width", "height", "loading", "srcset"],
+    ),
+    (
+        &["audio", "video"],
+        &["src", "controls", "autoplay", "loop", "muted"],
+    ),
+    (&["iframe"], &["src", "width", "height", "allow", "loading"]),
+    (&["form"], &["action", "method", "enctype", "novalidate"]),
+    (&["label"], &["for"]),
+    (
+        &["input"],
+        &[
+            "type",
+            "name",
+            "value",
+            "placeholder",
+            "checked",
+            "disabled",
+            "required",
+            "readonly",
+            "min",
+            "max",
+            "step",
+            "maxlength",
+            "pattern",
+            "autocomplete",
+            "autofocus",
+        ],
+    ),
+    (
+        &["textarea"],
+        &[
+            "name",
+            "value",
+            "placeholder",
+            "rows",
+            "cols",
+            "disabled",
+            "required",
+            "readonly",
+        ],
+    ),
+    (
+        &["select"],
+        &["name", "value", "multiple", "disabled", "required"],
+    ),
+    (&["option"], &["value", "selected", "disabled"]),
+    (&["button"], &["type", "disabled", "name", "value"]),
+    (&["td", "th"], &["colspan", "rowspan"]),
+    (&["time"], &["datetime"]),
+    (&["details", "dialog"], &["open"]),
+    (&["ol"], &["start", "reversed"]),
+    (&["progress"], &["value", "max"]),
+];
+
+/// DOM events offered as `on:` directives.
+const EVENTS: &[&str] = &[
+    "click",
+    "dblclick",
+    "input",
+    "change",
+    "submit",
+    "keydown",
+    "keyup",
+    "focus",
+    "blur",
+    "mouseenter",
+    "mouseleave",
+    "scroll",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Completes at the `|` in `source`.
+    fn complete_at(source: &str) -> Completions {
+        let offset = source.find('|').unwrap();
+        complete(&source.replace('|', ""), offset)
+    }
+
+    fn labels(completions: &Completions) -> Vec<&str> {
+        completions
+            .items
+            .iter()
+            .map(|item| item.label.as_str())
+            .collect()
+    }
+
+    const CARDS: &str = "import { Avatar } from \"./Avatar\"\n\
+                         component Card(title: string, count?: int = 0) {\n  return <h2>{title}</h2>\n}\n";
+
+    #[test]
+    fn completes_component_and_element_names_after_an_angle_bracket() {
+        let completions = complete_at(&format!("{}component App() {{\n  return <C|\n}}\n", CARDS));
+        assert_eq!(labels(&completions), ["Card", "canvas", "caption", "code"]);
+        assert_eq!(
+            completions.items[0].detail.as_deref(),
+            Some("component Card(title: string, count?: int = 0)")
+        );
+        assert_eq!(completions.items[1].kind, CompletionKind::Element);
+
+        // Imports are in scope too, and a bare `<` offers everything.
+        let completions = complete_at(&format!(
+            "{}component App() {{\n  return <div><|</div>\n}}\n",
+            CARDS
+        ));
+        assert_eq!(labels(&completions)[..4], ["Avatar", "Card", "App", "a"]);
+        assert_eq!(completions.items[0].detail, None);
+        // The unfinished `App` itself is not offered.
+        let completions = complete_at(&format!("{}component App() {{\n  return <|", CARDS));
+        assert_eq!(completions.items.len(), HTML_ELEMENTS.len() + 2);
+    }
+
+    #[test]
+    fn completes_the_props_a_component_still_needs() {
+        let source = format!(
+            "{}component App() {{\n  return <Card count={{1}} t|\n}}\n",
+            CARDS
+        );
+        let completions = complete_at(&source);
+        assert_eq!(
+            completions.items,
+            [Completion {
+                label: "title".into(),
+                kind: CompletionKind::Prop,
+                detail: Some("title: string".into()),
+            }]
+        );
+        assert_eq!(completions.start, source.find("t|").unwrap());
+
+        let completions = complete_at(&format!(
+            "{}component App() {{\n  return <Card |title=\"x\" />\n}}\n",
+            CARDS
+        ));
+        assert_eq!(labels(&completions), ["title", "count"]);
+    }
+
+    #[test]
+    fn completes_props_of_imported_components() {
+        let source = format!("{}component App() {{\n  return <Avatar s", CARDS);
+        let resolve = |path: &str| {
+            (path == "./Avatar").then(|| {
+                "component Avatar(src: string, size: int) { return <img src={src} /> }".to_string()
+            })
+        };
+        let completions = complete_with(&source, source.len(), &resolve);
+        assert_eq!(labels(&completions), ["src", "size"]);
+        assert_eq!(complete(&source, source.len()).items, []);
+    }
+
+    #[test]
+    fn completes_attributes_and_directives_of_html_elements() {
+        let completions = complete_at("component App() {\n  return <input type=\"text\" |");
+        let labels = labels(&completions);
+        assert!(labels.contains(&"placeholder"));
+        assert!(labels.contains(&"class"));
+        assert!(labels.contains(&"bind:value"));
+        assert!(!labels.contains(&"type"));
+        assert!(!labels.contains(&"href"));
+
+        let source = "component App() {\n  return <button on:cl|>Go</button>\n}";
+        let completions = complete_at(source);
+        assert_eq!(
+            completions.items,
+            [Completion {
+                label: "on:click".into(),
+                kind: CompletionKind::Directive,
+                detail: None,
+            }]
+        );
+        assert_eq!(completions.start, source.find("on:").unwrap());
+    }
+
+    #[test]
+    fn offers_nothing_outside_tags() {
+        for source in [
+            "component App() {\n  const x = a <|",
+            "component App() {\n  return <p>some t|</p>\n}",
+            "component App() {\n  return <p class={ac|}></p>\n}",
+            "component App() {\n  return <p class=|></p>\n}",
+            "component App() {\n  return <p class=\"a|",
+            "comp|",
+        ] {
+            assert_eq!(complete_at(source).items, [], "{}", source);
+        }
+    }
+}
@@ -0,0 +1,98 @@
+//! HTML character references in markup text and attribute strings.
+//!
+//! Markup is decoded once when it is parsed, so the AST always holds the text
+//! a browser would display and codegen only has to re-escape it for JSX.
+
+/// Named references RavensOne understands. JSX itself accepts the full HTML
+/// list, but anything outside this table is kept verbatim.
+const NAMED: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{a0}'),
+    ("lbrace", '{'),
+    ("rbrace", '}'),
+    ("copy", '©'),
+    ("reg", '®'),
+    ("trade", '™'),
+    ("hellip", '…'),
+    ("mdash", '—'),
+    ("ndash", '–'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("laquo", '«'),
+    ("raquo", '»'),
+    ("middot", '·'),
+    ("bull", '•'),
+    ("times", '×'),
+    ("deg", '°'),
+    ("euro", '€'),
+];
+
+/// Replaces `&name;`, `&#123;` and `&#x7B;` references with the characters
+/// they stand for. Unknown or malformed references are left as written.
+pub fn decode(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        match rest[1..].find(';').and_then(|end| {
+            let reference = &rest[1..end + 1];
+            resolve(reference).map(|ch| (ch, end + 2))
+        }) {
+            Some((ch, len)) => {
+                out.push(ch);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn resolve(reference: &str) -> Option<char> {
+    if let Some(number) = reference.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        return char::from_u32(code);
+    }
+    NAMED
+        .iter()
+        .find(|(name, _)| *name == reference)
+        .map(|(_, ch)| *ch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_named_and_numeric_references() {
+        assert_eq!(decode("Tom &amp; Jerry"), "Tom & Jerry");
+        assert_eq!(decode("&lt;b&gt; &quot;x&quot;"), "<b> \"x\"");
+        assert_eq!(decode("&#123;&#x7d;&#X41;"), "{}A");
+        assert_eq!(decode("&lbrace;&rbrace;&copy;"), "{}©");
+    }
+
+    #[test]
+    fn keeps_unknown_and_malformed_references() {
+        assert_eq!(decode("a & b"), "a & b");
+        assert_eq!(decode("&bogus; &amp"), "&bogus; &amp");
+        assert_eq!(decode("&#xZZ; &#;"), "&#xZZ; &#;");
+        assert_eq!(decode("&&amp;"), "&&");
+    }
+}
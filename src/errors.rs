@@ -1,6 +1,608 @@
-//! Placeholder module for RavensOne.
+//! Compiler diagnostics and their terminal rendering.
+//!
+//! A [`Diagnostic`] is built from a [`CompileError`] and rendered the way
+//! rustc does: a header with the severity and message, the file path with the
+//! primary position, and the offending source lines with labelled underlines.
+//! Positions are always recomputed from byte offsets into the whole file, so
+//! they stay correct however the span was produced.
 
-/// Initializes module-specific resources.
-pub fn init() -> &'static str {
-    "initialized"
+use std::fmt::Write;
+
+use serde_json::json;
+
+use crate::lexer::LexError;
+use crate::module::ModuleError;
+use crate::parser::ParseError;
+use crate::token::{Span, TokenKind};
+use crate::CompileError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl Severity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        }
+    }
+}
+
+/// A message attached to a range of source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+    /// The primary label marks where the problem is; secondary labels point
+    /// at related code, such as an earlier definition.
+    pub primary: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    pub fn with_code(mut self, code: &'static str) -> Self {
+        self.code = Some(code);
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: true,
+        });
+        self
+    }
+
+    pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+            primary: false,
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// The first primary label, which gives the diagnostic its position.
+    pub fn primary(&self) -> Option<&Label> {
+        self.labels.iter().find(|label| label.primary)
+    }
+
+    /// Renders the diagnostic for `source`, read from `path`. With `color`
+    /// set the output uses ANSI escapes.
+    pub fn render(&self, path: &str, source: &str, color: bool) -> String {
+        let style = Style { color };
+        let lines = LineIndex::new(source);
+        let mut out = String::new();
+
+        let severity = match self.severity {
+            Severity::Error => RED,
+            Severity::Warning => YELLOW,
+        };
+        let head = match self.code {
+            Some(code) => format!("{}[{}]", self.severity.as_str(), code),
+            None => self.severity.as_str().to_string(),
+        };
+        let _ = writeln!(
+            out,
+            "{}{}",
+            style.paint(&head, severity),
+            style.paint(&format!(": {}", self.message), BOLD)
+        );
+
+        let mut labels: Vec<(usize, usize, &Label)> = self
+            .labels
+            .iter()
+            .map(|label| {
+                let (line, col) = lines.position(source, label.span.offset);
+                (line, col, label)
+            })
+            .collect();
+        labels.sort_by_key(|&(line, col, _)| (line, col));
+        let gutter = labels
+            .last()
+            .map_or(0, |&(line, _, _)| line.to_string().len());
+        let pad = " ".repeat(gutter);
+
+        let location = match self.primary() {
+            Some(label) => {
+                let (line, col) = lines.position(source, label.span.offset);
+                format!("{}:{}:{}", path, line, col)
+            }
+            None => path.to_string(),
+        };
+        let _ = writeln!(out, "{}{} {}", pad, style.paint("-->", BLUE), location);
+        if labels.is_empty() {
+            self.render_notes(&pad, &style, &mut out);
+            return out;
+        }
+
+        let bar = style.paint("|", BLUE);
+        let _ = writeln!(out, "{} {}", pad, bar);
+        let mut previous: Option<usize> = None;
+        for &(line, _, label) in &labels {
+            if previous != Some(line) {
+                if previous.is_some_and(|previous| line > previous + 1) {
+                    let _ = writeln!(out, "{}", style.paint("...", BLUE));
+                }
+                let number = style.paint(&format!("{:>width$}", line, width = gutter), BLUE);
+                let _ = writeln!(out, "{} {} {}", number, bar, lines.text(source, line));
+                previous = Some(line);
+            }
+            let text = lines.text(source, line);
+            let start = label.span.offset.min(source.len()) - lines.start(line);
+            let start = start.min(text.len());
+            let end = (label.span.end_offset().min(source.len()) - lines.start(line))
+                .clamp(start, text.len());
+            // Keep tabs in the indentation so the marks line up with the text.
+            let indent: String = text[..start]
+                .chars()
+                .map(|ch| if ch == '\t' { '\t' } else { ' ' })
+                .collect();
+            let width = text[start..end].chars().count().max(1);
+            let (mark, color) = if label.primary {
+                ('^', severity)
+            } else {
+                ('-', BLUE)
+            };
+            let underline = mark.to_string().repeat(width);
+            let marks = if label.message.is_empty() {
+                underline
+            } else {
+                format!("{} {}", underline, label.message)
+            };
+            let _ = writeln!(
+                out,
+                "{} {} {}{}",
+                pad,
+                bar,
+                indent,
+                style.paint(&marks, color)
+            );
+        }
+        self.render_notes(&pad, &style, &mut out);
+        out
+    }
+
+    fn render_notes(&self, pad: &str, style: &Style, out: &mut String) {
+        for note in &self.notes {
+            let _ = writeln!(out, "{} {} note: {}", pad, style.paint("=", BLUE), note);
+        }
+    }
+
+    /// The diagnostic as one JSON object for `raven build --message-format
+    /// json`. `span` is the primary label's range, or `null` without one.
+    /// Byte ranges are end-exclusive; lines and columns are 1-based and count
+    /// characters, with `col_end` just past the last one.
+    pub fn to_json(&self, path: &str, source: &str) -> serde_json::Value {
+        let lines = LineIndex::new(source);
+        let range = |span: Span| {
+            let start = span.offset.min(source.len());
+            let end = span.end_offset().clamp(start, source.len());
+            let (line_start, col_start) = lines.position(source, start);
+            let (line_end, col_end) = lines.position(source, end);
+            json!({
+                "byte_start": start,
+                "byte_end": end,
+                "line_start": line_start,
+                "col_start": col_start,
+                "line_end": line_end,
+                "col_end": col_end,
+            })
+        };
+        json!({
+            "code": self.code,
+            "severity": self.severity.as_str(),
+            "message": self.message,
+            "file": path,
+            "span": self.primary().map(|label| range(label.span)),
+            "labels": self
+                .labels
+                .iter()
+                .map(|label| json!({
+                    "message": label.message,
+                    "primary": label.primary,
+                    "span": range(label.span),
+                }))
+                .collect::<Vec<_>>(),
+            "notes": self.notes,
+        })
+    }
+}
+
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[1;31m";
+const YELLOW: &str = "\x1b[1;33m";
+const BLUE: &str = "\x1b[1;34m";
+const RESET: &str = "\x1b[0m";
+
+struct Style {
+    color: bool,
+}
+
+impl Style {
+    fn paint(&self, text: &str, code: &str) -> String {
+        if self.color {
+            format!("{}{}{}", code, text, RESET)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Byte offsets of the start of every line of a file.
+struct LineIndex {
+    starts: Vec<usize>,
+}
+
+impl LineIndex {
+    fn new(source: &str) -> Self {
+        Self {
+            starts: std::iter::once(0)
+                .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+                .collect(),
+        }
+    }
+
+    /// The 1-based line and character column of byte `offset`.
+    fn position(&self, source: &str, offset: usize) -> (usize, usize) {
+        let mut offset = offset.min(source.len());
+        while !source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let col = source[self.starts[line - 1]..offset].chars().count() + 1;
+        (line, col)
+    }
+
+    fn start(&self, line: usize) -> usize {
+        self.starts[line - 1]
+    }
+
+    /// The text of 1-based `line`, without its line break.
+    fn text<'a>(&self, source: &'a str, line: usize) -> &'a str {
+        let end = self.starts.get(line).map_or(source.len(), |&next| next - 1);
+        source[self.start(line)..end].trim_end_matches('\r')
+    }
+}
+
+/// Describes a token the parser did not expect, e.g. "`}`" or "end of file".
+fn describe(kind: &TokenKind) -> String {
+    match kind {
+        TokenKind::Ident(_)
+        | TokenKind::Str(_)
+        | TokenKind::Number(_)
+        | TokenKind::Template(_)
+        | TokenKind::Regex(_)
+        | TokenKind::Text(_)
+        | TokenKind::Whitespace
+        | TokenKind::Comment(_)
+        | TokenKind::Eof => kind.to_string(),
+        _ => format!("`{}`", kind),
+    }
+}
+
+impl CompileError {
+    /// One diagnostic per problem.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        match self {
+            CompileError::ParseErrors(errors) => errors.iter().map(Diagnostic::from).collect(),
+            _ => vec![self.into()],
+        }
+    }
+}
+
+impl From<&CompileError> for Diagnostic {
+    /// The diagnostic for `err`; for [`CompileError::ParseErrors`] only the
+    /// first error's. Use [`CompileError::diagnostics`] to get them all.
+    fn from(err: &CompileError) -> Self {
+        let diagnostic = match err {
+            CompileError::Lex(err) => err.into(),
+            CompileError::Parse(err) => err.into(),
+            CompileError::ParseErrors(errors) => (&errors[0]).into(),
+            CompileError::Module(err) => err.into(),
+            CompileError::MissingDeclaration => {
+                Diagnostic::error("expected a `component`, `page` or `server fn` declaration")
+                    .with_note("a `.raven` file must declare something to compile")
+            }
+            CompileError::MissingReturn { component, span } => {
+                Diagnostic::error(format!("component `{}` has no `return`", component))
+                    .with_label(*span, "declared here")
+                    .with_note("a component returns the markup it renders")
+            }
+            CompileError::EmptyReturn(span) => {
+                Diagnostic::error("`return` has no markup to render")
+                    .with_label(*span, "empty `return`")
+            }
+            CompileError::RequiredAfterOptional {
+                param,
+                optional,
+                span,
+            } => Diagnostic::error(format!(
+                "required parameter `{}` follows optional parameter `{}`",
+                param, optional
+            ))
+            .with_label(*span, "required parameter")
+            .with_note(
+                "arguments are passed by position, so only trailing parameters may be left out",
+            ),
+            CompileError::MissingKey {
+                element,
+                span,
+                element_span,
+            } => Diagnostic::error(format!(
+                "`<{}>` in a `for` loop needs a `key` attribute",
+                element
+            ))
+            .with_label(*element_span, "missing `key`")
+            .with_secondary(*span, "rendered once per item of this loop")
+            .with_note("React uses keys to match list items between renders"),
+        };
+        diagnostic.with_code(err.code())
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(err: &LexError) -> Self {
+        let diagnostic = match err {
+            LexError::Invalid(span) => Diagnostic::error("unexpected character")
+                .with_label(*span, "no token starts with this character"),
+            LexError::UnterminatedString(span) => Diagnostic::error("unterminated string literal")
+                .with_label(*span, "this string is never closed"),
+            LexError::UnterminatedTemplate(span) => {
+                Diagnostic::error("unterminated template literal")
+                    .with_label(*span, "this template is never closed")
+            }
+            LexError::UnterminatedComment(span) => Diagnostic::error("unterminated block comment")
+                .with_label(*span, "this comment is never closed"),
+            LexError::UnclosedExpression(span) => Diagnostic::error("unclosed `{` in markup")
+                .with_label(*span, "this `{` is never closed"),
+            LexError::InvalidEscape(span) => Diagnostic::error("invalid escape sequence")
+                .with_label(*span, "not a JavaScript string escape"),
+        };
+        diagnostic.with_code(err.code())
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(err: &ParseError) -> Self {
+        let diagnostic = match err {
+            ParseError::Unexpected {
+                found,
+                span,
+                expected,
+            } => Diagnostic::error(format!("expected {}, found {}", expected, describe(found)))
+                .with_label(*span, format!("expected {}", expected)),
+            ParseError::UnclosedTag(name, span) => {
+                Diagnostic::error(format!("unclosed `<{}>` tag", name))
+                    .with_label(*span, "opened here but never closed")
+            }
+            ParseError::MismatchedClose {
+                expected,
+                found,
+                span,
+            } => Diagnostic::error(format!(
+                "expected `</{}>` but found `</{}>`",
+                expected, found
+            ))
+            .with_label(*span, "mismatched closing tag"),
+            ParseError::UnclosedBlock(span) => {
+                Diagnostic::error("unclosed `{`").with_label(*span, "this `{` is never closed")
+            }
+            ParseError::UnknownDirective { namespace, span } => {
+                Diagnostic::error(format!("unknown directive `{}:`", namespace))
+                    .with_label(*span, "unknown directive")
+                    .with_note("directives are `on:`, `class:` and `bind:`")
+            }
+            ParseError::InvalidBindTarget(span) => {
+                Diagnostic::error("`bind:` needs a state variable name")
+                    .with_label(*span, "not a variable name")
+            }
+            ParseError::ShorthandDirective { name, span } => {
+                Diagnostic::error(format!("`{}` needs a value", name))
+                    .with_label(*span, "the name is not a variable name")
+                    .with_note(format!("write `{}={{...}}`", name))
+            }
+        };
+        diagnostic.with_code(err.code())
+    }
+}
+
+impl From<&ModuleError> for Diagnostic {
+    fn from(err: &ModuleError) -> Self {
+        let diagnostic = match err {
+            ModuleError::Duplicate {
+                name,
+                first,
+                second,
+            } => Diagnostic::error(format!("`{}` is defined more than once", name))
+                .with_label(*second, "redefined here")
+                .with_secondary(*first, "first defined here"),
+            ModuleError::MultiplePages { first, second } => {
+                Diagnostic::error("only one `page` is allowed per file")
+                    .with_label(*second, "second page")
+                    .with_secondary(*first, "first page")
+            }
+        };
+        diagnostic.with_code(err.code())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compile_component;
+
+    fn render(source: &str) -> String {
+        let err = compile_component(source).unwrap_err();
+        Diagnostic::from(&err).render("app.raven", source, false)
+    }
+
+    #[test]
+    fn points_at_the_offending_line_of_the_file() {
+        let source =
+            "component App() {\n  return (\n    <div>\n      <p>hi</span>\n    </div>\n  )\n}\n";
+        assert_eq!(
+            render(source),
+            "error[R0203]: expected `</p>` but found `</span>`\n\
+             \x20--> app.raven:4:14\n\
+             \x20 |\n\
+             4 |       <p>hi</span>\n\
+             \x20 |              ^^^^ mismatched closing tag\n"
+        );
+    }
+
+    #[test]
+    fn separates_distant_labels_and_prints_notes() {
+        let source = "component Card() { return <div /> }\n\n\n\n\n\n\n\n\ncomponent Card() { return <span /> }\n";
+        assert_eq!(
+            render(source),
+            "error[R0301]: `Card` is defined more than once\n\
+             \x20 --> app.raven:10:11\n\
+             \x20  |\n\
+             \x201 | component Card() { return <div /> }\n\
+             \x20  |           ---- first defined here\n\
+             ...\n\
+             10 | component Card() { return <span /> }\n\
+             \x20  |           ^^^^ redefined here\n"
+        );
+        let source = "component List(items: [Todo]) {\n  return <ul>{for item in items { <li>{item}</li> }}</ul>\n}";
+        let rendered = render(source);
+        assert!(
+            rendered
+                .contains("  |                                   ^^^^^^^^^^^^^^^ missing `key`\n"),
+            "{}",
+            rendered
+        );
+        assert!(
+            rendered.ends_with("  = note: React uses keys to match list items between renders\n")
+        );
+    }
+
+    #[test]
+    fn underlines_with_tabs_and_marks_the_end_of_file() {
+        let source = "component App() {\n\treturn <p>{x</p>\n}";
+        let err = compile_component(source).unwrap_err();
+        let diagnostic = Diagnostic::from(&err);
+        let rendered = diagnostic.render("app.raven", source, false);
+        assert!(
+            rendered.starts_with("error[R0204]: unclosed `{`"),
+            "{}",
+            rendered
+        );
+        let underline = rendered
+            .lines()
+            .find(|line| line.contains('^'))
+            .expect("an underline");
+        assert!(underline.starts_with("  | \t"), "{:?}", underline);
+
+        let source = "component App() {\n  return <p>";
+        let rendered = render(source);
+        assert!(rendered.contains("--> app.raven:2:10\n"), "{}", rendered);
+    }
+
+    #[test]
+    fn serializes_to_the_documented_json_schema() {
+        let source = "component Card() { return <div /> }\ncomponent Card() { return <p>é</p> }\n";
+        let err = compile_component(source).unwrap_err();
+        let json = Diagnostic::from(&err).to_json("app.raven", source);
+        assert_eq!(
+            json,
+            json!({
+                "code": "R0301",
+                "severity": "error",
+                "message": "`Card` is defined more than once",
+                "file": "app.raven",
+                "span": {
+                    "byte_start": 46,
+                    "byte_end": 50,
+                    "line_start": 2,
+                    "col_start": 11,
+                    "line_end": 2,
+                    "col_end": 15,
+                },
+                "labels": [
+                    {
+                        "message": "redefined here",
+                        "primary": true,
+                        "span": {
+                            "byte_start": 46,
+                            "byte_end": 50,
+                            "line_start": 2,
+                            "col_start": 11,
+                            "line_end": 2,
+                            "col_end": 15,
+                        },
+                    },
+                    {
+                        "message": "first defined here",
+                        "primary": false,
+                        "span": {
+                            "byte_start": 10,
+                            "byte_end": 14,
+                            "line_start": 1,
+                            "col_start": 11,
+                            "line_end": 1,
+                            "col_end": 15,
+                        },
+                    },
+                ],
+                "notes": [],
+            })
+        );
+
+        let json = Diagnostic::error("missing `return`")
+            .with_code("R0001")
+            .with_note("add one")
+            .to_json("app.raven", "");
+        assert_eq!(json["code"], "R0001");
+        assert_eq!(json["span"], serde_json::Value::Null);
+        assert_eq!(json["labels"], json!([]));
+        assert_eq!(json["notes"], json!(["add one"]));
+    }
+
+    #[test]
+    fn colours_only_when_asked() {
+        let diagnostic = Diagnostic::warning("unused").with_code("R0001");
+        assert_eq!(
+            diagnostic.render("a.raven", "", false),
+            "warning[R0001]: unused\n--> a.raven\n"
+        );
+        let colored = diagnostic.render("a.raven", "", true);
+        assert!(colored.starts_with("\x1b[1;33mwarning[R0001]\x1b[0m"));
+    }
 }
@@ -0,0 +1,626 @@
+//! Long-form explanations of error codes, printed by `raven explain`.
+//!
+//! Every explanation shows an erroneous example followed by a corrected one,
+//! each in a ```` ```raven ```` block. The tests compile both, so the examples
+//! cannot drift from what the compiler actually reports.
+
+/// The explanation of `code`, e.g. `R0203`, ignoring ASCII case.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, text)| *text)
+}
+
+const EXPLANATIONS: &[(&str, &str)] = &[
+    (
+        "R0101",
+        r#"A character that cannot start any token appeared in code.
+
+Outside of strings, comments and markup text, only identifiers, numbers and
+JavaScript operators may appear. A stray `#` or `@` usually comes from a
+copy-paste or an unfinished edit.
+
+Erroneous example:
+
+```raven
+component Price(amount: float) {
+  const total = amount # 1.2
+  return <p>{total}</p>
+}
+```
+
+Corrected:
+
+```raven
+component Price(amount: float) {
+  const total = amount * 1.2
+  return <p>{total}</p>
+}
+```
+"#,
+    ),
+    (
+        "R0102",
+        r#"A string literal is missing its closing quote.
+
+Strings end at the same kind of quote they start with, on the same line. Use
+a `\n` escape or a template literal for text that spans lines. Only
+attribute values inside a tag may contain raw line breaks.
+
+Erroneous example:
+
+```raven
+component Greeting() {
+  const name = "Ada
+  return <p>{name}</p>
+}
+```
+
+Corrected:
+
+```raven
+component Greeting() {
+  const name = "Ada"
+  return <p>{name}</p>
+}
+```
+"#,
+    ),
+    (
+        "R0103",
+        r#"A template literal is missing its closing backtick.
+
+A template starting with `` ` `` runs until the next unescaped backtick,
+including across lines, so a missing one swallows the rest of the file. Every
+`${` placeholder inside it also needs its `}`.
+
+Erroneous example:
+
+```raven
+component Greeting(name: string) {
+  const message = `Hello, ${name}!
+  return <p>{message}</p>
+}
+```
+
+Corrected:
+
+```raven
+component Greeting(name: string) {
+  const message = `Hello, ${name}!`
+  return <p>{message}</p>
+}
+```
+"#,
+    ),
+    (
+        "R0104",
+        r#"A block comment is missing its closing `*/`.
+
+Block comments do not nest, and everything after an unclosed `/*` is part of
+the comment.
+
+Erroneous example:
+
+```raven
+/* Shows the current user
+component Avatar(url: string) {
+  return <img src={url} />
+}
+```
+
+Corrected:
+
+```raven
+/* Shows the current user */
+component Avatar(url: string) {
+  return <img src={url} />
+}
+```
+"#,
+    ),
+    (
+        "R0105",
+        r#"An expression in markup is missing its closing `}`.
+
+A `{` in markup starts an expression that ends at its matching `}`. Without it
+the file ends inside the expression.
+
+Erroneous example:
+
+```raven
+component Field(name: string) {
+  return <input value={name />
+```
+
+Corrected:
+
+```raven
+component Field(name: string) {
+  return <input value={name} />
+}
+```
+"#,
+    ),
+    (
+        "R0106",
+        r#"A string literal contains a backslash escape JavaScript does not accept.
+
+Strings take the same escapes as in JavaScript: `\n`, `\t`, `\r`, `\0`, `\xHH`
+with two hex digits, `\uHHHH` with four, `\u{H...}` with up to six, and a
+backslash before any other character that stands for that character. Octal
+escapes such as `\1` are not allowed.
+
+Erroneous example:
+
+```raven
+component Degrees(value: int) {
+  const unit = "\xB"
+  return <p>{value}{unit}</p>
+}
+```
+
+Corrected:
+
+```raven
+component Degrees(value: int) {
+  const unit = "\xB0"
+  return <p>{value}{unit}</p>
+}
+```
+"#,
+    ),
+    (
+        "R0201",
+        r#"The parser found a token it did not expect here.
+
+The message says what was expected instead, for example a tag name after `<`
+or a value after `=` in an attribute.
+
+Erroneous example:
+
+```raven
+component Title() {
+  return <h1 class=>Welcome</h1>
+}
+```
+
+Corrected:
+
+```raven
+component Title() {
+  return <h1 class="title">Welcome</h1>
+}
+```
+"#,
+    ),
+    (
+        "R0202",
+        r#"An element is never closed.
+
+Every element needs a closing tag, or must close itself with `/>`. An element
+also ends as unclosed when the closing tag of an element around it comes first.
+
+Erroneous example:
+
+```raven
+component Card() {
+  return <div><p>Hello</div>
+}
+```
+
+Corrected:
+
+```raven
+component Card() {
+  return <div><p>Hello</p></div>
+}
+```
+"#,
+    ),
+    (
+        "R0203",
+        r#"A closing tag does not match the element it closes.
+
+Closing tags must name the innermost open element; a fragment `<>` closes with
+`</>`.
+
+Erroneous example:
+
+```raven
+component Note() {
+  return <p>Saved</span>
+}
+```
+
+Corrected:
+
+```raven
+component Note() {
+  return <p>Saved</p>
+}
+```
+"#,
+    ),
+    (
+        "R0204",
+        r#"A `{` is never closed.
+
+This applies to component and function bodies, to `if` and `for` blocks in
+markup, and to `{}` expressions that run into a closing tag.
+
+Erroneous example:
+
+```raven
+component Contact(email: string) {
+  return <span>{email</span>
+}
+```
+
+Corrected:
+
+```raven
+component Contact(email: string) {
+  return <span>{email}</span>
+}
+```
+"#,
+    ),
+    (
+        "R0205",
+        r#"An attribute uses a directive prefix that does not exist.
+
+The directives are `on:` for event handlers, `class:` for toggling a class and
+`bind:` for two-way binding. Any other `name:` prefix is an error.
+
+Erroneous example:
+
+```raven
+component Save(save: fn() -> void) {
+  return <button use:click={save}>Save</button>
+}
+```
+
+Corrected:
+
+```raven
+component Save(save: fn() -> void) {
+  return <button on:click={save}>Save</button>
+}
+```
+"#,
+    ),
+    (
+        "R0206",
+        r#"`bind:` was given something other than a variable name.
+
+A binding writes back to its state variable, so it needs a plain name rather
+than a property access or another expression.
+
+Erroneous example:
+
+```raven
+component Form(form: Form) {
+  return <input bind:value={form.name} />
+}
+```
+
+Corrected:
+
+```raven
+component Form(form: Form) {
+  let name = form.name
+  return <input bind:value={name} />
+}
+```
+"#,
+    ),
+    (
+        "R0207",
+        r#"A `class:` or `bind:` directive without a value has a name that is not a
+variable name.
+
+A directive written without a value reads the variable it is named after, so
+`class:active` means `class:active={active}`. A name with a `-` in it cannot
+be a variable, so the value has to be written out.
+
+Erroneous example:
+
+```raven
+component Item(done: bool) {
+  return <li class:is-done>Task</li>
+}
+```
+
+Corrected:
+
+```raven
+component Item(done: bool) {
+  return <li class:is-done={done}>Task</li>
+}
+```
+"#,
+    ),
+    (
+        "R0301",
+        r#"A name is declared more than once in the same file.
+
+Components, pages, server functions, types and imports share one namespace per
+file.
+
+Erroneous example:
+
+```raven
+component Card() { return <div /> }
+component Card() { return <section /> }
+```
+
+Corrected:
+
+```raven
+component Card() { return <div /> }
+component WideCard() { return <section /> }
+```
+"#,
+    ),
+    (
+        "R0302",
+        r#"A file declares more than one `page`.
+
+Each `.raven` file is routed by its path, so it can hold at most one page. Move
+the others into files of their own, or turn them into components.
+
+Erroneous example:
+
+```raven
+page Home() { return <h1>Home</h1> }
+page About() { return <h1>About</h1> }
+```
+
+Corrected:
+
+```raven
+page Home() { return <h1>Home</h1> }
+component About() { return <h1>About</h1> }
+```
+"#,
+    ),
+    (
+        "R0401",
+        r#"The file declares nothing that compiles to code.
+
+A `.raven` file needs at least one `component`, `page`, `server fn` or `type`;
+imports alone produce no output.
+
+Erroneous example:
+
+```raven
+import { Card } from "./Card"
+```
+
+Corrected:
+
+```raven
+import { Card } from "./Card"
+
+component Featured() {
+  return <Card />
+}
+```
+"#,
+    ),
+    (
+        "R0402",
+        r#"A component has no `return` statement.
+
+A component renders the markup it returns, so its body must end in a `return`.
+
+Erroneous example:
+
+```raven
+component Banner() {
+  const text = "Sale"
+}
+```
+
+Corrected:
+
+```raven
+component Banner() {
+  const text = "Sale"
+  return <p>{text}</p>
+}
+```
+"#,
+    ),
+    (
+        "R0403",
+        r#"A component's `return` has nothing to render.
+
+Return markup, or `null` to render nothing.
+
+Erroneous example:
+
+```raven
+component Empty() {
+  return
+}
+```
+
+Corrected:
+
+```raven
+component Empty() {
+  return null
+}
+```
+"#,
+    ),
+    (
+        "R0404",
+        r#"A required parameter follows an optional one.
+
+Server function arguments and page route segments are passed by position, so
+once a parameter may be left out, every later one must be optional too.
+Component props are passed by name and may come in any order.
+
+Erroneous example:
+
+```raven
+server fn search(query?: string, page: int) {
+  return db.posts.findMany({ where: { query }, skip: page })
+}
+```
+
+Corrected:
+
+```raven
+server fn search(page: int, query?: string) {
+  return db.posts.findMany({ where: { query }, skip: page })
+}
+```
+"#,
+    ),
+    (
+        "R0405",
+        r#"An element rendered by a markup `for` loop has no `key`.
+
+React matches list items between renders by their `key`, so every root element
+of a loop body needs one that is unique among its siblings.
+
+Erroneous example:
+
+```raven
+component List(items: [Todo]) {
+  return <ul>{for item in items { <li>{item.title}</li> }}</ul>
+}
+```
+
+Corrected:
+
+```raven
+component List(items: [Todo]) {
+  return <ul>{for item in items { <li key={item.id}>{item.title}</li> }}</ul>
+}
+```
+"#,
+    ),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::{Span, TokenKind};
+    use crate::{compile_component, CompileError, LexError, ModuleError, ParseError};
+
+    /// The ```` ```raven ```` blocks of an explanation.
+    fn examples(text: &str) -> Vec<&str> {
+        text.split("```raven\n")
+            .skip(1)
+            .map(|block| block.split("```").next().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn examples_fail_with_their_code_and_the_fixes_compile() {
+        for (code, text) in EXPLANATIONS {
+            let examples = examples(text);
+            assert_eq!(
+                examples.len(),
+                2,
+                "{} needs a wrong and a right example",
+                code
+            );
+            match compile_component(examples[0]) {
+                Err(err) => assert_eq!(err.code(), *code, "{}: {}", code, err),
+                Ok(_) => panic!("{}'s erroneous example compiles", code),
+            }
+            if let Err(err) = compile_component(examples[1]) {
+                panic!("{}'s corrected example fails: {}", code, err);
+            }
+        }
+    }
+
+    #[test]
+    fn every_error_code_has_an_explanation() {
+        let span = Span {
+            line: 1,
+            col: 1,
+            offset: 0,
+            len: 0,
+            end_line: 1,
+            end_col: 1,
+        };
+        let errors = [
+            CompileError::Lex(LexError::Invalid(span)),
+            CompileError::Lex(LexError::UnterminatedString(span)),
+            CompileError::Lex(LexError::UnterminatedTemplate(span)),
+            CompileError::Lex(LexError::UnterminatedComment(span)),
+            CompileError::Lex(LexError::UnclosedExpression(span)),
+            CompileError::Lex(LexError::InvalidEscape(span)),
+            CompileError::Parse(ParseError::Unexpected {
+                found: TokenKind::Eof,
+                span,
+                expected: "`}`",
+            }),
+            CompileError::Parse(ParseError::UnclosedTag("p".into(), span)),
+            CompileError::Parse(ParseError::MismatchedClose {
+                expected: "p".into(),
+                found: "b".into(),
+                span,
+            }),
+            CompileError::Parse(ParseError::UnclosedBlock(span)),
+            CompileError::Parse(ParseError::UnknownDirective {
+                namespace: "use".into(),
+                span,
+            }),
+            CompileError::Parse(ParseError::InvalidBindTarget(span)),
+            CompileError::Parse(ParseError::ShorthandDirective {
+                name: "class:is-done".into(),
+                span,
+            }),
+            CompileError::Module(ModuleError::Duplicate {
+                name: "A".into(),
+                first: span,
+                second: span,
+            }),
+            CompileError::Module(ModuleError::MultiplePages {
+                first: span,
+                second: span,
+            }),
+            CompileError::MissingDeclaration,
+            CompileError::MissingReturn {
+                component: "A".into(),
+                span,
+            },
+            CompileError::EmptyReturn(span),
+            CompileError::RequiredAfterOptional {
+                param: "b".into(),
+                optional: "a".into(),
+                span,
+            },
+            CompileError::MissingKey {
+                element: "li".into(),
+                span,
+                element_span: span,
+            },
+        ];
+        let mut codes: Vec<&str> = errors.iter().map(CompileError::code).collect();
+        for code in &codes {
+            assert!(explain(code).is_some(), "{} has no explanation", code);
+        }
+        codes.dedup();
+        assert_eq!(codes.len(), errors.len(), "codes are unique");
+        assert_eq!(
+            codes,
+            EXPLANATIONS
+                .iter()
+                .map(|(code, _)| *code)
+                .collect::<Vec<_>>(),
+            "every explanation belongs to an error"
+        );
+        assert_eq!(explain("r0203"), explain("R0203"));
+        assert_eq!(explain("R9999"), None);
+    }
+}
@@ -1,6 +1,1797 @@
-//! Placeholder module for RavensOne.
+//! The canonical layout of `.raven` files, applied by `raven fmt`.
+//!
+//! A file is parsed and printed back from its syntax tree, so its layout
+//! depends on the code alone. Printing goes through a small document of text,
+//! line breaks and groups in the style of Wadler's "prettier printer": a group
+//! stays on one line when it fits the width and breaks every line it holds
+//! otherwise.
+//!
+//! Comments are not part of the tree. They are collected from between the
+//! tokens and printed again before the item, statement, entry or child they
+//! preceded, or at the end of the line they trailed.
 
-/// Initializes module-specific resources.
-pub fn init() -> &'static str {
-    "initialized"
+use crate::ast::{
+    ArrowBody, Attr, AttrValue, Block, Case, Code, Component, Directive, DirectiveKind, Else, Expr,
+    ExprKind, Field, ForHead, Function, IfStmt, Import, Item, Node, Param, Prop, PropKey, ServerFn,
+    SourceFile, Stmt, Type, TypeDecl, TypeDeclBody, TypeKind,
+};
+use crate::codegen_enhanced::DEFAULT_WIDTH;
+use crate::lexer::collapse_whitespace;
+use crate::token::{Span, Token, TokenKind};
+use crate::CompileError;
+
+/// Columns added per level of nesting.
+const INDENT: usize = 2;
+
+/// Formats a whole `.raven` file. Files with syntax errors are refused with
+/// the errors [`compile_component`](crate::compile_component) would report.
+pub fn format_source(src: &str) -> Result<String, CompileError> {
+    let (tokens, file) = crate::parse_file(src)?;
+    let mut formatter = Formatter {
+        src,
+        comments: comments(src, &tokens),
+        next: 0,
+    };
+    let doc = formatter.file(&file);
+    let text = render(&doc, DEFAULT_WIDTH);
+    let text = text.trim_end();
+    Ok(if text.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", text)
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Comment {
+    start: usize,
+    /// Where the comment ends, trailing whitespace excluded.
+    end: usize,
+    /// A `//` comment, which needs a line break after it.
+    line: bool,
+    /// Whether code comes before the comment on its line and the comment
+    /// does not lead into the token after it.
+    trailing: bool,
+    /// The offsets of `{` and past `}` around a markup expression that holds
+    /// nothing but comments, as in `{/* note */}`.
+    braces: Option<(usize, usize)>,
+}
+
+/// The comments in `src`, which can only sit in the gaps between its tokens.
+fn comments(src: &str, tokens: &[Token]) -> Vec<Comment> {
+    let mut comments = Vec::new();
+    let mut gap = 0;
+    for (idx, token) in tokens.iter().enumerate() {
+        let first = comments.len();
+        while let Some(found) = src[gap..token.span.offset].find('/') {
+            let start = gap + found;
+            let rest = &src[start..token.span.offset];
+            let (line, len) = if rest.starts_with("//") {
+                (true, rest.find('\n').unwrap_or(rest.len()))
+            } else {
+                (false, rest.find("*/").map_or(rest.len(), |end| end + 2))
+            };
+            let line_start = src[..start].rfind('\n').map_or(0, |newline| newline + 1);
+            // `/* note */ value` belongs to `value`, wherever it is moved, unless
+            // the comment only sits before a closing delimiter.
+            let after = &rest[len..];
+            let leads = !line
+                && after.trim().is_empty()
+                && !after.contains('\n')
+                && !matches!(
+                    token.kind,
+                    TokenKind::Comma
+                        | TokenKind::Semi
+                        | TokenKind::RParen
+                        | TokenKind::RBracket
+                        | TokenKind::RBrace
+                        | TokenKind::Eof
+                );
+            comments.push(Comment {
+                start,
+                end: start + rest[..len].trim_end().len(),
+                line,
+                trailing: !leads && !src[line_start..start].trim().is_empty(),
+                braces: None,
+            });
+            gap = start + len;
+        }
+        if idx > 0 && tokens[idx - 1].kind == TokenKind::LBrace && token.kind == TokenKind::RBrace {
+            let braces = (tokens[idx - 1].span.offset, token.span.end_offset());
+            for comment in &mut comments[first..] {
+                comment.braces = Some(braces);
+            }
+        }
+        gap = token.span.end_offset();
+    }
+    comments
+}
+
+struct Formatter<'a> {
+    src: &'a str,
+    comments: Vec<Comment>,
+    /// The first comment not printed yet.
+    next: usize,
+}
+
+/// One child of an element, with what its neighbours need to know about it.
+struct Piece {
+    doc: Doc,
+    /// Text, which is never rewrapped.
+    text: bool,
+    /// Text starting with a space, which a line break before would remove.
+    space_before: bool,
+    /// Text ending with a space.
+    space_after: bool,
+    /// A `//` comment, which has to stand on a line of its own.
+    own_line: bool,
+}
+
+impl Piece {
+    fn new(doc: Doc) -> Self {
+        Piece {
+            doc,
+            text: false,
+            space_before: false,
+            space_after: false,
+            own_line: false,
+        }
+    }
+}
+
+impl<'a> Formatter<'a> {
+    fn file(&mut self, file: &SourceFile) -> Doc {
+        self.lines(
+            &file.items,
+            self.src.len(),
+            |item| item_span(item).offset,
+            |before, after| !matches!((before, after), (Item::Import(_), Item::Import(_))),
+            Self::item,
+        )
+    }
+
+    fn item(&mut self, item: &Item) -> Doc {
+        match item {
+            Item::Component(component) => self.component("component", component),
+            Item::Page(page) => self.component("page", page),
+            Item::ServerFn(func) => self.server_fn(func),
+            Item::Import(import) => self.import(import),
+            Item::TypeDecl(decl) => self.type_decl(decl),
+        }
+    }
+
+    fn import(&mut self, import: &Import) -> Doc {
+        let mut parts = vec![text("import ")];
+        if let Some(default) = &import.default {
+            parts.push(text(&default.name));
+            if !import.names.is_empty() {
+                parts.push(text(", "));
+            }
+        }
+        if import.default.is_none() || !import.names.is_empty() {
+            let after = import.names.last().or(import.default.as_ref());
+            let close =
+                self.closing(after.map_or(import.span.offset, |name| name.span.end_offset()));
+            parts.push(self.delimited(
+                ("{", "}"),
+                true,
+                &import.names,
+                close,
+                |name| name.span.offset,
+                |_, name| text(&name.name),
+            ));
+        }
+        parts.push(text(" from "));
+        parts.push(text(self.slice(import.source_span)));
+        concat(parts)
+    }
+
+    fn type_decl(&mut self, decl: &TypeDecl) -> Doc {
+        let head = text(format!("type {}", decl.name.name));
+        match &decl.body {
+            TypeDeclBody::Alias(ty) => concat(vec![head, text(" = "), text(type_text(ty))]),
+            TypeDeclBody::Fields(fields) => {
+                let close = decl.span.end_offset() - 1;
+                if fields.is_empty() && !self.pending(close) {
+                    return concat(vec![head, text(" {}")]);
+                }
+                let last = fields.len().saturating_sub(1);
+                let body = self.lines(
+                    fields,
+                    close,
+                    |field| field.span.offset,
+                    |_, _| false,
+                    |_, field: &Field| {
+                        let comma = if std::ptr::eq(field, &fields[last]) {
+                            ""
+                        } else {
+                            ","
+                        };
+                        text(format!(
+                            "{}: {}{}",
+                            field.name.name,
+                            type_text(&field.ty),
+                            comma
+                        ))
+                    },
+                );
+                braced_lines(concat(vec![head, text(" ")]), body)
+            }
+        }
+    }
+
+    fn component(&mut self, keyword: &str, component: &Component) -> Doc {
+        let params = self.params(&component.params, component.name.span.end_offset());
+        concat(vec![
+            text(format!("{} {}", keyword, component.name.name)),
+            params,
+            text(" "),
+            self.block(&component.body),
+        ])
+    }
+
+    fn server_fn(&mut self, func: &ServerFn) -> Doc {
+        let mut parts = vec![
+            text(format!("server fn {}", func.name.name)),
+            self.params(&func.params, func.name.span.end_offset()),
+        ];
+        if let Some(ret) = &func.ret {
+            parts.push(text(format!(" -> {}", type_text(ret))));
+        }
+        parts.push(text(" "));
+        parts.push(self.block(&func.body));
+        concat(parts)
+    }
+
+    /// A parameter list whose `(` follows `after`.
+    fn params(&mut self, params: &[Param], after: usize) -> Doc {
+        let close = self.closing(
+            params
+                .last()
+                .map_or(self.next_char(after) + 1, |param| param.span.end_offset()),
+        );
+        self.delimited(
+            ("(", ")"),
+            false,
+            params,
+            close,
+            |param| param.span.offset,
+            Self::param,
+        )
+    }
+
+    fn param(&mut self, param: &Param) -> Doc {
+        let mut parts = vec![text(&param.name.name)];
+        if param.optional.is_some() {
+            parts.push(text("?"));
+        }
+        if let Some(ty) = &param.ty {
+            parts.push(text(format!(": {}", type_text(ty))));
+        }
+        if let Some(default) = &param.default {
+            parts.push(text(" = "));
+            parts.push(self.expr(default));
+        }
+        concat(parts)
+    }
+
+    fn block(&mut self, block: &Block) -> Doc {
+        let close = block.span.end_offset() - 1;
+        if block.stmts.is_empty() && !self.pending(close) {
+            return text("{}");
+        }
+        let body = self.lines(
+            &block.stmts,
+            close,
+            |stmt| stmt.span().offset,
+            |_, _| false,
+            Self::stmt,
+        );
+        braced_lines(text(""), body)
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) -> Doc {
+        let doc = match stmt {
+            Stmt::VarDecl(decl) => {
+                let mut parts = vec![text(format!("{} ", decl.keyword.name))];
+                parts.push(self.expr(&decl.target));
+                if let Some(ty) = &decl.ty {
+                    parts.push(text(format!(": {}", type_text(ty))));
+                }
+                if let Some(value) = &decl.value {
+                    parts.push(text(" = "));
+                    parts.push(self.expr(value));
+                }
+                concat(parts)
+            }
+            Stmt::Expr(expr) => self.expr(expr),
+            Stmt::Return(ret) => match &ret.value {
+                None => text("return"),
+                Some(value) => {
+                    let markup = match &value.kind {
+                        ExprKind::Markup(node) => Some(node),
+                        ExprKind::Paren(inner) => match &inner.kind {
+                            ExprKind::Markup(node) => Some(node),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    // The parentheses around returned markup are layout, not
+                    // code: they appear exactly when it spans lines.
+                    let value = match markup {
+                        Some(node) => group(vec![
+                            if_break("(", ""),
+                            indent(vec![Doc::SoftLine, self.node(node)]),
+                            Doc::SoftLine,
+                            if_break(")", ""),
+                        ]),
+                        None => self.expr(value),
+                    };
+                    concat(vec![text("return "), value])
+                }
+            },
+            Stmt::Code(code) => self.code(code),
+            Stmt::Throw(throw) => concat(vec![text("throw "), self.expr(&throw.value)]),
+            Stmt::If(stmt) => self.if_stmt(stmt),
+            Stmt::For(stmt) => {
+                let mut parts = vec![text("for (")];
+                match &stmt.head {
+                    ForHead::Steps { init, test, update } => {
+                        if let Some(init) = init {
+                            parts.push(self.stmt(init));
+                        }
+                        parts.push(text(";"));
+                        if let Some(test) = test {
+                            parts.push(text(" "));
+                            parts.push(self.expr(test));
+                        }
+                        parts.push(text(";"));
+                        if let Some(update) = update {
+                            parts.push(text(" "));
+                            parts.push(self.expr(update));
+                        }
+                    }
+                    ForHead::Each {
+                        keyword,
+                        binding,
+                        of,
+                        iterable,
+                    } => {
+                        if let Some(keyword) = keyword {
+                            parts.push(text(format!("{} ", keyword.name)));
+                        }
+                        parts.push(self.expr(binding));
+                        parts.push(text(if *of { " of " } else { " in " }));
+                        parts.push(self.expr(iterable));
+                    }
+                }
+                parts.push(text(") "));
+                parts.push(self.body(&stmt.body));
+                concat(parts)
+            }
+            Stmt::While(stmt) if stmt.is_do => concat(vec![
+                text("do "),
+                self.body(&stmt.body),
+                text(" while ("),
+                self.expr(&stmt.cond),
+                text(")"),
+            ]),
+            Stmt::While(stmt) => concat(vec![
+                text("while ("),
+                self.expr(&stmt.cond),
+                text(") "),
+                self.body(&stmt.body),
+            ]),
+            Stmt::Switch(stmt) => {
+                let head = concat(vec![text("switch ("), self.expr(&stmt.value), text(") ")]);
+                let close = stmt.span.end_offset() - 1;
+                if stmt.cases.is_empty() && !self.pending(close) {
+                    concat(vec![head, text("{}")])
+                } else {
+                    let cases = self.lines(
+                        &stmt.cases,
+                        close,
+                        |case| case.span.offset,
+                        |_, _| false,
+                        Self::case,
+                    );
+                    braced_lines(head, cases)
+                }
+            }
+            Stmt::Try(stmt) => {
+                let mut parts = vec![text("try "), self.block(&stmt.block)];
+                if let Some(catch) = &stmt.catch {
+                    parts.push(text(" catch "));
+                    if let Some(param) = &catch.param {
+                        parts.push(text("("));
+                        parts.push(self.expr(param));
+                        parts.push(text(") "));
+                    }
+                    parts.push(self.block(&catch.body));
+                }
+                if let Some(finally) = &stmt.finally {
+                    parts.push(text(" finally "));
+                    parts.push(self.block(finally));
+                }
+                concat(parts)
+            }
+        };
+        // Statements are printed without semicolons, so one that could
+        // continue the line before it gets a leading one.
+        let start = self.src[stmt.span().offset..].chars().next();
+        if matches!(start, Some('+' | '-' | '~' | '<')) {
+            concat(vec![text(";"), doc])
+        } else {
+            doc
+        }
+    }
+
+    /// `if (cond) { ... }` and any `else` after it.
+    fn if_stmt(&mut self, stmt: &IfStmt) -> Doc {
+        let mut parts = vec![
+            text("if ("),
+            self.expr(&stmt.cond),
+            text(") "),
+            self.body(&stmt.then),
+        ];
+        match &stmt.otherwise {
+            Some(Else::If(next)) => {
+                parts.push(text(" else "));
+                parts.push(self.if_stmt(next));
+            }
+            Some(Else::Block(block)) => {
+                parts.push(text(" else "));
+                parts.push(self.body(block));
+            }
+            None => {}
+        }
+        concat(parts)
+    }
+
+    /// The body of an `if`, `for` or `while`, braced even where the source
+    /// left out the braces around a single statement.
+    fn body(&mut self, block: &Block) -> Doc {
+        if self.src[block.span.offset..].starts_with('{') {
+            return self.block(block);
+        }
+        let body = self.lines(
+            &block.stmts,
+            block.span.end_offset(),
+            |stmt| stmt.span().offset,
+            |_, _| false,
+            Self::stmt,
+        );
+        braced_lines(text(""), body)
+    }
+
+    /// `case value:` or `default:`, with the statements under it indented.
+    fn case(&mut self, case: &Case) -> Doc {
+        let head = match &case.test {
+            Some(test) => concat(vec![text("case "), self.expr(test), text(":")]),
+            None => text("default:"),
+        };
+        if case.body.is_empty() {
+            return head;
+        }
+        let body = self.lines(
+            &case.body,
+            case.span.end_offset(),
+            |stmt| stmt.span().offset,
+            |_, _| false,
+            Self::stmt,
+        );
+        concat(vec![head, indent(vec![Doc::HardLine, body])])
+    }
+
+    /// Code the compiler passes through, reindented to its new position
+    /// unless a template literal makes its line breaks significant.
+    fn code(&mut self, code: &Code) -> Doc {
+        self.skip(code.span.end_offset());
+        if !code.text.contains('\n') || code.text.contains('`') {
+            return text(&code.text);
+        }
+        let line_start = self.src[..code.span.offset]
+            .rfind('\n')
+            .map_or(0, |newline| newline + 1);
+        let line = &self.src[line_start..];
+        let base = line.len() - line.trim_start_matches([' ', '\t']).len();
+        let mut parts = Vec::new();
+        for (idx, line) in code.text.lines().enumerate() {
+            if idx > 0 {
+                parts.push(Doc::HardLine);
+                let indent = line.len() - line.trim_start_matches([' ', '\t']).len();
+                parts.push(text(&line[indent.min(base)..]));
+            } else {
+                parts.push(text(line));
+            }
+        }
+        concat(parts)
+    }
+
+    fn expr(&mut self, expr: &Expr) -> Doc {
+        match &expr.kind {
+            ExprKind::Ident(name) | ExprKind::Number(name) => text(name),
+            ExprKind::Str(_) | ExprKind::Template(_) | ExprKind::Regex(_) => {
+                text(self.slice(expr.span))
+            }
+            ExprKind::Bool(value) => text(value.to_string()),
+            ExprKind::Null => text("null"),
+            ExprKind::Array(items) => self.delimited(
+                ("[", "]"),
+                false,
+                items,
+                expr.span.end_offset() - 1,
+                |item| item.span.offset,
+                Self::expr,
+            ),
+            ExprKind::Object(props) => self.delimited(
+                ("{", "}"),
+                true,
+                props,
+                expr.span.end_offset() - 1,
+                prop_start,
+                Self::prop,
+            ),
+            ExprKind::Spread(inner) => concat(vec![text("..."), self.expr(inner)]),
+            ExprKind::Member {
+                object,
+                property,
+                optional,
+            } => concat(vec![
+                self.expr(object),
+                text(if *optional { "?." } else { "." }),
+                text(&property.name),
+            ]),
+            ExprKind::Index {
+                object,
+                index,
+                optional,
+            } => concat(vec![
+                self.expr(object),
+                text(if *optional { "?.[" } else { "[" }),
+                self.expr(index),
+                text("]"),
+            ]),
+            ExprKind::Call {
+                callee,
+                args,
+                optional,
+            } => concat(vec![
+                self.expr(callee),
+                text(if *optional { "?." } else { "" }),
+                self.args(args, expr.span.end_offset() - 1),
+            ]),
+            ExprKind::Arrow {
+                is_async,
+                params,
+                ret,
+                body,
+            } => {
+                let mut parts = Vec::new();
+                let mut start = expr.span.offset;
+                if *is_async {
+                    parts.push(text("async "));
+                    start = self.next_char(start + "async".len());
+                }
+                parts.push(match params.last() {
+                    Some(param) if !self.src[start..].starts_with('(') => {
+                        let close = param.span.end_offset();
+                        self.delimited(
+                            ("(", ")"),
+                            false,
+                            params,
+                            close,
+                            |param| param.span.offset,
+                            Self::expr,
+                        )
+                    }
+                    _ => self.fn_params(params, start, ret.as_deref()),
+                });
+                parts.push(text(" => "));
+                parts.push(match body {
+                    ArrowBody::Block(block) => self.block(block),
+                    ArrowBody::Expr(body) => self.expr(body),
+                });
+                concat(parts)
+            }
+            ExprKind::Function(function) => {
+                let Function {
+                    is_async,
+                    name,
+                    params,
+                    ret,
+                    body,
+                } = &**function;
+                let mut parts = Vec::new();
+                let mut start = expr.span.offset;
+                if *is_async {
+                    parts.push(text("async "));
+                    start = self.next_char(start + "async".len());
+                }
+                parts.push(text("function "));
+                start += "function".len();
+                if let Some(name) = name {
+                    parts.push(text(&name.name));
+                    start = name.span.end_offset();
+                }
+                let open = self.next_char(start);
+                parts.push(self.fn_params(params, open, ret.as_deref()));
+                parts.push(text(" "));
+                parts.push(self.block(body));
+                concat(parts)
+            }
+            ExprKind::Param {
+                target,
+                optional,
+                ty,
+            } => {
+                let mut parts = vec![self.expr(target)];
+                if *optional {
+                    parts.push(text("?"));
+                }
+                if let Some(ty) = ty {
+                    parts.push(text(format!(": {}", ty)));
+                }
+                concat(parts)
+            }
+            ExprKind::TypeArgs { callee, types } => {
+                concat(vec![self.expr(callee), text(format!("<{}>", types))])
+            }
+            ExprKind::As { value, ty } => {
+                concat(vec![self.expr(value), text(format!(" as {}", ty))])
+            }
+            ExprKind::Unary { op, operand } => {
+                // `- -x` must not run together into `--x`.
+                let doubled = match &operand.kind {
+                    ExprKind::Unary { op: inner, .. } => {
+                        matches!(op.as_str(), "+" | "-") && inner.starts_with(op.as_str())
+                    }
+                    _ => false,
+                };
+                let space = op.starts_with(|c: char| c.is_ascii_alphabetic()) || doubled;
+                concat(vec![
+                    text(op),
+                    text(if space { " " } else { "" }),
+                    self.expr(operand),
+                ])
+            }
+            ExprKind::Postfix { op, operand } => concat(vec![self.expr(operand), text(op)]),
+            ExprKind::Binary { op, left, right }
+            | ExprKind::Assign {
+                op,
+                target: left,
+                value: right,
+            } => concat(vec![
+                self.expr(left),
+                text(format!(" {} ", op)),
+                self.expr(right),
+            ]),
+            ExprKind::Conditional {
+                test,
+                consequent,
+                alternate,
+            } => group(vec![
+                self.expr(test),
+                indent(vec![
+                    Doc::Line,
+                    text("? "),
+                    self.expr(consequent),
+                    Doc::Line,
+                    text(": "),
+                    self.expr(alternate),
+                ]),
+            ]),
+            ExprKind::Paren(inner) => match inner.kind {
+                ExprKind::Markup(_) => group(vec![
+                    text("("),
+                    indent(vec![Doc::SoftLine, self.expr(inner)]),
+                    Doc::SoftLine,
+                    text(")"),
+                ]),
+                _ => concat(vec![text("("), self.expr(inner), text(")")]),
+            },
+            ExprKind::Markup(node) => self.node(node),
+        }
+    }
+
+    /// A parameter list whose `(` is at `open`, and its return type.
+    fn fn_params(&mut self, params: &[Expr], open: usize, ret: Option<&str>) -> Doc {
+        let close = self.closing(
+            params
+                .last()
+                .map_or(open + 1, |param| param.span.end_offset()),
+        );
+        let mut parts = vec![self.delimited(
+            ("(", ")"),
+            false,
+            params,
+            close,
+            |param| param.span.offset,
+            Self::expr,
+        )];
+        if let Some(ret) = ret {
+            parts.push(text(format!(": {}", ret)));
+        }
+        concat(parts)
+    }
+
+    /// Call arguments. A trailing function, object or array argument hugs
+    /// the parentheses and breaks on its own, as in `list.map((x) => {`.
+    fn args(&mut self, args: &[Expr], close: usize) -> Doc {
+        if let Some((last, rest)) = args.split_last() {
+            let block_like = |arg: &Expr| match &arg.kind {
+                ExprKind::Arrow { .. } | ExprKind::Function(_) => true,
+                ExprKind::Object(items) => !items.is_empty(),
+                ExprKind::Array(items) => !items.is_empty(),
+                _ => false,
+            };
+            let start = args[0].span.offset;
+            let has_comments = self.comments[self.next..]
+                .iter()
+                .any(|comment| comment.start >= start && comment.start < close);
+            if block_like(last) && !rest.iter().any(block_like) && !has_comments {
+                let mut parts = vec![text("(")];
+                for arg in rest {
+                    parts.push(self.expr(arg));
+                    parts.push(text(", "));
+                }
+                parts.push(self.expr(last));
+                parts.push(text(")"));
+                return concat(parts);
+            }
+        }
+        self.delimited(
+            ("(", ")"),
+            false,
+            args,
+            close,
+            |arg| arg.span.offset,
+            Self::expr,
+        )
+    }
+
+    fn prop(&mut self, prop: &Prop) -> Doc {
+        match prop {
+            Prop::KeyValue { key, value } => {
+                let key = match key {
+                    PropKey::Ident(ident) => text(&ident.name),
+                    PropKey::Str(value) => text(quote(value)),
+                    PropKey::Number(value) => text(value),
+                    PropKey::Computed(key) => concat(vec![text("["), self.expr(key), text("]")]),
+                };
+                concat(vec![key, text(": "), self.expr(value)])
+            }
+            Prop::Shorthand(ident) => text(&ident.name),
+            Prop::Spread(value) => concat(vec![text("..."), self.expr(value)]),
+        }
+    }
+
+    /// Markup written as an element's child or in expression position.
+    fn node(&mut self, node: &Node) -> Doc {
+        match node {
+            Node::Element {
+                name,
+                attrs,
+                children,
+                span,
+            } => self.element(name, attrs, children, *span),
+            Node::Fragment { children, span } => self.element("", &[], children, *span),
+            Node::Text { span, .. } => text(collapse_whitespace(self.slice(*span))),
+            Node::Expr(expr) => self.braced(expr),
+            Node::If { .. } => group(vec![text("{"), self.if_chain(node), text("}")]),
+            Node::For {
+                binding,
+                iterable,
+                body,
+                ..
+            } => {
+                let head = vec![
+                    text("{for "),
+                    self.expr(binding),
+                    text(" in "),
+                    self.expr(iterable),
+                    text(" "),
+                ];
+                let (body, _) = self.markup_body(body, iterable.span.end_offset());
+                group([head, vec![body, text("}")]].concat())
+            }
+            Node::Error { span } => text(self.slice(*span)),
+        }
+    }
+
+    /// An element, or a fragment when `name` is empty.
+    fn element(&mut self, name: &str, attrs: &[Attr], children: &[Node], span: Span) -> Doc {
+        let end = span.end_offset();
+        if !name.is_empty() && children.is_empty() && self.src[..end].ends_with("/>") {
+            return self.open_tag(name, attrs, true);
+        }
+        let open = self.open_tag(name, attrs, false);
+        let close = self.src[..end].rfind("</").unwrap_or(end);
+        let mut pieces = Vec::new();
+        for child in children {
+            self.content_comments(self.node_start(child), &mut pieces);
+            pieces.push(match child {
+                Node::Text { span, .. } => {
+                    let text = collapse_whitespace(self.slice(*span));
+                    Piece {
+                        text: true,
+                        space_before: text.starts_with(' '),
+                        space_after: text.ends_with(' '),
+                        own_line: false,
+                        doc: Doc::Text(text),
+                    }
+                }
+                _ => Piece::new(self.node(child)),
+            });
+        }
+        self.content_comments(close, &mut pieces);
+        let close = text(format!("</{}>", name));
+        if pieces.is_empty() {
+            return concat(vec![open, close]);
+        }
+        // Whitespace next to a line break does not render, so children only
+        // go on lines of their own where no space touches the break. Like
+        // Prettier, nested elements and several expressions always do.
+        let nested = children
+            .iter()
+            .any(|child| matches!(child, Node::Element { .. } | Node::Fragment { .. }));
+        let expressions = children
+            .iter()
+            .filter(|child| matches!(child, Node::Expr(_)))
+            .count();
+        let mut inner = Vec::new();
+        if nested || expressions > 1 {
+            inner.push(Doc::BreakParent);
+        }
+        let mut separators = vec![boundary(None, pieces.first())];
+        separators.extend(
+            pieces
+                .windows(2)
+                .map(|pair| boundary(Some(&pair[0]), Some(&pair[1]))),
+        );
+        separators.push(boundary(pieces.last(), None));
+        let glued = |separator: &Doc| matches!(separator, Doc::Text(_));
+        for (idx, piece) in pieces.into_iter().enumerate() {
+            inner.push(separators[idx].clone());
+            // An element joined to text by a space stays on the text's line.
+            if !piece.text && (glued(&separators[idx]) || glued(&separators[idx + 1])) {
+                inner.push(Doc::Flat(Box::new(piece.doc)));
+            } else {
+                inner.push(piece.doc);
+            }
+        }
+        let last = separators.pop().unwrap_or(Doc::SoftLine);
+        group(vec![open, indent(inner), last, close])
+    }
+
+    fn open_tag(&mut self, name: &str, attrs: &[Attr], self_closing: bool) -> Doc {
+        if attrs.is_empty() {
+            return text(if self_closing {
+                format!("<{} />", name)
+            } else {
+                format!("<{}>", name)
+            });
+        }
+        let mut parts = Vec::new();
+        for attr in attrs {
+            parts.push(Doc::Line);
+            parts.push(self.attr(attr));
+        }
+        let end = if self_closing {
+            vec![Doc::Line, text("/>")]
+        } else {
+            vec![Doc::SoftLine, text(">")]
+        };
+        group([vec![text(format!("<{}", name)), indent(parts)], end].concat())
+    }
+
+    fn attr(&mut self, attr: &Attr) -> Doc {
+        match attr {
+            Attr::Named {
+                name,
+                value: AttrValue::Bool,
+                ..
+            } => text(name),
+            Attr::Named {
+                name,
+                value: AttrValue::Str(_),
+                span,
+            } => {
+                let written = self.slice(*span);
+                let value = written[written.find('=').map_or(0, |eq| eq + 1)..].trim_start();
+                text(format!("{}={}", name, value))
+            }
+            Attr::Named {
+                name,
+                value: AttrValue::Expr(value),
+                ..
+            } => concat(vec![text(format!("{}=", name)), self.braced(value)]),
+            Attr::Spread { value, .. } => concat(vec![text("{..."), self.expr(value), text("}")]),
+            Attr::Directive(Directive {
+                kind, name, value, ..
+            }) => {
+                let namespace = match kind {
+                    DirectiveKind::On => "on",
+                    DirectiveKind::Class => "class",
+                    DirectiveKind::Bind => "bind",
+                };
+                let head = text(format!("{}:{}", namespace, name.name));
+                match &value.kind {
+                    ExprKind::Ident(value) if *kind != DirectiveKind::On && *value == name.name => {
+                        head
+                    }
+                    _ => concat(vec![head, text("="), self.braced(value)]),
+                }
+            }
+        }
+    }
+
+    /// `{expr}` in markup, with any comments written inside the braces.
+    fn braced(&mut self, expr: &Expr) -> Doc {
+        let mut parts = vec![text("{")];
+        parts.extend(self.inline_leading(expr.span.offset));
+        parts.push(self.expr(expr));
+        let close = self.next_char(expr.span.end_offset());
+        while let Some(comment) = self.take(close) {
+            parts.push(text(format!(" {}", self.comment_text(comment))));
+            if comment.line {
+                parts.push(Doc::HardLine);
+            }
+        }
+        parts.push(text("}"));
+        concat(parts)
+    }
+
+    /// `if cond { ... } else ...` without the braces around it.
+    fn if_chain(&mut self, node: &Node) -> Doc {
+        let Node::If {
+            cond,
+            then,
+            otherwise,
+            ..
+        } = node
+        else {
+            return self.node(node);
+        };
+        let mut parts = vec![text("if "), self.expr(cond), text(" ")];
+        let (body, end) = self.markup_body(then, cond.span.end_offset());
+        parts.push(body);
+        if let Some(otherwise) = otherwise {
+            parts.push(text(" else "));
+            match otherwise.as_slice() {
+                [inner @ Node::If { span, .. }]
+                    if self.src[..span.offset].trim_end().ends_with("else") =>
+                {
+                    parts.push(self.if_chain(inner));
+                }
+                nodes => parts.push(self.markup_body(nodes, end).0),
+            }
+        }
+        concat(parts)
+    }
+
+    /// The `{ ... }` body of a markup `if` or `for` whose `{` follows
+    /// `after`, and the offset past its `}`. Its lines break with the group
+    /// around the whole `if` or `for`.
+    fn markup_body(&mut self, nodes: &[Node], after: usize) -> (Doc, usize) {
+        let open = self.next_char(after);
+        let close = self.next_char(nodes.last().map_or(open + 1, |node| self.node_end(node)));
+        if nodes.is_empty() && !self.pending(close) {
+            return (text("{}"), close + 1);
+        }
+        let mut entries = Vec::new();
+        for node in nodes {
+            self.body_comments(self.node_start(node), &mut entries);
+            entries.push(match node {
+                // Text in a body is a string literal.
+                Node::Text { span, .. } => text(self.slice(*span)),
+                _ => self.node(node),
+            });
+        }
+        self.body_comments(close, &mut entries);
+        let mut inner = Vec::new();
+        for entry in entries {
+            inner.push(Doc::Line);
+            inner.push(entry);
+        }
+        let doc = concat(vec![text("{"), indent(inner), Doc::Line, text("}")]);
+        (doc, close + 1)
+    }
+
+    fn body_comments(&mut self, before: usize, entries: &mut Vec<Doc>) {
+        while let Some(comment) = self.take(before) {
+            entries.push(match comment.braces {
+                Some((_, close)) => self.comment_braces(comment, close),
+                None if comment.line => {
+                    concat(vec![text(self.comment_text(comment)), Doc::BreakParent])
+                }
+                None => text(self.comment_text(comment)),
+            });
+        }
+    }
+
+    /// Comments among an element's children, before offset `before`.
+    fn content_comments(&mut self, before: usize, pieces: &mut Vec<Piece>) {
+        while let Some(comment) = self.take(before) {
+            let text = self.comment_text(comment);
+            pieces.push(match comment.braces {
+                Some((_, close)) => Piece::new(self.comment_braces(comment, close)),
+                None if comment.line => Piece {
+                    own_line: true,
+                    ..Piece::new(Doc::Text(text.to_string()))
+                },
+                // Only braces keep a block comment from reading as text.
+                None => Piece::new(Doc::Text(format!("{{{}}}", text))),
+            });
+        }
+    }
+
+    /// `{/* note */}`: `first` and the other comments in braces that hold
+    /// nothing else and end at `close`.
+    fn comment_braces(&mut self, first: Comment, close: usize) -> Doc {
+        let mut parts = vec![text("{"), text(self.comment_text(first))];
+        let mut last = first;
+        while let Some(comment) = self.take(close) {
+            parts.push(Doc::HardLine);
+            parts.push(text(self.comment_text(comment)));
+            last = comment;
+        }
+        if last.line {
+            parts.push(Doc::HardLine);
+        }
+        parts.push(text("}"));
+        concat(parts)
+    }
+
+    /// Where a child starts, including the `{` of an expression.
+    fn node_start(&self, node: &Node) -> usize {
+        match node {
+            Node::Expr(_) | Node::If { .. } | Node::For { .. } => {
+                self.previous_char(node.span().offset)
+            }
+            _ => node.span().offset,
+        }
+    }
+
+    /// Where a child ends, including the `}` of an expression.
+    fn node_end(&self, node: &Node) -> usize {
+        match node {
+            Node::Expr(_) | Node::If { .. } | Node::For { .. } => {
+                self.next_char(node.span().end_offset()) + 1
+            }
+            _ => node.span().end_offset(),
+        }
+    }
+
+    /// Lays out `entries` one per line, each after the comments before it,
+    /// and keeps single blank lines between them. `blank(a, b)` forces one
+    /// between neighbours. The comments left before `end` close the list.
+    fn lines<T>(
+        &mut self,
+        entries: &[T],
+        end: usize,
+        start: impl Fn(&T) -> usize,
+        blank: impl Fn(&T, &T) -> bool,
+        print: impl Fn(&mut Self, &T) -> Doc,
+    ) -> Doc {
+        let mut parts = Vec::new();
+        // Where the last printed entry or comment ended.
+        let mut cursor: Option<usize> = None;
+        for (idx, entry) in entries.iter().enumerate() {
+            let offset = start(entry);
+            let mut forced = idx > 0 && blank(&entries[idx - 1], entry);
+            while let Some(comment) = self.take(offset) {
+                self.separate(&mut parts, cursor, comment.start, forced);
+                forced = false;
+                parts.push(text(self.comment_text(comment)));
+                cursor = Some(comment.end);
+            }
+            self.separate(&mut parts, cursor, offset, forced);
+            parts.push(print(self, entry));
+            let next = entries.get(idx + 1).map_or(end, &start);
+            parts.extend(self.trailing(next));
+            cursor = Some(self.previous_char(next) + 1);
+        }
+        while let Some(comment) = self.take(end) {
+            self.separate(&mut parts, cursor, comment.start, false);
+            parts.push(text(self.comment_text(comment)));
+            cursor = Some(comment.end);
+        }
+        concat(parts)
+    }
+
+    /// Starts a new line of a [`Self::lines`] list before `offset`, after a
+    /// blank one if the source had one there.
+    fn separate(&self, parts: &mut Vec<Doc>, cursor: Option<usize>, offset: usize, forced: bool) {
+        if let Some(cursor) = cursor {
+            parts.push(Doc::HardLine);
+            if forced || self.src[cursor..offset].matches('\n').count() > 1 {
+                parts.push(Doc::HardLine);
+            }
+        }
+    }
+
+    /// `open`, the entries separated by commas and `close`: on one line if
+    /// they fit, otherwise one entry per line with a trailing comma. The
+    /// `close` delimiter is at offset `end`.
+    fn delimited<T>(
+        &mut self,
+        (open, close): (&str, &str),
+        spaced: bool,
+        entries: &[T],
+        end: usize,
+        start: impl Fn(&T) -> usize,
+        print: impl Fn(&mut Self, &T) -> Doc,
+    ) -> Doc {
+        if entries.is_empty() && !self.pending(end) {
+            return text(format!("{}{}", open, close));
+        }
+        let mut parts = Vec::new();
+        for (idx, entry) in entries.iter().enumerate() {
+            if idx > 0 {
+                parts.push(text(","));
+                parts.push(Doc::Line);
+            }
+            parts.extend(self.inline_leading(start(entry)));
+            parts.push(print(self, entry));
+            let next = entries.get(idx + 1).map_or(end, &start);
+            parts.extend(self.trailing(next));
+        }
+        if !entries.is_empty() {
+            parts.push(if_break(",", ""));
+        }
+        let mut first = entries.is_empty();
+        while let Some(comment) = self.take(end) {
+            if !first {
+                parts.push(Doc::HardLine);
+            }
+            first = false;
+            parts.push(text(self.comment_text(comment)));
+            if comment.line {
+                parts.push(Doc::BreakParent);
+            }
+        }
+        let line = if spaced { Doc::Line } else { Doc::SoftLine };
+        group(vec![
+            text(open),
+            indent([vec![line.clone()], parts].concat()),
+            line,
+            text(close),
+        ])
+    }
+
+    /// Comments before `before`, each followed by a space or, for a `//`
+    /// comment, a line break.
+    fn inline_leading(&mut self, before: usize) -> Vec<Doc> {
+        let mut parts = Vec::new();
+        while let Some(comment) = self.take(before) {
+            parts.push(text(self.comment_text(comment)));
+            parts.push(if comment.line {
+                Doc::HardLine
+            } else {
+                text(" ")
+            });
+        }
+        parts
+    }
+
+    /// Comments that followed code on their line, before `before`; they stay
+    /// at the end of the line.
+    fn trailing(&mut self, before: usize) -> Vec<Doc> {
+        let mut parts = Vec::new();
+        while self
+            .comments
+            .get(self.next)
+            .is_some_and(|comment| comment.trailing && comment.start < before)
+        {
+            let comment = self.comments[self.next];
+            self.next += 1;
+            let text = format!(" {}", self.comment_text(comment));
+            if comment.line {
+                parts.push(Doc::LineSuffix(text));
+                parts.push(Doc::BreakParent);
+            } else {
+                parts.push(Doc::Text(text));
+            }
+        }
+        parts
+    }
+
+    /// Whether a comment before `before` has yet to be printed.
+    fn pending(&self, before: usize) -> bool {
+        self.comments
+            .get(self.next)
+            .is_some_and(|comment| comment.start < before)
+    }
+
+    /// The next comment if it starts before `before`.
+    fn take(&mut self, before: usize) -> Option<Comment> {
+        let comment = *self.comments.get(self.next)?;
+        if comment.start >= before {
+            return None;
+        }
+        self.next += 1;
+        Some(comment)
+    }
+
+    /// Drops the comments before `before`, which were printed as part of
+    /// verbatim source.
+    fn skip(&mut self, before: usize) {
+        while self.take(before).is_some() {}
+    }
+
+    fn comment_text(&self, comment: Comment) -> &'a str {
+        &self.src[comment.start..comment.end]
+    }
+
+    /// The offset of the first character at or after `offset` that is
+    /// neither whitespace nor part of a comment.
+    fn next_char(&self, mut offset: usize) -> usize {
+        loop {
+            let rest = &self.src[offset..];
+            let skipped = offset + rest.len() - rest.trim_start().len();
+            match self
+                .comments
+                .iter()
+                .find(|comment| comment.start == skipped)
+            {
+                Some(comment) => offset = comment.end,
+                None => return skipped,
+            }
+        }
+    }
+
+    /// The offset of the last character before `offset` that is neither
+    /// whitespace nor part of a comment.
+    fn previous_char(&self, mut offset: usize) -> usize {
+        loop {
+            let end = self.src[..offset].trim_end().len();
+            match self.comments.iter().find(|comment| comment.end == end) {
+                Some(comment) => offset = comment.start,
+                None => return end.saturating_sub(1),
+            }
+        }
+    }
+
+    /// The offset of the bracket closing a list whose last entry ends at
+    /// `after`, past a trailing comma.
+    fn closing(&self, after: usize) -> usize {
+        let next = self.next_char(after);
+        if self.src[next..].starts_with(',') {
+            self.next_char(next + 1)
+        } else {
+            next
+        }
+    }
+
+    fn slice(&self, span: Span) -> &'a str {
+        &self.src[span.offset..span.end_offset()]
+    }
+}
+
+/// `head {`, the lines of `body` indented, and `}`.
+fn braced_lines(head: Doc, body: Doc) -> Doc {
+    concat(vec![
+        head,
+        text("{"),
+        indent(vec![Doc::HardLine, body]),
+        Doc::HardLine,
+        text("}"),
+    ])
+}
+
+/// The separator between two children of an element, or between a child and
+/// the element's tags when one side is `None`.
+fn boundary(before: Option<&Piece>, after: Option<&Piece>) -> Doc {
+    let glued = match (before, after) {
+        _ if before.is_some_and(|piece| piece.own_line)
+            || after.is_some_and(|piece| piece.own_line) =>
+        {
+            return Doc::HardLine
+        }
+        (Some(before), Some(after)) => before.space_after || after.space_before,
+        (Some(before), None) => before.space_after,
+        (None, Some(after)) => after.space_before,
+        (None, None) => false,
+    };
+    if glued {
+        text("")
+    } else {
+        Doc::SoftLine
+    }
+}
+
+fn item_span(item: &Item) -> Span {
+    match item {
+        Item::Component(component) | Item::Page(component) => component.span,
+        Item::ServerFn(func) => func.span,
+        Item::Import(import) => import.span,
+        Item::TypeDecl(decl) => decl.span,
+    }
+}
+
+/// Where an object entry starts, as far as the tree records it.
+fn prop_start(prop: &Prop) -> usize {
+    match prop {
+        Prop::KeyValue {
+            key: PropKey::Ident(ident),
+            ..
+        }
+        | Prop::Shorthand(ident) => ident.span.offset,
+        Prop::KeyValue {
+            key: PropKey::Computed(key),
+            ..
+        } => key.span.offset,
+        Prop::KeyValue { value, .. } | Prop::Spread(value) => value.span.offset,
+    }
+}
+
+fn type_text(ty: &Type) -> String {
+    let list = |types: &[Type]| types.iter().map(type_text).collect::<Vec<_>>().join(", ");
+    match &ty.kind {
+        TypeKind::Named { name, args } if args.is_empty() => name.clone(),
+        TypeKind::Named { name, args } => format!("{}<{}>", name, list(args)),
+        TypeKind::Array(inner) => format!("[{}]", type_text(inner)),
+        TypeKind::Optional(inner) => format!("{}?", type_text(inner)),
+        TypeKind::Function { params, ret } => match ret {
+            Some(ret) => format!("fn({}) -> {}", list(params), type_text(ret)),
+            None => format!("fn({})", list(params)),
+        },
+    }
+}
+
+/// A string literal whose value is `value`, using the lexer's escapes.
+fn quote(value: &str) -> String {
+    let mut out = String::from('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            other => out.push(other),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// The layout document [`render`] prints.
+#[derive(Debug, Clone)]
+enum Doc {
+    Text(String),
+    /// A space, or a line break in a broken group.
+    Line,
+    /// Nothing, or a line break in a broken group.
+    SoftLine,
+    /// A line break that also breaks every group around it.
+    HardLine,
+    /// Breaks every group around it without printing anything.
+    BreakParent,
+    Concat(Vec<Doc>),
+    Indent(Box<Doc>),
+    /// Contents laid out flat if they fit, and broken otherwise; the flag is
+    /// set when they hold a forced break.
+    Group(Box<Doc>, bool),
+    /// The first document in a broken group, the second in a flat one.
+    IfBreak(Box<Doc>, Box<Doc>),
+    /// Text held back until the end of the line, for trailing comments.
+    LineSuffix(String),
+    /// Contents laid out flat unless they hold a forced break.
+    Flat(Box<Doc>),
+}
+
+impl Doc {
+    fn forces_break(&self) -> bool {
+        match self {
+            Doc::HardLine | Doc::BreakParent => true,
+            Doc::Text(text) => text.contains('\n'),
+            Doc::Line | Doc::SoftLine | Doc::LineSuffix(_) => false,
+            Doc::Concat(docs) => docs.iter().any(Doc::forces_break),
+            Doc::Indent(doc) | Doc::Flat(doc) => doc.forces_break(),
+            Doc::Group(_, broken) => *broken,
+            Doc::IfBreak(broken, flat) => broken.forces_break() || flat.forces_break(),
+        }
+    }
+}
+
+fn text(value: impl Into<String>) -> Doc {
+    Doc::Text(value.into())
+}
+
+fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+
+fn indent(docs: Vec<Doc>) -> Doc {
+    Doc::Indent(Box::new(Doc::Concat(docs)))
+}
+
+fn group(docs: Vec<Doc>) -> Doc {
+    let doc = Doc::Concat(docs);
+    let broken = doc.forces_break();
+    Doc::Group(Box::new(doc), broken)
+}
+
+fn if_break(broken: &str, flat: &str) -> Doc {
+    Doc::IfBreak(Box::new(text(broken)), Box::new(text(flat)))
+}
+
+/// Prints `doc`, breaking the groups that do not fit in `width` columns.
+fn render(doc: &Doc, width: usize) -> String {
+    let mut out = String::new();
+    let mut suffix = String::new();
+    let mut column = 0;
+    // Indentation, whether the enclosing group is broken, and the document.
+    let mut stack = vec![(0, true, doc)];
+    while let Some((indentation, broken, doc)) = stack.pop() {
+        match doc {
+            Doc::Text(text) => {
+                out.push_str(text);
+                column = match text.rfind('\n') {
+                    Some(newline) => text[newline + 1..].chars().count(),
+                    None => column + text.chars().count(),
+                };
+            }
+            Doc::Line if !broken => {
+                out.push(' ');
+                column += 1;
+            }
+            Doc::SoftLine if !broken => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => {
+                out.push_str(&suffix);
+                suffix.clear();
+                out.truncate(out.trim_end_matches(' ').len());
+                out.push('\n');
+                out.push_str(&" ".repeat(indentation));
+                column = indentation;
+            }
+            Doc::BreakParent => {}
+            Doc::Concat(docs) => {
+                stack.extend(docs.iter().rev().map(|doc| (indentation, broken, doc)));
+            }
+            Doc::Indent(doc) => stack.push((indentation + INDENT, broken, doc)),
+            // Groups inside a flat one stay flat.
+            Doc::Group(doc, forced) => {
+                let flat =
+                    !forced && (!broken || fits(doc, &stack, width as isize - column as isize));
+                stack.push((indentation, !flat, doc));
+            }
+            Doc::Flat(doc) => stack.push((indentation, doc.forces_break(), doc)),
+            Doc::IfBreak(when_broken, when_flat) => {
+                stack.push((
+                    indentation,
+                    broken,
+                    if broken { when_broken } else { when_flat },
+                ));
+            }
+            Doc::LineSuffix(text) => suffix.push_str(text),
+        }
+    }
+    out.push_str(&suffix);
+    out
+}
+
+/// Whether `doc` laid out flat, and what follows it up to the next line
+/// break, fits in `remaining` columns.
+fn fits(doc: &Doc, rest: &[(usize, bool, &Doc)], mut remaining: isize) -> bool {
+    let mut flat = vec![(false, doc)];
+    let mut rest = rest.iter().rev();
+    while remaining >= 0 {
+        let (broken, doc) = match flat.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some(&(_, broken, doc)) => (broken, doc),
+                None => return true,
+            },
+        };
+        match doc {
+            Doc::Text(text) => match text.find('\n') {
+                Some(newline) => return remaining >= text[..newline].chars().count() as isize,
+                None => remaining -= text.chars().count() as isize,
+            },
+            Doc::Line if !broken => remaining -= 1,
+            Doc::SoftLine if !broken => {}
+            Doc::Line | Doc::SoftLine | Doc::HardLine => return true,
+            Doc::BreakParent | Doc::LineSuffix(_) => {}
+            Doc::Concat(docs) => flat.extend(docs.iter().rev().map(|doc| (broken, doc))),
+            Doc::Indent(doc) => flat.push((broken, doc)),
+            Doc::Group(doc, forced) => flat.push((*forced || broken, doc)),
+            Doc::Flat(doc) => flat.push((doc.forces_break(), doc)),
+            Doc::IfBreak(when_broken, when_flat) => {
+                flat.push((broken, if broken { when_broken } else { when_flat }))
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formatted(src: &str) -> String {
+        let once = format_source(src).expect("source formats");
+        assert_eq!(
+            format_source(&once).expect("output formats"),
+            once,
+            "formatting is idempotent"
+        );
+        once
+    }
+
+    #[test]
+    fn lays_out_declarations_and_parameters() {
+        let src = r#"import Layout,{Card,   Button} from "./ui"
+type Todo {id: uuid; title: string, done: bool}
+server fn save(todo: Todo, retries?: int) -> Todo { return db.todo.upsert({ where: { id: todo.id }, create: todo, update: todo }) }
+component Row(todo: Todo, onToggle: fn(Todo) -> void, highlighted: bool = false) { return <li>{todo.title}</li> }
+"#;
+        assert_eq!(
+            formatted(src),
+            r#"import Layout, { Card, Button } from "./ui"
+
+type Todo {
+  id: uuid,
+  title: string,
+  done: bool
+}
+
+server fn save(todo: Todo, retries?: int) -> Todo {
+  return db.todo.upsert({ where: { id: todo.id }, create: todo, update: todo })
+}
+
+component Row(
+  todo: Todo,
+  onToggle: fn(Todo) -> void,
+  highlighted: bool = false,
+) {
+  return <li>{todo.title}</li>
+}
+"#
+        );
+    }
+
+    #[test]
+    fn wraps_attributes_and_children_that_do_not_fit() {
+        let src = r#"component Card(user: User) {
+  return <article class="profile-card" data-user-id={user.id} aria-labelledby="profile-name"><h2>{user.name}</h2></article>
+}
+"#;
+        assert_eq!(
+            formatted(src),
+            r#"component Card(user: User) {
+  return (
+    <article
+      class="profile-card"
+      data-user-id={user.id}
+      aria-labelledby="profile-name"
+    >
+      <h2>{user.name}</h2>
+    </article>
+  )
+}
+"#
+        );
+    }
+
+    #[test]
+    fn formats_expressions_and_statements() {
+        let src = r#"component List(items: [Todo]) {
+  let count = items.filter(item => !item.done).length;  let label = count == 1 ? "item" : "items"
+  const toggle = async (event) => { await save(event.target.value); count++ }
+  const n = - -count
+  return <ul class:empty={count == 0} class:active={active} bind:value={value}>{for item in items { <li key={item.id}>{item.title}</li> }}</ul>
+}
+"#;
+        assert_eq!(
+            formatted(src),
+            r#"component List(items: [Todo]) {
+  let count = items.filter((item) => !item.done).length
+  let label = count == 1 ? "item" : "items"
+  const toggle = async (event) => {
+    await save(event.target.value)
+    count++
+  }
+  const n = - -count
+  return (
+    <ul class:empty={count == 0} class:active bind:value>
+      {for item in items { <li key={item.id}>{item.title}</li> }}
+    </ul>
+  )
+}
+"#
+        );
+    }
+
+    #[test]
+    fn formats_typescript_expressions() {
+        let canonical = r#"component Field(ref: Element, maybe: string) {
+  const [s, setS] = useState<string>("")
+  const ok = /[a/]+/g.test(s)
+  async function load() {}
+  const f = function () {
+    return 1
+  }
+  const el = ref as HTMLElement
+  const m = maybe!
+  const id = (x: number): number => x
+  return <input value={s} />
+}
+"#;
+        assert_eq!(formatted(canonical), canonical);
+        let written = canonical
+            .replace("useState<string>(\"\")", "useState<string>( \"\" )")
+            .replace("function () {\n    return 1\n  }", "function(){ return 1 }")
+            .replace("(x: number): number", "( x: number ):number");
+        assert_eq!(formatted(&written), canonical);
+    }
+
+    #[test]
+    fn formats_control_flow_statements_from_the_tree() {
+        let canonical = r#"component Summary(items: [Item]) {
+  let total = 0
+  if (items.length == 0) {
+    return <p>Nothing yet</p>
+  } else if (items.length == 1) {
+    return <p>One item</p>
+  }
+  for (let i = 0; i < items.length; i++) {
+    total += items[i].price
+  }
+  for (const item of items) {
+    check(item)
+  }
+  while (total > 100) {
+    total -= 100
+  }
+  do {
+    total++
+  } while (total < 10)
+  switch (total) {
+    case 0:
+      throw new Error("empty")
+    default:
+      log(total)
+  }
+  try {
+    save(total)
+  } catch (err) {
+    report(err)
+  } finally {
+    done()
+  }
+  return <p>{total}</p>
+}
+"#;
+        let written = r#"component Summary(items: [Item]) {
+      let total = 0
+   if (items.length==0)
+ { return <p>Nothing yet</p> }
+        else if(items.length == 1) return <p>One item</p>;
+  for(let i = 0;i < items.length;i++) total += items[i].price
+     for (const item of items) { check(item) }
+  while(total>100){total -= 100}
+  do { total++ }   while (total < 10);
+  switch(total){ case 0: throw new Error("empty")
+  default: log(total) }
+    try { save(total) }
+      catch(err) { report(err) } finally { done() }
+  return <p>{total}</p>
+}
+"#;
+        assert_eq!(formatted(canonical), canonical);
+        assert_eq!(formatted(written), canonical);
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let src = r#"// Rows of the table
+component Row(todo: Todo /* the row */) {
+  const done = todo.done // cached
+
+  return <tr>
+    // the title cell
+    <td>{todo.title}</td>
+    {/* no actions yet */}
+  </tr>
+}
+"#;
+        assert_eq!(
+            formatted(src),
+            r#"// Rows of the table
+component Row(todo: Todo /* the row */) {
+  const done = todo.done // cached
+
+  return (
+    <tr>
+      // the title cell
+      <td>{todo.title}</td>
+      {/* no actions yet */}
+    </tr>
+  )
+}
+"#
+        );
+    }
+
+    #[test]
+    fn keeps_comments_next_to_the_arguments_they_precede() {
+        let src = r#"component Sum() {
+  const a = add(1, /* inline */ 2)
+  const b = add(/* first */ 1, 2 /* after */, 3)
+  const c = add(
+    1, // one
+    2,
+  )
+  return <p>{a + b + c}</p>
+}
+"#;
+        assert_eq!(
+            formatted(src),
+            r#"component Sum() {
+  const a = add(1, /* inline */ 2)
+  const b = add(/* first */ 1, 2 /* after */, 3)
+  const c = add(
+    1, // one
+    2,
+  )
+  return <p>{a + b + c}</p>
+}
+"#
+        );
+    }
+
+    #[test]
+    fn never_breaks_lines_where_whitespace_renders() {
+        let src = r#"component Line() {
+  return <p>Hello <b>world</b>, and <i>welcome</i> back to the site where all is text</p>
+}
+component Code() {
+  return <code>  two  spaces  </code>
+}
+"#;
+        assert_eq!(
+            formatted(src),
+            r#"component Line() {
+  return (
+    <p>
+      Hello <b>world</b>
+      , and <i>welcome</i> back to the site where all is text
+    </p>
+  )
+}
+
+component Code() {
+  return <code>  two  spaces  </code>
+}
+"#
+        );
+    }
+
+    #[test]
+    fn puts_each_child_of_a_broken_element_on_its_own_line() {
+        let src = r#"component Row(v: string) {
+  return <div class="a-class-name-long-enough-to-break-the-line">{/* c */}text<span>{v}</span></div>
+}
+"#;
+        let expected = r#"component Row(v: string) {
+  return (
+    <div class="a-class-name-long-enough-to-break-the-line">
+      {/* c */}
+      text
+      <span>{v}</span>
+    </div>
+  )
+}
+"#;
+        assert_eq!(formatted(src), expected);
+        assert_eq!(formatted(expected), expected);
+    }
+
+    #[test]
+    fn refuses_files_with_syntax_errors() {
+        let err = format_source("component A() {\n  return <p>hi</span>\n}\n").unwrap_err();
+        assert_eq!(err.code(), "R0203");
+    }
 }
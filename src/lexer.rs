@@ -5,8 +5,80 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum LexError {
-    #[error("invalid token at {0:?}")]
+    #[error("unexpected character at {0:?}")]
     Invalid(Span),
+    #[error("unterminated string literal at {0:?}")]
+    UnterminatedString(Span),
+    #[error("unterminated template literal at {0:?}")]
+    UnterminatedTemplate(Span),
+    #[error("unterminated block comment at {0:?}")]
+    UnterminatedComment(Span),
+    #[error("unclosed expression `{{` at {0:?}")]
+    UnclosedExpression(Span),
+    #[error("invalid escape sequence at {0:?}")]
+    InvalidEscape(Span),
+}
+
+impl LexError {
+    /// The stable code `raven explain` knows this error by.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexError::Invalid(_) => "R0101",
+            LexError::UnterminatedString(_) => "R0102",
+            LexError::UnterminatedTemplate(_) => "R0103",
+            LexError::UnterminatedComment(_) => "R0104",
+            LexError::UnclosedExpression(_) => "R0105",
+            LexError::InvalidEscape(_) => "R0106",
+        }
+    }
+}
+
+/// What the lexer is currently looking at. Source files start in `Code`; a `<` in
+/// expression position pushes a `Markup` frame that pops again once its root
+/// element is closed, and a `{` in markup pushes a `Code` frame that pops at
+/// its matching `}`.
+#[derive(Copy, Clone, Debug)]
+enum Mode {
+    Code {
+        /// Braces opened in this frame and not yet closed.
+        braces: usize,
+        /// The `{` that started an embedded expression; `None` at the top
+        /// level of a file.
+        open: Option<Span>,
+    },
+    Markup {
+        depth: usize,
+        root: bool,
+        in_tag: bool,  // <-- track whether we're inside a <...> tag
+        closing: bool, // the current tag is a `</...>`
+    },
+}
+
+/// Operators recognised in code, longest first so that `===` wins over `==`.
+const OPERATORS: &[&str] = &[
+    "===", "!==", "...", "**", "&&", "||", "??", "?.", "==", "!=", "<=", ">=", "=>", "->", "++",
+    "--", "+=", "-=", "*=", "/=", "%=", "+", "-", "*", "/", "%", "!", "&", "|", "^", "~", "<", ">",
+    "=", "?", ":", ".", ",", ";", "(", ")", "[", "]",
+];
+
+/// Keywords after which a `<` starts markup rather than a comparison.
+const EXPRESSION_KEYWORDS: &[&str] = &["return", "else", "in", "of", "await", "yield"];
+
+/// Whether an expression may begin after a token of kind `previous`, rather
+/// than the token ending a value that an operator would continue.
+pub(crate) fn may_start_expression(previous: Option<&TokenKind>) -> bool {
+    match previous {
+        Some(TokenKind::Ident(word)) => EXPRESSION_KEYWORDS.contains(&word.as_str()),
+        Some(
+            TokenKind::Number(_)
+            | TokenKind::Str(_)
+            | TokenKind::Template(_)
+            | TokenKind::Regex(_)
+            | TokenKind::RParen
+            | TokenKind::RBracket,
+        ) => false,
+        _ => true,
+    }
 }
 
 struct Lexer<'a> {
@@ -14,17 +86,17 @@ struct Lexer<'a> {
     index: usize,
     line: usize,
     col: usize,
-    in_tag: bool, // <-- track whether we're inside a <...> tag
+    modes: Vec<Mode>,
 }
 
 impl<'a> Lexer<'a> {
-    fn new(input: &'a str) -> Self {
+    fn new(input: &'a str, mode: Mode) -> Self {
         Self {
             input,
             index: 0,
             line: 1,
             col: 1,
-            in_tag: false,
+            modes: vec![mode],
         }
     }
 
@@ -32,54 +104,30 @@ impl<'a> Lexer<'a> {
         let mut tokens = Vec::new();
 
         while let Some(ch) = self.peek_char() {
-            match ch {
-                '<' => tokens.push(self.consume_single(TokenKind::LAngle)),
-                '>' => tokens.push(self.consume_single(TokenKind::RAngle)),
-                '/' => {
-                    if self.peek_next_char() == Some('/') && !self.in_tag {
-                        // Only treat // as a line comment when not inside a tag
-                        self.consume_comment();
-                        continue;
-                    }
-                    tokens.push(self.consume_single(TokenKind::Slash));
-                }
-                '=' => tokens.push(self.consume_single(TokenKind::Equals)),
-                '{' => {
-                    let lbrace = self.consume_single(TokenKind::LBrace);
-                    tokens.push(lbrace);
-                    self.consume_expression(&mut tokens)?;
-                }
-                '}' => {
-                    // stray closing brace; parser will validate
-                    tokens.push(self.consume_single(TokenKind::RBrace));
-                }
-                '"' => {
-                    let (tok, _) = self.consume_string()?;
-                    tokens.push(tok);
-                }
-                c if c.is_whitespace() => {
-                    self.consume_whitespace();
-                }
-                c if is_ident_start(c) && self.in_tag => {
-                    // Identifiers (tag/attr names) only *inside* a tag
-                    tokens.push(self.consume_ident());
-                }
-                _ => {
-                    // Everything else outside tags is text
-                    let (tok, produced) = self.consume_text();
-                    if produced {
-                        tokens.push(tok);
-                    }
-                }
+            match self.mode() {
+                Mode::Code { .. } => self.lex_code(ch, &mut tokens)?,
+                Mode::Markup { in_tag: true, .. } => self.lex_tag(ch, &mut tokens)?,
+                Mode::Markup { .. } => self.lex_content(ch, &mut tokens)?,
             }
         }
 
+        // An expression frame still open at EOF never saw its closing `}`.
+        let unclosed = self.modes.iter().rev().find_map(|mode| match mode {
+            Mode::Code { open, .. } => *open,
+            Mode::Markup { .. } => None,
+        });
+        if let Some(open) = unclosed {
+            return Err(LexError::UnclosedExpression(open));
+        }
+
         // EOF
         let eof_span = Span {
             line: self.line,
             col: self.col,
             offset: self.index,
             len: 0,
+            end_line: self.line,
+            end_col: self.col,
         };
         tokens.push(Token {
             kind: TokenKind::Eof,
@@ -88,19 +136,203 @@ impl<'a> Lexer<'a> {
         Ok(tokens)
     }
 
+    fn lex_code(&mut self, ch: char, tokens: &mut Vec<Token>) -> Result<(), LexError> {
+        match ch {
+            c if c.is_whitespace() => self.consume_whitespace(),
+            '/' if self.peek_next_char() == Some('/') => self.consume_comment(),
+            '/' if self.peek_next_char() == Some('*') => self.consume_block_comment()?,
+            '"' | '\'' => {
+                let (tok, _) = self.consume_string(ch, false)?;
+                tokens.push(tok);
+            }
+            '`' => tokens.push(self.consume_template()?),
+            '/' if starts_regex(tokens.last().map(|t| &t.kind)) => {
+                match regex_len(&self.input[self.index..]) {
+                    Some(len) => tokens.push(self.consume_regex(len)),
+                    None => tokens.push(self.consume_operator()?),
+                }
+            }
+            '{' => {
+                if let Some(Mode::Code { braces, .. }) = self.modes.last_mut() {
+                    *braces += 1;
+                }
+                tokens.push(self.consume_single(TokenKind::LBrace));
+            }
+            '}' => {
+                let finished = match self.modes.last_mut() {
+                    Some(Mode::Code { braces: 0, open }) => open.is_some(),
+                    Some(Mode::Code { braces, .. }) => {
+                        *braces -= 1;
+                        false
+                    }
+                    _ => false,
+                };
+                if finished {
+                    self.modes.pop();
+                }
+                tokens.push(self.consume_single(TokenKind::RBrace));
+            }
+            // A closing tag cannot appear in code, so an expression in markup
+            // that reaches one is missing its `}`. End the expression there
+            // and let the parser report it, rather than lexing the rest of the
+            // file as code.
+            '<' if self.peek_next_char() == Some('/')
+                && matches!(
+                    self.mode(),
+                    Mode::Code {
+                        braces: 0,
+                        open: Some(_)
+                    }
+                ) =>
+            {
+                self.modes.pop();
+            }
+            '<' if self.starts_markup(tokens) => {
+                let tok = self.consume_single(TokenKind::LAngle);
+                self.modes.push(Mode::Markup {
+                    depth: 0,
+                    root: false,
+                    in_tag: true,
+                    closing: false,
+                });
+                tokens.push(tok);
+            }
+            c if is_ident_start(c) || c == '$' => tokens.push(self.consume_code_ident()),
+            c if c.is_ascii_digit() => tokens.push(self.consume_number()),
+            _ => tokens.push(self.consume_operator()?),
+        }
+        Ok(())
+    }
+
+    fn lex_tag(&mut self, ch: char, tokens: &mut Vec<Token>) -> Result<(), LexError> {
+        match ch {
+            '<' => tokens.push(self.consume_single(TokenKind::LAngle)),
+            '>' => tokens.push(self.consume_single(TokenKind::RAngle)),
+            '/' => tokens.push(self.consume_single(TokenKind::Slash)),
+            '=' => tokens.push(self.consume_single(TokenKind::Equals)),
+            // namespaced attributes such as `on:click`
+            ':' => tokens.push(self.consume_single(TokenKind::Colon)),
+            '{' => self.open_expression(tokens),
+            '}' => {
+                // stray closing brace; parser will validate
+                tokens.push(self.consume_single(TokenKind::RBrace));
+            }
+            '"' => {
+                // Attribute values may span lines, as in HTML.
+                let (tok, _) = self.consume_string('"', true)?;
+                tokens.push(tok);
+            }
+            c if c.is_whitespace() => self.consume_whitespace(),
+            c if is_ident_start(c) => {
+                // Identifiers (tag/attr names) only *inside* a tag
+                tokens.push(self.consume_ident());
+            }
+            _ => {
+                let (tok, produced) = self.consume_text();
+                if produced {
+                    tokens.push(tok);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn lex_content(&mut self, ch: char, tokens: &mut Vec<Token>) -> Result<(), LexError> {
+        match ch {
+            '<' => tokens.push(self.consume_single(TokenKind::LAngle)),
+            '/' if self.peek_next_char() == Some('/') => self.consume_comment(),
+            '{' => self.open_expression(tokens),
+            '}' => tokens.push(self.consume_single(TokenKind::RBrace)),
+            // Whitespace only matters inside an element.
+            c if c.is_whitespace() && self.markup_depth() == 0 => self.consume_whitespace(),
+            _ => {
+                // Everything else between tags is text, quotes and whitespace
+                // included
+                let (tok, produced) = self.consume_text();
+                if produced {
+                    tokens.push(tok);
+                }
+            }
+        }
+        Ok(())
+    }
+
     fn consume_single(&mut self, kind: TokenKind) -> Token {
         let mark = self.mark();
         self.advance_char();
         // Update tag state when we see < or >
         match kind {
-            TokenKind::LAngle => self.in_tag = true,
-            TokenKind::RAngle => self.in_tag = false,
+            TokenKind::LAngle => self.open_tag(),
+            TokenKind::RAngle => self.close_tag(),
             _ => {}
         }
         let span = self.span_from(mark);
         Token { kind, span }
     }
 
+    /// Starts a `{ ... }` expression in markup; its contents are lexed as code.
+    fn open_expression(&mut self, tokens: &mut Vec<Token>) {
+        let lbrace = self.consume_single(TokenKind::LBrace);
+        self.modes.push(Mode::Code {
+            braces: 0,
+            open: Some(lbrace.span),
+        });
+        tokens.push(lbrace);
+    }
+
+    /// Elements open in the current markup frame, or zero outside markup.
+    fn markup_depth(&self) -> usize {
+        match self.mode() {
+            Mode::Markup { depth, .. } => depth,
+            Mode::Code { .. } => 0,
+        }
+    }
+
+    fn open_tag(&mut self) {
+        let closing = self.input[self.index..].trim_start().starts_with('/');
+        if let Some(Mode::Markup {
+            in_tag, closing: c, ..
+        }) = self.modes.last_mut()
+        {
+            *in_tag = true;
+            *c = closing;
+        }
+    }
+
+    fn close_tag(&mut self) {
+        let self_closing = self.input[..self.index - 1].trim_end().ends_with('/');
+        let finished = match self.modes.last_mut() {
+            Some(Mode::Markup {
+                depth,
+                root,
+                in_tag,
+                closing,
+            }) => {
+                *in_tag = false;
+                if *closing {
+                    *depth = depth.saturating_sub(1);
+                } else if !self_closing {
+                    *depth += 1;
+                }
+                !*root && *depth == 0
+            }
+            _ => false,
+        };
+        if finished {
+            self.modes.pop();
+        }
+    }
+
+    /// Decides whether a `<` in code opens markup: it must be followed by a tag
+    /// name (or `>` for a fragment) and sit where an expression may begin.
+    fn starts_markup(&self, tokens: &[Token]) -> bool {
+        let next = self.peek_next_char();
+        if !matches!(next, Some(c) if is_ident_start(c) || c == '>') {
+            return false;
+        }
+        may_start_expression(tokens.last().map(|t| &t.kind))
+    }
+
     fn consume_ident(&mut self) -> Token {
         let mark = self.mark();
         // first char already validated by caller
@@ -120,42 +352,109 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn consume_string(&mut self) -> Result<(Token, String), LexError> {
+    fn consume_code_ident(&mut self) -> Token {
+        let mark = self.mark();
+        self.advance_char();
+        while let Some(ch) = self.peek_char() {
+            if ch.is_ascii_alphanumeric() || ch == '_' || ch == '$' {
+                self.advance_char();
+            } else {
+                break;
+            }
+        }
+        let span = self.span_from(mark);
+        let text = &self.input[mark.index..self.index];
+        Token {
+            kind: TokenKind::Ident(text.to_string()),
+            span,
+        }
+    }
+
+    fn consume_number(&mut self) -> Token {
+        let mark = self.mark();
+        while let Some(ch) = self.peek_char() {
+            let fraction =
+                ch == '.' && matches!(self.peek_next_char(), Some(c) if c.is_ascii_digit());
+            if ch.is_ascii_alphanumeric() || ch == '_' || fraction {
+                self.advance_char();
+            } else {
+                break;
+            }
+        }
+        let span = self.span_from(mark);
+        let text = &self.input[mark.index..self.index];
+        Token {
+            kind: TokenKind::Number(text.to_string()),
+            span,
+        }
+    }
+
+    fn consume_operator(&mut self) -> Result<Token, LexError> {
+        let mark = self.mark();
+        let rest = &self.input[self.index..];
+        let op = OPERATORS
+            .iter()
+            .copied()
+            .find(|op| rest.starts_with(op))
+            .ok_or_else(|| {
+                let len = rest.chars().next().map_or(0, char::len_utf8);
+                LexError::Invalid(Span {
+                    line: mark.line,
+                    col: mark.col,
+                    offset: mark.index,
+                    len,
+                    end_line: mark.line,
+                    end_col: mark.col + 1,
+                })
+            })?;
+        for _ in op.chars() {
+            self.advance_char();
+        }
+        let kind = match op {
+            "=" => TokenKind::Equals,
+            "=>" => TokenKind::FatArrow,
+            "->" => TokenKind::Arrow,
+            ":" => TokenKind::Colon,
+            "?" => TokenKind::Question,
+            "." => TokenKind::Dot,
+            "," => TokenKind::Comma,
+            ";" => TokenKind::Semi,
+            "(" => TokenKind::LParen,
+            ")" => TokenKind::RParen,
+            "[" => TokenKind::LBracket,
+            "]" => TokenKind::RBracket,
+            other => TokenKind::Op(other.to_string()),
+        };
+        Ok(Token {
+            kind,
+            span: self.span_from(mark),
+        })
+    }
+
+    /// Lexes a string literal ending at `quote`. Unless `multiline` is set, a
+    /// raw line break ends the string unterminated, as in JavaScript.
+    fn consume_string(
+        &mut self,
+        quote: char,
+        multiline: bool,
+    ) -> Result<(Token, String), LexError> {
         let mark = self.mark();
-        // skip opening "
+        // skip opening quote
         self.advance_char();
         let mut value = String::new();
 
         while let Some(ch) = self.peek_char() {
             match ch {
                 '\\' => {
+                    let backslash = self.mark();
                     self.advance_char();
-                    match self.peek_char() {
-                        Some('"') => {
-                            self.advance_char();
-                            value.push('"');
-                        }
-                        Some('\\') => {
-                            self.advance_char();
-                            value.push('\\');
-                        }
-                        Some('n') => {
-                            self.advance_char();
-                            value.push('\n');
-                        }
-                        Some('t') => {
-                            self.advance_char();
-                            value.push('\t');
-                        }
-                        Some(other) => {
-                            self.advance_char();
-                            value.push(other);
-                        }
-                        None => return Err(LexError::Invalid(self.span_from(mark))),
+                    if self.peek_char().is_none() {
+                        return Err(LexError::UnterminatedString(self.span_from(mark)));
                     }
+                    value.extend(self.consume_escape(backslash)?);
                 }
-                '"' => {
-                    // closing "
+                c if c == quote => {
+                    // closing quote
                     self.advance_char();
                     let span = self.span_from(mark);
                     let tok = Token {
@@ -164,6 +463,7 @@ impl<'a> Lexer<'a> {
                     };
                     return Ok((tok, value));
                 }
+                '\n' | '\r' if !multiline => break,
                 _ => {
                     self.advance_char();
                     value.push(ch);
@@ -171,27 +471,183 @@ impl<'a> Lexer<'a> {
             }
         }
 
-        // EOF before closing quote
-        Err(LexError::Invalid(self.span_from(mark)))
+        // EOF or a line break before the closing quote
+        Err(LexError::UnterminatedString(self.span_from(mark)))
+    }
+
+    /// Decodes the escape sequence following a `\` in a string literal the way
+    /// JavaScript does. A line continuation decodes to nothing.
+    fn consume_escape(&mut self, backslash: Mark) -> Result<Option<char>, LexError> {
+        let ch = self.advance_char().expect("escape follows the backslash");
+        let decoded = match ch {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            'b' => '\u{8}',
+            'f' => '\u{c}',
+            'v' => '\u{b}',
+            '0' if !self.peek_char().is_some_and(|c| c.is_ascii_digit()) => '\0',
+            '\r' => {
+                if self.peek_char() == Some('\n') {
+                    self.advance_char();
+                }
+                return Ok(None);
+            }
+            '\n' | '\u{2028}' | '\u{2029}' => return Ok(None),
+            'x' => self
+                .consume_hex(2)
+                .and_then(char::from_u32)
+                .ok_or_else(|| LexError::InvalidEscape(self.span_from(backslash)))?,
+            'u' => {
+                let code = self
+                    .consume_unicode_escape()
+                    .ok_or_else(|| LexError::InvalidEscape(self.span_from(backslash)))?;
+                // A UTF-16 surrogate pair spelled as two escapes, as in
+                // `\uD83D\uDE00`, makes one character.
+                let low = (0xD800..0xDC00).contains(&code).then(|| {
+                    let hex = self.input[self.index..].strip_prefix("\\u")?.get(..4)?;
+                    u32::from_str_radix(hex, 16)
+                        .ok()
+                        .filter(|low| (0xDC00..0xE000).contains(low))
+                });
+                let code = match low.flatten() {
+                    Some(low) => {
+                        for _ in 0.."\\uXXXX".len() {
+                            self.advance_char();
+                        }
+                        0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00)
+                    }
+                    None => code,
+                };
+                char::from_u32(code)
+                    .ok_or_else(|| LexError::InvalidEscape(self.span_from(backslash)))?
+            }
+            // Octal escapes are a syntax error in strict-mode JavaScript.
+            '0'..='9' => return Err(LexError::InvalidEscape(self.span_from(backslash))),
+            other => other,
+        };
+        Ok(Some(decoded))
+    }
+
+    /// The code point of a `\u` escape after the `u`: four hex digits, or one
+    /// to six in braces.
+    fn consume_unicode_escape(&mut self) -> Option<u32> {
+        if self.peek_char() != Some('{') {
+            return self.consume_hex(4);
+        }
+        self.advance_char();
+        let digits = self.input[self.index..]
+            .chars()
+            .take_while(char::is_ascii_hexdigit)
+            .count();
+        let code = match digits {
+            1..=6 => self.consume_hex(digits)?,
+            _ => return None,
+        };
+        (self.advance_char() == Some('}') && code <= 0x10FFFF).then_some(code)
+    }
+
+    /// Consumes exactly `count` hex digits and returns their value.
+    fn consume_hex(&mut self, count: usize) -> Option<u32> {
+        let mut code = 0;
+        for _ in 0..count {
+            let digit = self.peek_char()?.to_digit(16)?;
+            self.advance_char();
+            code = code * 16 + digit;
+        }
+        Some(code)
+    }
+
+    /// Consumes a regular expression literal `len` bytes long.
+    fn consume_regex(&mut self, len: usize) -> Token {
+        let mark = self.mark();
+        let end = self.index + len;
+        while self.index < end {
+            self.advance_char();
+        }
+        Token {
+            kind: TokenKind::Regex(self.input[mark.index..end].to_string()),
+            span: self.span_from(mark),
+        }
+    }
+
+    fn consume_template(&mut self) -> Result<Token, LexError> {
+        let mark = self.mark();
+        self.skip_template()
+            .map_err(|()| LexError::UnterminatedTemplate(self.span_from(mark)))?;
+        let span = self.span_from(mark);
+        let raw = &self.input[mark.index + 1..self.index - 1];
+        Ok(Token {
+            kind: TokenKind::Template(raw.to_string()),
+            span,
+        })
+    }
+
+    /// Skips a template literal, including any `${ ... }` placeholders and the
+    /// templates nested inside them.
+    fn skip_template(&mut self) -> Result<(), ()> {
+        self.advance_char(); // opening `
+        while let Some(ch) = self.advance_char() {
+            match ch {
+                '\\' => {
+                    self.advance_char();
+                }
+                '`' => return Ok(()),
+                '$' if self.peek_char() == Some('{') => {
+                    self.advance_char();
+                    let mut depth = 1usize;
+                    while depth > 0 {
+                        match self.peek_char().ok_or(())? {
+                            '`' => self.skip_template()?,
+                            '{' => {
+                                depth += 1;
+                                self.advance_char();
+                            }
+                            '}' => {
+                                depth -= 1;
+                                self.advance_char();
+                            }
+                            _ => {
+                                self.advance_char();
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(())
     }
 
     fn consume_text(&mut self) -> (Token, bool) {
         let mark = self.mark();
         let mut value = String::new();
+        let in_tag = matches!(self.mode(), Mode::Markup { in_tag: true, .. });
 
         while let Some(ch) = self.peek_char() {
             // stop at constructs handled by other scanners
             if ch == '<'
                 || ch == '{'
                 || ch == '}'
-                || ch == '"'
-                || (self.in_tag && (ch == '/' || ch == '='))
+                || (in_tag && (ch == '"' || ch == '/' || ch == '=' || ch == '>'))
             {
                 break;
             }
             // If we encounter an identifier-start *and* we're inside a tag, stop;
             // the tag body (attr names) should be handled by consume_ident.
-            if self.in_tag && is_ident_start(ch) {
+            if in_tag && is_ident_start(ch) {
+                break;
+            }
+            // A `//` comment may start on its own line inside an element.
+            if ch == '/'
+                && self.peek_next_char() == Some('/')
+                && value
+                    .rsplit('\n')
+                    .next()
+                    .unwrap_or_default()
+                    .trim()
+                    .is_empty()
+            {
                 break;
             }
             value.push(ch);
@@ -199,7 +655,7 @@ impl<'a> Lexer<'a> {
         }
 
         let span = self.span_from(mark);
-        let trimmed = trim_text(&value);
+        let trimmed = collapse_whitespace(&value);
         if trimmed.is_empty() {
             (
                 Token {
@@ -241,30 +697,23 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn consume_expression(&mut self, tokens: &mut Vec<Token>) -> Result<(), LexError> {
-        // we already pushed LBrace
-        let start_span = tokens.last().map(|t| t.span.clone()).unwrap_or(Span {
-            line: self.line,
-            col: self.col,
-            offset: self.index,
-            len: 0,
-        });
-
-        while let Some(ch) = self.peek_char() {
-            if ch == '}' {
-                let mark = self.mark();
+    fn consume_block_comment(&mut self) -> Result<(), LexError> {
+        let mark = self.mark();
+        self.advance_char(); // '/'
+        self.advance_char(); // '*'
+        while self.peek_char().is_some() {
+            if self.input[self.index..].starts_with("*/") {
+                self.advance_char();
                 self.advance_char();
-                let span = self.span_from(mark);
-                tokens.push(Token {
-                    kind: TokenKind::RBrace,
-                    span,
-                });
                 return Ok(());
             }
             self.advance_char();
         }
+        Err(LexError::UnterminatedComment(self.span_from(mark)))
+    }
 
-        Err(LexError::Invalid(start_span))
+    fn mode(&self) -> Mode {
+        *self.modes.last().expect("lexer always has a mode")
     }
 
     fn peek_char(&self) -> Option<char> {
@@ -306,6 +755,8 @@ impl<'a> Lexer<'a> {
             col: mark.col,
             offset: mark.index,
             len: self.index - mark.index,
+            end_line: self.line,
+            end_col: self.col,
         }
     }
 } // <-- closes impl<'a> Lexer<'a>
@@ -325,19 +776,91 @@ fn is_ident_continue(ch: char) -> bool {
     ch.is_ascii_alphanumeric() || ch == '_' || ch == '-'
 }
 
-fn trim_text(value: &str) -> String {
-    let mut text = value.to_string();
-    if text.starts_with('\n') {
-        text.remove(0);
+/// Whether a `/` after a token of kind `previous` starts a regular expression
+/// rather than dividing.
+fn starts_regex(previous: Option<&TokenKind>) -> bool {
+    match previous {
+        Some(TokenKind::Op(op)) => op != "++" && op != "--",
+        other => may_start_expression(other),
+    }
+}
+
+/// The length of the regular expression literal `rest` starts with, flags
+/// included, or `None` when no `/` closes it on the same line.
+fn regex_len(rest: &str) -> Option<usize> {
+    let mut in_class = false;
+    let mut chars = rest.char_indices().skip(1);
+    while let Some((idx, ch)) = chars.next() {
+        match ch {
+            '\\' => {
+                chars.next();
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '/' if !in_class => {
+                let flags = rest[idx + 1..]
+                    .chars()
+                    .take_while(char::is_ascii_alphabetic)
+                    .count();
+                return Some(idx + 1 + flags);
+            }
+            '\n' | '\r' => return None,
+            _ => {}
+        }
     }
-    if text.ends_with('\n') {
-        text.pop();
+    None
+}
+
+/// Applies JSX's whitespace rules to a run of markup text: whitespace next to
+/// a line break is dropped, lines that are left empty disappear and the
+/// remaining lines are joined with a single space. Whitespace within a line,
+/// such as the space in `Hello <b>` or a tab, is kept.
+pub(crate) fn collapse_whitespace(value: &str) -> String {
+    let lines: Vec<&str> = value.lines().collect();
+    let last_non_empty = lines
+        .iter()
+        .rposition(|line| line.contains(|c| c != ' ' && c != '\t'))
+        .unwrap_or(0);
+    let mut text = String::new();
+    for (idx, line) in lines.iter().enumerate() {
+        let mut line = *line;
+        if idx > 0 {
+            line = line.trim_start_matches([' ', '\t']);
+        }
+        // `lines` drops a trailing newline, so an unterminated last line is the
+        // only one that keeps its trailing whitespace.
+        if idx + 1 < lines.len() || value.ends_with('\n') {
+            line = line.trim_end_matches([' ', '\t']);
+        }
+        if !line.is_empty() {
+            text.push_str(line);
+            if idx != last_non_empty {
+                text.push(' ');
+            }
+        }
     }
     text
 }
 
+/// Lexes a markup fragment such as `<div>Hello</div>`.
 pub fn lex(input: &str) -> Result<Vec<Token>, LexError> {
-    Lexer::new(input).lex()
+    let root = Mode::Markup {
+        depth: 0,
+        root: true,
+        in_tag: false,
+        closing: false,
+    };
+    Lexer::new(input, root).lex()
+}
+
+/// Lexes a whole `.raven` source file, switching into markup wherever an
+/// element appears in expression position.
+pub fn lex_source(input: &str) -> Result<Vec<Token>, LexError> {
+    let root = Mode::Code {
+        braces: 0,
+        open: None,
+    };
+    Lexer::new(input, root).lex()
 }
 
 #[cfg(test)]
@@ -345,6 +868,10 @@ mod tests {
     use super::*;
     use crate::token::TokenKind;
 
+    fn kinds(tokens: Vec<Token>) -> Vec<TokenKind> {
+        tokens.into_iter().map(|t| t.kind).collect()
+    }
+
     #[test]
     fn lexes_simple_element() {
         let tokens = lex("<div>Hello</div>").unwrap();
@@ -375,4 +902,271 @@ mod tests {
         assert_eq!(kinds[3], TokenKind::Equals);
         assert_eq!(kinds[4], TokenKind::Str("cta".into()));
     }
+
+    #[test]
+    fn lexes_component_header_in_code_mode() {
+        let tokens = lex_source("component Button(returnUrl: string) {").unwrap();
+        assert_eq!(
+            kinds(tokens),
+            vec![
+                TokenKind::Ident("component".into()),
+                TokenKind::Ident("Button".into()),
+                TokenKind::LParen,
+                TokenKind::Ident("returnUrl".into()),
+                TokenKind::Colon,
+                TokenKind::Ident("string".into()),
+                TokenKind::RParen,
+                TokenKind::LBrace,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn tells_regex_literals_from_division() {
+        let tokens = lex_source("x = /[/]a\\/+/gi.test(a / b)").unwrap();
+        assert_eq!(
+            kinds(tokens),
+            vec![
+                TokenKind::Ident("x".into()),
+                TokenKind::Equals,
+                TokenKind::Regex("/[/]a\\/+/gi".into()),
+                TokenKind::Dot,
+                TokenKind::Ident("test".into()),
+                TokenKind::LParen,
+                TokenKind::Ident("a".into()),
+                TokenKind::Op("/".into()),
+                TokenKind::Ident("b".into()),
+                TokenKind::RParen,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn switches_to_markup_after_return_and_back() {
+        let tokens = lex_source("return <p>hi</p>; x < y").unwrap();
+        assert_eq!(
+            kinds(tokens),
+            vec![
+                TokenKind::Ident("return".into()),
+                TokenKind::LAngle,
+                TokenKind::Ident("p".into()),
+                TokenKind::RAngle,
+                TokenKind::Text("hi".into()),
+                TokenKind::LAngle,
+                TokenKind::Slash,
+                TokenKind::Ident("p".into()),
+                TokenKind::RAngle,
+                TokenKind::Semi,
+                TokenKind::Ident("x".into()),
+                TokenKind::Op("<".into()),
+                TokenKind::Ident("y".into()),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_markup_expressions_as_code() {
+        let tokens = lex("<a href={base + \"/\"}>{n > 1}</a>").unwrap();
+        assert_eq!(
+            kinds(tokens)[4..=13].to_vec(),
+            vec![
+                TokenKind::LBrace,
+                TokenKind::Ident("base".into()),
+                TokenKind::Op("+".into()),
+                TokenKind::Str("/".into()),
+                TokenKind::RBrace,
+                TokenKind::RAngle,
+                TokenKind::LBrace,
+                TokenKind::Ident("n".into()),
+                TokenKind::Op(">".into()),
+                TokenKind::Number("1".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn tracks_nested_braces_strings_and_comments_in_expressions() {
+        let sources = [
+            "<button onClick={() => { setOpen(true) }}>x</button>",
+            "<p>{ {a: 1}.a }</p>",
+            "<p>{\"}\"}</p>",
+            "<p>{'{'}{`}${ {b: `}`}.b }`}</p>",
+            "<p>{/* } */ x // }\n}</p>",
+        ];
+        for src in sources {
+            // Every expression closes back into markup, so the closing tag is
+            // lexed as markup rather than as code.
+            let kinds = kinds(lex(src).unwrap());
+            assert!(
+                matches!(
+                    kinds[kinds.len() - 5..],
+                    [
+                        TokenKind::LAngle,
+                        TokenKind::Slash,
+                        TokenKind::Ident(_),
+                        TokenKind::RAngle,
+                        TokenKind::Eof
+                    ]
+                ),
+                "lexing {}: {:?}",
+                src,
+                kinds
+            );
+        }
+    }
+
+    #[test]
+    fn reports_unclosed_expression_at_its_brace() {
+        let err = lex("<p>\n  {items.map((i) => { i })").unwrap_err();
+        match err {
+            LexError::UnclosedExpression(span) => assert_eq!((span.line, span.col), (2, 3)),
+            other => panic!("expected unclosed expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn ends_an_unclosed_expression_at_a_closing_tag() {
+        let tokens = lex("<p>{a</p><b>x</b>").unwrap();
+        assert_eq!(
+            kinds(tokens)[4..],
+            [
+                TokenKind::Ident("a".into()),
+                TokenKind::LAngle,
+                TokenKind::Slash,
+                TokenKind::Ident("p".into()),
+                TokenKind::RAngle,
+                TokenKind::LAngle,
+                TokenKind::Ident("b".into()),
+                TokenKind::RAngle,
+                TokenKind::Text("x".into()),
+                TokenKind::LAngle,
+                TokenKind::Slash,
+                TokenKind::Ident("b".into()),
+                TokenKind::RAngle,
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_directive_attribute_names() {
+        let tokens = lex("<li class:is-done={done} />").unwrap();
+        assert_eq!(
+            kinds(tokens)[2..5].to_vec(),
+            vec![
+                TokenKind::Ident("class".into()),
+                TokenKind::Colon,
+                TokenKind::Ident("is-done".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_quotes_between_tags_as_text() {
+        let tokens = lex("<q>She said \"hi\" &amp; left</q>").unwrap();
+        assert_eq!(
+            kinds(tokens)[3],
+            TokenKind::Text("She said \"hi\" &amp; left".into())
+        );
+    }
+
+    #[test]
+    fn collapses_markup_whitespace_like_jsx() {
+        let cases = [
+            ("Hello ", "Hello "),
+            (" again", " again"),
+            ("  two  spaces  ", "  two  spaces  "),
+            ("\n  Hello\n  ", "Hello"),
+            ("\n  again and\n  again\n", "again and again"),
+            ("a\n\n\n  b", "a b"),
+            ("\t tab\t", "\t tab\t"),
+            ("a\t\n\tb", "a b"),
+            (" ", " "),
+            ("\n   \n", ""),
+        ];
+        for (raw, collapsed) in cases {
+            assert_eq!(collapse_whitespace(raw), collapsed, "collapsing {:?}", raw);
+        }
+        let tokens = lex("<p>Hello <b>world</b> again\n  // note\n</p>").unwrap();
+        let texts: Vec<TokenKind> = kinds(tokens)
+            .into_iter()
+            .filter(|kind| matches!(kind, TokenKind::Text(_)))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                TokenKind::Text("Hello ".into()),
+                TokenKind::Text("world".into()),
+                TokenKind::Text(" again".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_javascript_string_escapes() {
+        let cases = [
+            (r#""a\nb\tc""#, "a\nb\tc"),
+            (r#""a\rb\x41""#, "a\rbA"),
+            (r#""\u00e9\u{1F600}\uD83D\uDE00""#, "é😀😀"),
+            (r#""\0\b\f\v""#, "\0\u{8}\u{c}\u{b}"),
+            (r#"'it\'s \"q\" \\ \d'"#, "it's \"q\" \\ d"),
+            ("\"one \\\ntwo\"", "one two"),
+        ];
+        for (src, value) in cases {
+            assert_eq!(
+                kinds(lex_source(src).unwrap())[0],
+                TokenKind::Str(value.into()),
+                "decoding {}",
+                src
+            );
+        }
+        for src in [
+            r#""\x4""#,
+            r#""\u12""#,
+            r#""\u{110000}""#,
+            r#""\uD800""#,
+            r#""\1""#,
+        ] {
+            assert!(
+                matches!(lex_source(src), Err(LexError::InvalidEscape(_))),
+                "rejecting {}",
+                src
+            );
+        }
+    }
+
+    #[test]
+    fn ends_code_strings_at_line_breaks() {
+        let src = "const name = \"Ada\nreturn name";
+        match lex_source(src) {
+            Err(LexError::UnterminatedString(span)) => {
+                assert_eq!((span.line, span.col, span.len), (1, 14, 4))
+            }
+            other => panic!("expected an unterminated string, got {:?}", other),
+        }
+        assert!(matches!(
+            lex_source("'a\r\nb'"),
+            Err(LexError::UnterminatedString(_))
+        ));
+        let tokens = lex("<p title=\"two\nlines\">x</p>").unwrap();
+        assert_eq!(kinds(tokens)[4], TokenKind::Str("two\nlines".into()));
+    }
+
+    #[test]
+    fn skips_comments_and_strings_in_code() {
+        let tokens = lex_source("// return here\nlet s = \"}\" /* { */").unwrap();
+        assert_eq!(
+            kinds(tokens),
+            vec![
+                TokenKind::Ident("let".into()),
+                TokenKind::Ident("s".into()),
+                TokenKind::Equals,
+                TokenKind::Str("}".into()),
+                TokenKind::Eof,
+            ]
+        );
+    }
 }
@@ -2,178 +2,289 @@
 
 mod ast;
 mod codegen;
+mod codegen_enhanced;
+mod completion;
+mod entities;
+mod errors;
+mod explain;
+mod formatter;
 mod lexer;
+mod lsp;
+mod module;
 mod parser;
+mod rpc;
+mod sourcemap;
 mod token;
 
 pub use codegen::to_tsx;
-pub use lexer::lex;
-pub use parser::parse;
+pub use completion::{complete, Completion, CompletionKind, Completions};
+pub use explain::explain;
+pub use formatter::format_source;
+pub use lexer::{lex, lex_source};
+pub use lsp::serve_lsp;
+pub use parser::{parse, parse_recovering, parse_source, parse_source_recovering};
 
+pub use crate::errors::{Diagnostic, Label, Severity};
 pub use crate::lexer::LexError;
+pub use crate::module::ModuleError;
 pub use crate::parser::ParseError;
+pub use crate::sourcemap::{decode_mappings, Segment, SourceMapError};
 
+use crate::ast::{Attr, Block, Component, Item, Node, Param, SourceFile, Stmt};
+use crate::codegen::Layout;
+use crate::token::{Span, Token};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum CompileError {
-    #[error("component syntax error: {0}")]
-    Component(String),
+    #[error("expected a `component`, `page` or `server fn` declaration")]
+    MissingDeclaration,
+    #[error("component `{component}` at {span:?} has no `return`")]
+    MissingReturn { component: String, span: Span },
+    #[error("`return` at {0:?} has no markup to render")]
+    EmptyReturn(Span),
     #[error("lex error: {0}")]
     Lex(#[from] lexer::LexError),
     #[error("parse error: {0}")]
     Parse(#[from] parser::ParseError),
+    /// Every syntax error in a file with more than one, in the order found.
+    #[error("{} parse errors, the first: {}", .0.len(), .0[0])]
+    ParseErrors(Vec<parser::ParseError>),
+    #[error("module error: {0}")]
+    Module(#[from] module::ModuleError),
+    #[error("required parameter `{param}` follows optional parameter `{optional}` at {span:?}")]
+    RequiredAfterOptional {
+        param: String,
+        optional: String,
+        span: Span,
+    },
+    #[error("`<{element}>` in the `for` loop at {span:?} needs a `key` attribute")]
+    MissingKey {
+        element: String,
+        /// The `for` loop.
+        span: Span,
+        /// The element without a key.
+        element_span: Span,
+    },
 }
 
-/// Compile a RavensOne component source string into TSX.
-pub fn compile_component(src: &str) -> Result<String, CompileError> {
-    let component = parse_component(src)?;
-    let markup = extract_markup(&component.body)?;
-    let tokens = lex(&markup)?;
-    let doc = parse(&tokens, &markup)?;
-    let tsx = to_tsx(&doc);
-    Ok(render_component(&component.name, &component.params, &tsx))
+impl CompileError {
+    /// The stable code `raven explain` knows this error by; for
+    /// [`CompileError::ParseErrors`], the first error's.
+    pub fn code(&self) -> &'static str {
+        match self {
+            CompileError::Lex(err) => err.code(),
+            CompileError::Parse(err) => err.code(),
+            CompileError::ParseErrors(errors) => errors[0].code(),
+            CompileError::Module(err) => err.code(),
+            CompileError::MissingDeclaration => "R0401",
+            CompileError::MissingReturn { .. } => "R0402",
+            CompileError::EmptyReturn(_) => "R0403",
+            CompileError::RequiredAfterOptional { .. } => "R0404",
+            CompileError::MissingKey { .. } => "R0405",
+        }
+    }
 }
 
-struct Component {
-    name: String,
-    params: Vec<String>,
-    body: String,
+/// Compile a RavensOne source file into TSX.
+pub fn compile_component(src: &str) -> Result<String, CompileError> {
+    Ok(compile(src, false)?.into_string())
 }
 
-fn parse_component(src: &str) -> Result<Component, CompileError> {
-    let trimmed = src.trim();
-
-    // `component` keyword
-    let rest = trimmed
-        .strip_prefix("component")
-        .ok_or_else(|| CompileError::Component("expected `component` keyword".into()))?
-        .trim_start();
-
-    // name + params
-    let paren_start = rest
-        .find('(')
-        .ok_or_else(|| CompileError::Component("missing parameter list".into()))?;
-    let name = rest[..paren_start].trim();
-    if name.is_empty() {
-        return Err(CompileError::Component("missing component name".into()));
-    }
-
-    let (params_raw, after_params) = extract_parens(&rest[paren_start..])?;
-    let params = parse_params(params_raw);
+/// Like [`compile_component`], with returned markup indented across lines so
+/// that the output reads well in review.
+pub fn compile_component_pretty(src: &str) -> Result<String, CompileError> {
+    Ok(compile(src, true)?.into_string())
+}
 
-    // body block
-    let (body, _remainder) = extract_block(after_params.trim_start())?;
+/// Generated TSX together with a Source Map v3 pointing back into the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MappedTsx {
+    pub code: String,
+    /// The `.map` file's JSON.
+    pub map: String,
+}
 
-    Ok(Component {
-        name: name.to_string(),
-        params,
-        body: body.to_string(),
+/// Compiles `src` like [`compile_component`] (or [`compile_component_pretty`]
+/// when `pretty` is set) and renders its source map. `file` is the name of the
+/// generated file and `source_name` the path of `src` relative to the map.
+pub fn compile_with_source_map(
+    src: &str,
+    pretty: bool,
+    file: &str,
+    source_name: &str,
+) -> Result<MappedTsx, CompileError> {
+    let output = compile(src, pretty)?;
+    Ok(MappedTsx {
+        map: sourcemap::render(&output, src, file, source_name),
+        code: output.into_string(),
     })
 }
 
-fn extract_markup(body: &str) -> Result<String, CompileError> {
-    // naive: find `return ...;` and grab the expression
-    let return_idx = body
-        .find("return")
-        .ok_or_else(|| CompileError::Component("missing `return` in component body".into()))?;
-
-    let after_return = &body[return_idx + "return".len()..];
-    let mut markup = after_return.trim();
+fn compile(src: &str, pretty: bool) -> Result<sourcemap::Output, CompileError> {
+    let layout = if pretty {
+        Layout::Pretty {
+            width: codegen_enhanced::DEFAULT_WIDTH,
+        }
+    } else {
+        Layout::Compact
+    };
+    Ok(codegen::render_module(&check(src)?, layout))
+}
 
-    // strip trailing `;`
-    if markup.ends_with(';') {
-        markup = markup[..markup.len() - 1].trim_end();
+/// Lexes and parses a whole file, failing with every syntax error in it.
+pub(crate) fn parse_file(src: &str) -> Result<(Vec<Token>, SourceFile), CompileError> {
+    let tokens = lex_source(src)?;
+    let (file, mut errors) = parse_source_recovering(&tokens, src);
+    match errors.len() {
+        0 => Ok((tokens, file)),
+        1 => Err(CompileError::Parse(errors.remove(0))),
+        _ => Err(CompileError::ParseErrors(errors)),
     }
+}
 
-    // allow parentheses wrapping
-    if markup.starts_with('(') && markup.ends_with(')') && markup.len() >= 2 {
-        markup = markup[1..markup.len() - 1].trim();
+/// Runs every check of [`compile_component`] without generating code.
+pub(crate) fn check(src: &str) -> Result<module::Module, CompileError> {
+    let (_, file) = parse_file(src)?;
+    let module = module::Module::new(file)?;
+    let mut declarations = 0;
+    for item in &module.file.items {
+        match item {
+            Item::Component(component) => {
+                check_component(component)?;
+                check_keys(&component.body)?;
+            }
+            Item::Page(page) => {
+                check_component(page)?;
+                check_keys(&page.body)?;
+                check_positional_params(&page.params)?;
+            }
+            Item::ServerFn(func) => {
+                check_keys(&func.body)?;
+                check_positional_params(&func.params)?;
+            }
+            Item::Import(_) => continue,
+            Item::TypeDecl(_) => {}
+        }
+        declarations += 1;
     }
-
-    if markup.is_empty() {
-        return Err(CompileError::Component("empty return body".into()));
+    if declarations == 0 {
+        return Err(CompileError::MissingDeclaration);
     }
-    Ok(markup.to_string())
+    Ok(module)
 }
 
-fn render_component(name: &str, params: &[String], tsx: &str) -> String {
-    let params_binding = if params.is_empty() {
-        "()".to_string()
-    } else {
-        format!("({{ {} }})", params.join(", "))
-    };
+/// The server router and client stubs generated for a file's `server fn`s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RpcModules {
+    /// Dispatch table and `handleRpc` request handler.
+    pub server: String,
+    /// `fetch`-based stubs with the same signatures as the server functions.
+    pub client: String,
+}
 
-    let mut output = String::new();
-    output.push_str(&format!("export function {}{} {{\n", name, params_binding));
-    output.push_str("  return ");
-    output.push_str(tsx);
-    output.push_str(";\n}\n");
-    output
-}
-
-/// Extracts the inside of a balanced `( … )` sequence at the beginning of `input`.
-fn extract_parens(input: &str) -> Result<(&str, &str), CompileError> {
-    if !input.starts_with('(') {
-        return Err(CompileError::Component("expected `(`".into()));
-    }
-    let mut depth = 0usize;
-    for (idx, ch) in input.char_indices() {
-        match ch {
-            '(' => depth += 1,
-            ')' => {
-                depth = depth.saturating_sub(1);
-                if depth == 0 {
-                    let inside = &input[1..idx];
-                    let rest = &input[idx + 1..];
-                    return Ok((inside, rest));
-                }
-            }
-            _ => {}
-        }
+/// Generates the RPC modules for every `server fn` in `src`, or `None` when the
+/// file declares none. `module` is the import path of the file's compiled
+/// output, relative to the generated server and client modules.
+pub fn compile_rpc(src: &str, module: &str) -> Result<Option<RpcModules>, CompileError> {
+    let tokens = lex_source(src)?;
+    let file = parse_source(&tokens, src)?;
+    let funcs: Vec<_> = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::ServerFn(func) => Some(func),
+            _ => None,
+        })
+        .collect();
+    if funcs.is_empty() {
+        return Ok(None);
     }
-    Err(CompileError::Component(
-        "unclosed `(` in parameter list".into(),
-    ))
-}
-
-/// Extracts the inside of a balanced `{ … }` sequence at the beginning of `input`.
-fn extract_block(input: &str) -> Result<(&str, &str), CompileError> {
-    if !input.starts_with('{') {
-        return Err(CompileError::Component("expected `{`".into()));
-    }
-    let mut depth = 0usize;
-    for (idx, ch) in input.char_indices() {
-        match ch {
-            '{' => depth += 1,
-            '}' => {
-                depth = depth.saturating_sub(1);
-                if depth == 0 {
-                    let inside = &input[1..idx];
-                    let rest = &input[idx + 1..];
-                    return Ok((inside, rest));
-                }
+    let imports: Vec<_> = file
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Import(import) => Some(import),
+            _ => None,
+        })
+        .collect();
+    Ok(Some(RpcModules {
+        server: rpc::render_server(&funcs, module),
+        client: rpc::render_client(&funcs, &imports, module),
+    }))
+}
+
+/// Server function arguments and page route segments are positional, so once a
+/// parameter may be left out every later one must be optional too. Component
+/// props are passed by name and may come in any order.
+fn check_positional_params(params: &[Param]) -> Result<(), CompileError> {
+    let mut optional: Option<&Param> = None;
+    for param in params {
+        match optional {
+            Some(first) if !param.is_optional() => {
+                return Err(CompileError::RequiredAfterOptional {
+                    param: param.name.name.clone(),
+                    optional: first.name.name.clone(),
+                    span: param.span,
+                });
             }
+            None if param.is_optional() => optional = Some(param),
             _ => {}
         }
     }
-    Err(CompileError::Component(
-        "unclosed `{` in component body".into(),
-    ))
+    Ok(())
 }
 
-fn parse_params(params: &str) -> Vec<String> {
-    params
-        .split(',')
-        .filter_map(|param| {
-            let name = param.split(':').next()?.trim();
-            if name.is_empty() {
-                None
-            } else {
-                Some(name.to_string())
-            }
+fn check_component(component: &Component) -> Result<(), CompileError> {
+    let ret = component
+        .body
+        .stmts
+        .iter()
+        .find_map(|stmt| match stmt {
+            Stmt::Return(ret) => Some(ret),
+            _ => None,
         })
-        .collect()
+        .ok_or_else(|| CompileError::MissingReturn {
+            component: component.name.name.clone(),
+            span: component.name.span,
+        })?;
+    if ret.value.is_none() {
+        return Err(CompileError::EmptyReturn(ret.span));
+    }
+    Ok(())
+}
+
+/// A markup `for` renders a list, so React needs a `key` on every root element
+/// of the loop body. Loops anywhere in `block` are checked, including those in
+/// nested statements.
+fn check_keys(block: &Block) -> Result<(), CompileError> {
+    let mut missing = None;
+    ast::visit_nodes(block, &mut |node| {
+        let Node::For { body, span, .. } = node else {
+            return;
+        };
+        for child in ast::loop_roots(body) {
+            if let Node::Element {
+                name,
+                attrs,
+                span: element_span,
+                ..
+            } = child
+            {
+                let keyed = attrs
+                    .iter()
+                    .any(|attr| matches!(attr, Attr::Named { name, .. } if name == "key"));
+                if missing.is_none() && !keyed {
+                    missing = Some(CompileError::MissingKey {
+                        element: name.clone(),
+                        span: *span,
+                        element_span: *element_span,
+                    });
+                }
+            }
+        }
+    });
+    missing.map_or(Ok(()), Err)
 }
 
 /// Returns the current RavensOne library version.
@@ -192,4 +303,265 @@ mod tests {
         assert!(output.contains("export function App"));
         assert!(output.contains("<div>Hello</div>"));
     }
+
+    #[test]
+    fn return_inside_names_and_strings_is_not_mistaken_for_the_body() {
+        let source = "component Link(returnUrl: string) {\n  const label = \"return }\"\n  return (\n    <a href={returnUrl}>{label}</a>\n  )\n}";
+        let output = compile_component(source).unwrap();
+        assert_eq!(
+            output,
+            "interface LinkProps {\n  returnUrl: string;\n}\n\nexport function Link({ returnUrl }: LinkProps) {\n  const label = \"return }\";\n  return <a href={returnUrl}>{label}</a>;\n}\n"
+        );
+    }
+
+    #[test]
+    fn compiles_page_with_route_metadata() {
+        let output = compile_component("page Index() {\n  return <TodoList />\n}").unwrap();
+        assert_eq!(
+            output,
+            "export default function Index() {\n  return <TodoList />;\n}\n\nexport const route = { path: \"/\", params: [] };\n"
+        );
+
+        let output =
+            compile_component("page TeamDashboard(teamId: string) { return <section /> }").unwrap();
+        assert!(output
+            .contains("export default function TeamDashboard({ teamId }: TeamDashboardProps)"));
+        assert!(output.contains(
+            "export const route = { path: \"/team-dashboard/:teamId\", params: [\"teamId\"] };"
+        ));
+    }
+
+    #[test]
+    fn keeps_nested_braces_inside_markup_expressions() {
+        let source = "component Menu() {\n  return <button style={{ color: \"red\" }} onClick={() => { setOpen(true) }} title={\"}\"}>{ {a: 1}.a }</button>\n}";
+        let output = compile_component(source).unwrap();
+        assert!(output.contains(
+            "return <button style={{ color: \"red\" }} onClick={() => { setOpen(true); }} title={\"}\"}>{{ a: 1 }.a}</button>;"
+        ), "{}", output);
+    }
+
+    #[test]
+    fn requires_keys_on_for_loop_elements() {
+        let source = "component List(items: [Todo]) {\n  return <ul>\n    {for item in items { <li>{item.title}</li> }}\n  </ul>\n}";
+        match compile_component(source).unwrap_err() {
+            CompileError::MissingKey {
+                element,
+                span,
+                element_span,
+            } => {
+                assert_eq!(element, "li");
+                assert_eq!((span.line, span.col), (3, 6));
+                assert_eq!((element_span.line, element_span.col), (3, 26));
+            }
+            other => panic!("expected missing key, got {:?}", other),
+        }
+        let keyed = source.replace("<li>", "<li key={item.id}>");
+        assert!(compile_component(&keyed)
+            .unwrap()
+            .contains("{items.map((item) => <li key={item.id}>{item.title}</li>)}"));
+    }
+
+    #[test]
+    fn requires_keys_in_loops_inside_statements() {
+        let source = "component List(items: [Todo], compact: bool) {\n  if (compact) {\n    return <ul>{for item in items { <li>{item.title}</li> }}</ul>\n  }\n  return <p />\n}";
+        match compile_component(source).unwrap_err() {
+            CompileError::MissingKey {
+                element,
+                element_span,
+                ..
+            } => {
+                assert_eq!(element, "li");
+                assert_eq!((element_span.line, element_span.col), (3, 37));
+            }
+            other => panic!("expected missing key, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn compiles_server_fn_with_mapped_types() {
+        let source = "server fn listTodos(owner: uuid, tags: [string]) -> [Todo] {\n  return db.todo.findMany({ where: { owner } })\n}";
+        let output = compile_component(source).unwrap();
+        assert_eq!(
+            output,
+            "export async function listTodos(owner: string, tags: string[]): Promise<Todo[]> {\n  return await db.todo.findMany({ where: { owner } });\n}\n"
+        );
+    }
+
+    #[test]
+    fn compiles_markup_inside_control_flow_statements() {
+        let source = "component Retry(loading: bool, tries: [int]) {\n  if (loading) {\n    return <button on:click={reload} class=\"a\">Retry</button>\n  } else if (tries.length > 3) return <p>Gave up</p>\n  for (const t of tries) { log(t) }\n  return <p />\n}";
+        let output = compile_component(source).unwrap();
+        assert!(output.contains(
+            "  if (loading) {\n    return <button onClick={reload} className=\"a\">Retry</button>;\n  } else if (tries.length > 3) {\n    return <p>Gave up</p>;\n  }\n  for (const t of tries) {\n    log(t);\n  }\n  return <p />;\n"
+        ));
+    }
+
+    #[test]
+    fn keeps_every_string_escape() {
+        let output = compile_component(
+            "component S() {\n  let s = \"a\\rb\\x41\\u00e9\"\n  return <p>{s}</p>\n}",
+        )
+        .unwrap();
+        assert!(output.contains("  let s = \"a\\rbAé\";\n"));
+    }
+
+    #[test]
+    fn compiles_multi_item_file_with_imports() {
+        let source = "import { TodoList } from \"../components/TodoList\"\n\ntype Todo {\n  id: uuid,\n  done: bool\n}\n\ncomponent Header(title: string) {\n  return <h1>{title}</h1>\n}\n\npage Index() {\n  return <TodoList />\n}\n";
+        let output = compile_component(source).unwrap();
+        assert_eq!(
+            output,
+            "import { TodoList } from \"../components/TodoList\";\n\n\
+             export interface Todo {\n  id: string;\n  done: boolean;\n}\n\n\
+             interface HeaderProps {\n  title: string;\n}\n\n\
+             export function Header({ title }: HeaderProps) {\n  return <h1>{title}</h1>;\n}\n\n\
+             export default function Index() {\n  return <TodoList />;\n}\n\n\
+             export const route = { path: \"/\", params: [] };\n"
+        );
+    }
+
+    #[test]
+    fn emits_props_interface_from_typed_params() {
+        let output = compile_component(
+            "component Button(text: string, onClick: fn() -> void, icon: string?) {\n  return <button>{text}</button>\n}",
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            "interface ButtonProps {\n  text: string;\n  onClick: () => void;\n  icon?: string;\n}\n\n\
+             export function Button({ text, onClick, icon }: ButtonProps) {\n  return <button>{text}</button>;\n}\n"
+        );
+    }
+
+    #[test]
+    fn renders_defaults_and_optional_props() {
+        let output = compile_component(
+            "component Badge(label: string, tone: string = \"neutral\", icon?: string, size = 2) {\n  return <span>{label}</span>\n}",
+        )
+        .unwrap();
+        assert_eq!(
+            output,
+            "interface BadgeProps {\n  label: string;\n  tone?: string;\n  icon?: string;\n  size?: number;\n}\n\n\
+             export function Badge({ label, tone = \"neutral\", icon, size = 2 }: BadgeProps) {\n  return <span>{label}</span>;\n}\n"
+        );
+    }
+
+    #[test]
+    fn renders_optional_server_fn_params() {
+        let output = compile_component(
+            "server fn search(query: string, limit: int = 20, cursor?: string) -> [Todo] {\n  return db.todo.search(query, limit, cursor)\n}",
+        )
+        .unwrap();
+        assert!(output.starts_with(
+            "export async function search(query: string, limit: number = 20, cursor?: string): Promise<Todo[]> {"
+        ));
+    }
+
+    #[test]
+    fn reports_required_param_after_optional_one() {
+        // Props are named, so any order is fine for components...
+        assert!(compile_component("component A(a?: string, b: int) { return <i /> }").is_ok());
+        // ...but server fn arguments are positional.
+        let err = compile_component("server fn f(a?: string, b: int) { return a }").unwrap_err();
+        match err {
+            CompileError::RequiredAfterOptional {
+                param, optional, ..
+            } => assert_eq!((param.as_str(), optional.as_str()), ("b", "a")),
+            other => panic!("expected ordering error, got {:?}", other),
+        }
+        let err = compile_component("page Post(slug = \"home\", id: string) { return <i /> }")
+            .unwrap_err();
+        assert!(matches!(err, CompileError::RequiredAfterOptional { .. }));
+    }
+
+    #[test]
+    fn reports_duplicate_declarations() {
+        let err = compile_component(
+            "component Card() { return <div /> }\ncomponent Card() { return <span /> }",
+        )
+        .unwrap_err();
+        assert!(matches!(
+            err,
+            CompileError::Module(ModuleError::Duplicate { ref name, .. }) if name == "Card"
+        ));
+    }
+
+    #[test]
+    fn reports_missing_return() {
+        let err = compile_component("component App() { const x = 1 }").unwrap_err();
+        assert!(
+            matches!(err, CompileError::MissingReturn { ref component, .. } if component == "App")
+        );
+        assert_eq!(err.code(), "R0402");
+    }
+
+    /// Compiles `stmt` as the first statement of a component and returns the
+    /// TypeScript printed for it.
+    fn compile_stmt(stmt: &str) -> String {
+        let source = format!("component App() {{\n  {}\n  return <i />\n}}", stmt);
+        let output = compile_component(&source).unwrap();
+        output.lines().nth(1).unwrap().trim().to_string()
+    }
+
+    #[test]
+    fn keeps_type_arguments_of_generic_calls() {
+        assert_eq!(
+            compile_stmt("const [s, setS] = useState<string>(\"\")"),
+            "const [s, setS] = useState<string>(\"\");"
+        );
+        assert_eq!(
+            compile_stmt("const ok = a < b && c > (d)"),
+            "const ok = a < b && c > (d);"
+        );
+    }
+
+    #[test]
+    fn compiles_regex_literals() {
+        assert_eq!(compile_stmt("/a+/.test(x)"), "/a+/.test(x);");
+        assert_eq!(
+            compile_stmt("const half = total / 2 / 1"),
+            "const half = total / 2 / 1;"
+        );
+    }
+
+    #[test]
+    fn passes_async_function_declarations_through() {
+        assert_eq!(
+            compile_stmt("async function load() {}"),
+            "async function load() {};"
+        );
+    }
+
+    #[test]
+    fn compiles_function_expressions() {
+        assert_eq!(
+            compile_stmt("const f = function () { return 1 }"),
+            "const f = function () { return 1; };"
+        );
+    }
+
+    #[test]
+    fn compiles_as_assertions() {
+        assert_eq!(
+            compile_stmt("const el = ref as HTMLElement"),
+            "const el = ref as HTMLElement;"
+        );
+    }
+
+    #[test]
+    fn compiles_non_null_assertions() {
+        assert_eq!(compile_stmt("const m = maybe!"), "const m = maybe!;");
+    }
+
+    #[test]
+    fn compiles_typed_arrow_functions() {
+        assert_eq!(
+            compile_stmt("const id = (x: number): number => x"),
+            "const id = (x: number): number => x;"
+        );
+        assert_eq!(
+            compile_stmt("const pick = (a?: string, ...rest: number[]) => a"),
+            "const pick = (a?: string, ...rest: number[]) => a;"
+        );
+    }
 }
@@ -0,0 +1,956 @@
+//! Language server for `.raven` files, spoken over stdin and stdout by
+//! `raven lsp`.
+//!
+//! Open documents are synced in full on every change and checked like
+//! `raven build` would. Hover, go-to-definition, completion and document
+//! symbols read the recovering parser's tree, so they keep working while a
+//! file has syntax errors. On the wire, lines are 0-based and columns count
+//! UTF-16 code units, as the protocol requires.
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::path::{Component as PathComponent, Path, PathBuf};
+
+use serde_json::{json, Value};
+
+use crate::ast::{Attr, Component, Item, Node, SourceFile};
+use crate::completion::{complete_with, CompletionKind};
+use crate::errors::{Diagnostic, Severity};
+use crate::token::{Span, Token, TokenKind};
+use crate::{ast, check, lex_source, parse_source_recovering};
+
+const METHOD_NOT_FOUND: i64 = -32601;
+const PARSE_ERROR: i64 = -32700;
+
+/// Serves the Language Server Protocol on `input` and `output` until the
+/// client sends `exit` or closes `input`.
+pub fn serve_lsp(mut input: impl BufRead, output: impl Write) -> io::Result<()> {
+    let mut server = Server {
+        output,
+        documents: HashMap::new(),
+    };
+    while let Some(body) = read_message(&mut input)? {
+        match serde_json::from_slice::<Value>(&body) {
+            Ok(message) if message["method"] == "exit" => break,
+            Ok(message) => server.handle(&message)?,
+            Err(err) => server.send(&json!({
+                "jsonrpc": "2.0",
+                "id": null,
+                "error": { "code": PARSE_ERROR, "message": err.to_string() },
+            }))?,
+        }
+    }
+    Ok(())
+}
+
+/// Reads the body of one `Content-Length` framed message, or `None` at the end
+/// of `input`.
+fn read_message(input: &mut impl BufRead) -> io::Result<Option<Vec<u8>>> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = value.trim().parse::<usize>().ok();
+            }
+        }
+    }
+    let length = length.ok_or_else(|| {
+        io::Error::new(io::ErrorKind::InvalidData, "missing Content-Length header")
+    })?;
+    let mut body = vec![0; length];
+    input.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+struct Server<W> {
+    output: W,
+    /// The text of every open document by URI.
+    documents: HashMap<String, String>,
+}
+
+impl<W: Write> Server<W> {
+    fn send(&mut self, message: &Value) -> io::Result<()> {
+        let body = message.to_string();
+        write!(
+            self.output,
+            "Content-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        )?;
+        self.output.flush()
+    }
+
+    fn handle(&mut self, message: &Value) -> io::Result<()> {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+        let Some(id) = message.get("id") else {
+            return self.notification(method, params);
+        };
+        if method.is_empty() {
+            // A response; the server sends no requests of its own.
+            return Ok(());
+        }
+        let result = match method {
+            "initialize" => Some(json!({
+                "capabilities": {
+                    // Full text on every change.
+                    "textDocumentSync": 1,
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "documentSymbolProvider": true,
+                    "completionProvider": { "triggerCharacters": ["<", ":", " "] },
+                },
+                "serverInfo": { "name": "raven", "version": crate::version() },
+            })),
+            "shutdown" => Some(Value::Null),
+            "textDocument/hover" => Some(self.hover(params).unwrap_or(Value::Null)),
+            "textDocument/definition" => Some(self.definition(params).unwrap_or(Value::Null)),
+            "textDocument/completion" => Some(self.completion(params).unwrap_or(Value::Null)),
+            "textDocument/documentSymbol" => {
+                Some(self.document_symbols(params).unwrap_or(Value::Null))
+            }
+            _ => None,
+        };
+        let response = match result {
+            Some(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            None => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": {
+                    "code": METHOD_NOT_FOUND,
+                    "message": format!("unsupported method `{}`", method),
+                },
+            }),
+        };
+        self.send(&response)
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> io::Result<()> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        let text = match method {
+            "textDocument/didOpen" => params["textDocument"]["text"].as_str(),
+            "textDocument/didChange" => params["contentChanges"]
+                .as_array()
+                .and_then(|changes| changes.last())
+                .and_then(|change| change["text"].as_str()),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                return self.publish(uri, Vec::new());
+            }
+            _ => None,
+        };
+        let Some(text) = text else {
+            return Ok(());
+        };
+        let diagnostics = match check(text) {
+            Ok(_) => Vec::new(),
+            Err(err) => err
+                .diagnostics()
+                .iter()
+                .map(|diagnostic| lsp_diagnostic(diagnostic, uri, text))
+                .collect(),
+        };
+        self.documents.insert(uri.to_string(), text.to_string());
+        self.publish(uri, diagnostics)
+    }
+
+    fn publish(&mut self, uri: &str, diagnostics: Vec<Value>) -> io::Result<()> {
+        self.send(&json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/publishDiagnostics",
+            "params": { "uri": uri, "diagnostics": diagnostics },
+        }))
+    }
+
+    /// The URI and text of the document a request is about, and the byte
+    /// offset of its `position`.
+    fn cursor<'a>(&'a self, params: &'a Value) -> Option<(&'a str, &'a str, usize)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let source = self.documents.get(uri)?;
+        let position = &params["position"];
+        let offset = offset(
+            source,
+            position["line"].as_u64()? as usize,
+            position["character"].as_u64()? as usize,
+        );
+        Some((uri, source, offset))
+    }
+
+    fn hover(&self, params: &Value) -> Option<Value> {
+        let (uri, source, offset) = self.cursor(params)?;
+        let parsed = Parsed::new(source)?;
+        let (target, span) = parsed.target_at(offset)?;
+        let definition = self.find_component(uri, source, &parsed.file, target.component())?;
+        let (source, component) = (&definition.source, definition.component?);
+        let contents = match target {
+            Target::Component(_) => format!("```raven\n{}\n```", component.signature(source)),
+            Target::Prop {
+                component: name,
+                prop,
+            } => {
+                let param = component.params.iter().find(|p| p.name.name == prop)?;
+                format!(
+                    "```raven\n{}\n```\nProp of `{}`",
+                    text(source, param.span),
+                    name
+                )
+            }
+        };
+        Some(json!({
+            "contents": { "kind": "markdown", "value": contents },
+            "range": range(parsed.source, span),
+        }))
+    }
+
+    fn definition(&self, params: &Value) -> Option<Value> {
+        let (uri, source, offset) = self.cursor(params)?;
+        let parsed = Parsed::new(source)?;
+        let (target, _) = parsed.target_at(offset)?;
+        let definition = self.find_component(uri, source, &parsed.file, target.component())?;
+        let span = match (&target, &definition.component) {
+            (Target::Prop { prop, .. }, Some(component)) => {
+                component
+                    .params
+                    .iter()
+                    .find(|p| p.name.name == *prop)?
+                    .name
+                    .span
+            }
+            _ => definition.name,
+        };
+        Some(json!({
+            "uri": definition.uri,
+            "range": range(&definition.source, span),
+        }))
+    }
+
+    fn completion(&self, params: &Value) -> Option<Value> {
+        let (uri, source, offset) = self.cursor(params)?;
+        let resolve = |path: &str| self.resolve(uri, path).map(|(_, text)| text);
+        let completions = complete_with(source, offset, &resolve);
+        let replaced = byte_range(source, completions.start, offset);
+        let items: Vec<Value> = completions
+            .items
+            .into_iter()
+            .map(|item| {
+                let kind = match item.kind {
+                    CompletionKind::Component => CLASS_ITEM,
+                    CompletionKind::Prop | CompletionKind::Attribute => PROPERTY_ITEM,
+                    CompletionKind::Element => KEYWORD_ITEM,
+                    CompletionKind::Directive => EVENT_ITEM,
+                };
+                json!({
+                    "label": item.label,
+                    "kind": kind,
+                    "detail": item.detail,
+                    "textEdit": { "range": replaced, "newText": item.label },
+                })
+            })
+            .collect();
+        Some(Value::from(items))
+    }
+
+    fn document_symbols(&self, params: &Value) -> Option<Value> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let source = self.documents.get(uri)?;
+        let parsed = Parsed::new(source)?;
+        let symbol = |name: &str, detail: &str, kind: u8, span: Span, selection: Span| {
+            json!({
+                "name": name,
+                "detail": detail,
+                "kind": kind,
+                "range": range(source, span),
+                "selectionRange": range(source, selection),
+            })
+        };
+        let symbols = parsed
+            .file
+            .items
+            .iter()
+            .filter_map(|item| {
+                let (keyword, kind, name, params, span) = match item {
+                    Item::Component(c) => ("component", CLASS, &c.name, &c.params, c.span),
+                    Item::Page(c) => ("page", CLASS, &c.name, &c.params, c.span),
+                    Item::ServerFn(f) => ("server fn", FUNCTION, &f.name, &f.params, f.span),
+                    Item::TypeDecl(decl) => {
+                        let mut entry =
+                            symbol(&decl.name.name, "type", STRUCT, decl.span, decl.name.span);
+                        if let ast::TypeDeclBody::Fields(fields) = &decl.body {
+                            entry["children"] = fields
+                                .iter()
+                                .map(|field| {
+                                    symbol(
+                                        &field.name.name,
+                                        text(source, field.ty.span),
+                                        FIELD,
+                                        field.span,
+                                        field.name.span,
+                                    )
+                                })
+                                .collect();
+                        }
+                        return Some(entry);
+                    }
+                    Item::Import(_) => return None,
+                };
+                let mut entry = symbol(&name.name, keyword, kind, span, name.span);
+                entry["children"] = params
+                    .iter()
+                    .map(|param| {
+                        let ty = param.ty.as_ref().map_or("", |ty| text(source, ty.span));
+                        symbol(&param.name.name, ty, PROPERTY, param.span, param.name.span)
+                    })
+                    .collect();
+                Some(entry)
+            })
+            .collect();
+        Some(symbols)
+    }
+
+    /// Where component `name`, used in the document at `uri`, is declared:
+    /// in the document itself, or in the `.raven` file it is imported from.
+    /// An import that cannot be followed is its own definition.
+    fn find_component(
+        &self,
+        uri: &str,
+        source: &str,
+        file: &SourceFile,
+        name: &str,
+    ) -> Option<Definition> {
+        if let Some(component) = file.component(name) {
+            return Some(Definition::new(uri, source, component));
+        }
+        let (import, ident) = file.import_of(name)?;
+        if let Some((target_uri, target)) = self.resolve(uri, &import.source) {
+            if let Some(parsed) = Parsed::new(&target) {
+                let exported = if import.default.as_ref() == Some(ident) {
+                    parsed.file.page()
+                } else {
+                    parsed.file.component(name)
+                };
+                if let Some(component) = exported {
+                    return Some(Definition::new(&target_uri, &target, component));
+                }
+            }
+        }
+        Some(Definition {
+            uri: uri.to_string(),
+            source: source.to_string(),
+            name: ident.span,
+            component: None,
+        })
+    }
+
+    /// The URI and text of the `.raven` file that `import_source` names from
+    /// the document at `uri`, preferring the open document over the disk.
+    fn resolve(&self, uri: &str, import_source: &str) -> Option<(String, String)> {
+        if !import_source.starts_with('.') {
+            return None;
+        }
+        let dir = uri_to_path(uri)?.parent()?.to_path_buf();
+        let mut path = PathBuf::new();
+        for part in dir.join(import_source).components() {
+            match part {
+                PathComponent::CurDir => {}
+                PathComponent::ParentDir => {
+                    path.pop();
+                }
+                other => path.push(other),
+            }
+        }
+        if path.extension().is_none_or(|ext| ext != "raven") {
+            let mut name = path.file_name()?.to_os_string();
+            name.push(".raven");
+            path.set_file_name(name);
+        }
+        let target_uri = path_to_uri(&path);
+        let text = match self.documents.get(&target_uri) {
+            Some(text) => text.clone(),
+            None => std::fs::read_to_string(&path).ok()?,
+        };
+        Some((target_uri, text))
+    }
+}
+
+// `SymbolKind` values from the protocol.
+const CLASS: u8 = 5;
+const PROPERTY: u8 = 7;
+const FIELD: u8 = 8;
+const FUNCTION: u8 = 12;
+const STRUCT: u8 = 23;
+
+// `CompletionItemKind` values from the protocol.
+const CLASS_ITEM: u8 = 7;
+const PROPERTY_ITEM: u8 = 10;
+const KEYWORD_ITEM: u8 = 14;
+const EVENT_ITEM: u8 = 23;
+
+/// A document lexed and parsed as far as it goes.
+struct Parsed<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    file: SourceFile,
+}
+
+impl<'a> Parsed<'a> {
+    /// `None` when the document does not lex.
+    fn new(source: &'a str) -> Option<Self> {
+        let tokens = lex_source(source).ok()?;
+        let (file, _) = parse_source_recovering(&tokens, source);
+        Some(Self {
+            source,
+            tokens,
+            file,
+        })
+    }
+
+    /// The component name or prop at byte `offset`, and where it is written.
+    fn target_at(&self, offset: usize) -> Option<(Target, Span)> {
+        let touches = |span: Span| span.offset <= offset && offset <= span.end_offset();
+        for item in &self.file.items {
+            if let Item::Component(c) | Item::Page(c) = item {
+                if touches(c.name.span) {
+                    return Some((Target::Component(c.name.name.clone()), c.name.span));
+                }
+            }
+        }
+        let mut found = None;
+        for block in self.file.items.iter().filter_map(|item| match item {
+            Item::Component(c) | Item::Page(c) => Some(&c.body),
+            Item::ServerFn(f) => Some(&f.body),
+            _ => None,
+        }) {
+            ast::visit_nodes(block, &mut |node| {
+                let Node::Element {
+                    name, attrs, span, ..
+                } = node
+                else {
+                    return;
+                };
+                if found.is_some() || !touches(*span) || !is_component(name) {
+                    return;
+                }
+                if let Some(tag) = self.tag_names(*span).into_iter().find(|s| touches(*s)) {
+                    found = Some((Target::Component(name.clone()), tag));
+                    return;
+                }
+                for attr in attrs {
+                    if let Attr::Named {
+                        name: prop, span, ..
+                    } = attr
+                    {
+                        let prop_span = Span {
+                            len: prop.len(),
+                            end_line: span.line,
+                            end_col: span.col + prop.chars().count(),
+                            ..*span
+                        };
+                        if touches(prop_span) {
+                            found = Some((
+                                Target::Prop {
+                                    component: name.clone(),
+                                    prop: prop.clone(),
+                                },
+                                prop_span,
+                            ));
+                        }
+                    }
+                }
+            });
+        }
+        found
+    }
+
+    /// Where the element spanning `element` has its name written: in the
+    /// opening tag and, unless it closes itself, in the closing one.
+    fn tag_names(&self, element: Span) -> Vec<Span> {
+        let tokens = &self.tokens;
+        let open = tokens.partition_point(|t| t.span.offset <= element.offset);
+        let end = tokens.partition_point(|t| t.span.offset < element.end_offset());
+        let is_ident = |index: usize| {
+            tokens
+                .get(index)
+                .is_some_and(|t| matches!(t.kind, TokenKind::Ident(_)))
+        };
+        let mut names = Vec::new();
+        if is_ident(open) {
+            names.push(tokens[open].span);
+        }
+        if end >= 3
+            && end - 2 > open
+            && is_ident(end - 2)
+            && tokens[end - 3].kind == TokenKind::Slash
+        {
+            names.push(tokens[end - 2].span);
+        }
+        names
+    }
+}
+
+/// What a hover or go-to-definition request points at.
+enum Target {
+    /// A component's name, in its declaration or in a tag.
+    Component(String),
+    /// An attribute passed to a component.
+    Prop { component: String, prop: String },
+}
+
+impl Target {
+    fn component(&self) -> &str {
+        match self {
+            Target::Component(name)
+            | Target::Prop {
+                component: name, ..
+            } => name,
+        }
+    }
+}
+
+/// A component's declaration, or the import naming it when the imported file
+/// cannot be read.
+struct Definition {
+    uri: String,
+    source: String,
+    name: Span,
+    component: Option<Component>,
+}
+
+impl Definition {
+    fn new(uri: &str, source: &str, component: &Component) -> Self {
+        Self {
+            uri: uri.to_string(),
+            source: source.to_string(),
+            name: component.name.span,
+            component: Some(component.clone()),
+        }
+    }
+}
+
+/// Tags starting with an upper case letter render components rather than HTML
+/// elements.
+fn is_component(tag: &str) -> bool {
+    tag.starts_with(|ch: char| ch.is_ascii_uppercase())
+}
+
+fn text(source: &str, span: Span) -> &str {
+    source
+        .get(span.offset..span.end_offset())
+        .unwrap_or_default()
+}
+
+fn lsp_diagnostic(diagnostic: &Diagnostic, uri: &str, source: &str) -> Value {
+    let span = diagnostic.primary().map(|label| label.span);
+    let mut message = diagnostic.message.clone();
+    for note in &diagnostic.notes {
+        message.push_str("\nnote: ");
+        message.push_str(note);
+    }
+    let related: Vec<Value> = diagnostic
+        .labels
+        .iter()
+        .filter(|label| !label.primary)
+        .map(|label| {
+            json!({
+                "location": { "uri": uri, "range": range(source, label.span) },
+                "message": label.message,
+            })
+        })
+        .collect();
+    json!({
+        "range": match span {
+            Some(span) => range(source, span),
+            None => json!({ "start": position(source, 0), "end": position(source, 0) }),
+        },
+        "severity": match diagnostic.severity {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+        },
+        "code": diagnostic.code,
+        "source": "raven",
+        "message": message,
+        "relatedInformation": related,
+    })
+}
+
+fn range(source: &str, span: Span) -> Value {
+    byte_range(source, span.offset, span.end_offset())
+}
+
+fn byte_range(source: &str, start: usize, end: usize) -> Value {
+    json!({ "start": position(source, start), "end": position(source, end) })
+}
+
+/// The protocol position of byte `offset`.
+fn position(source: &str, offset: usize) -> Value {
+    let mut offset = offset.min(source.len());
+    while !source.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let line_start = source[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+    json!({
+        "line": source[..line_start].matches('\n').count(),
+        "character": source[line_start..offset].encode_utf16().count(),
+    })
+}
+
+/// The byte offset of a protocol position, clamped to its line.
+fn offset(source: &str, line: usize, character: usize) -> usize {
+    let line_start = match line {
+        0 => 0,
+        _ => match source.match_indices('\n').nth(line - 1) {
+            Some((idx, _)) => idx + 1,
+            None => return source.len(),
+        },
+    };
+    let mut units = 0;
+    for (idx, ch) in source[line_start..].char_indices() {
+        if units >= character || ch == '\n' {
+            return line_start + idx;
+        }
+        units += ch.len_utf16();
+    }
+    source.len()
+}
+
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let encoded = uri.strip_prefix("file://")?.as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let hex = encoded
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (encoded[i], hex) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(bytes).ok().map(PathBuf::from)
+}
+
+fn path_to_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARD_URI: &str = "file:///project/src/components/Card.raven";
+    const CARD: &str =
+        "component Card(title: string, count?: int = 0) {\n  return <h2>{title}</h2>\n}\n";
+    const INDEX_URI: &str = "file:///project/src/pages/index.raven";
+    const INDEX: &str = "import { Card } from \"../components/Card\"\n\
+                         import { Icon } from \"icons\"\n\
+                         \n\
+                         component Local(label: string) {\n  return <span>{label}</span>\n}\n\
+                         \n\
+                         page Index() {\n  return <main><Local label=\"a\" /><Card title=\"Hi\"></Card><Icon /></main>\n}\n";
+
+    /// Feeds `messages` to a server and returns everything it sent back.
+    fn session(messages: &[Value]) -> Vec<Value> {
+        let input: String = messages
+            .iter()
+            .map(|message| {
+                let body = message.to_string();
+                format!("Content-Length: {}\r\n\r\n{}", body.len(), body)
+            })
+            .collect();
+        let mut output = Vec::new();
+        serve_lsp(input.as_bytes(), &mut output).unwrap();
+        let mut replies = Vec::new();
+        let mut reader = output.as_slice();
+        while let Some(body) = read_message(&mut reader).unwrap() {
+            replies.push(serde_json::from_slice(&body).unwrap());
+        }
+        replies
+    }
+
+    fn open(uri: &str, text: &str) -> Value {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "textDocument/didOpen",
+            "params": { "textDocument": { "uri": uri, "languageId": "raven", "version": 1, "text": text } },
+        })
+    }
+
+    /// A request about the `nth` occurrence of `needle` in `text`, positioned
+    /// one character into it.
+    fn request(id: u64, method: &str, uri: &str, text: &str, needle: &str, nth: usize) -> Value {
+        let offset = text.match_indices(needle).nth(nth).unwrap().0 + 1;
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": { "textDocument": { "uri": uri }, "position": position(text, offset) },
+        })
+    }
+
+    fn result(replies: &[Value], id: u64) -> &Value {
+        &replies.iter().find(|reply| reply["id"] == id).unwrap()["result"]
+    }
+
+    fn location(uri: &str, start: (u64, u64), end: (u64, u64)) -> Value {
+        json!({
+            "uri": uri,
+            "range": {
+                "start": { "line": start.0, "character": start.1 },
+                "end": { "line": end.0, "character": end.1 },
+            },
+        })
+    }
+
+    #[test]
+    fn publishes_diagnostics_as_documents_change() {
+        let uri = "file:///app.raven";
+        let replies = session(&[
+            open(uri, "component App() {\n  return <p>hi</span>\n}\n"),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didChange",
+                "params": {
+                    "textDocument": { "uri": uri, "version": 2 },
+                    "contentChanges": [{ "text": "component App() {\n  return <p>hi</p>\n}\n" }],
+                },
+            }),
+            json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/didClose",
+                "params": { "textDocument": { "uri": uri } },
+            }),
+        ]);
+        assert_eq!(replies.len(), 3);
+        assert!(replies
+            .iter()
+            .all(|reply| reply["method"] == "textDocument/publishDiagnostics"
+                && reply["params"]["uri"] == uri));
+        assert_eq!(
+            replies[0]["params"]["diagnostics"],
+            json!([{
+                "range": {
+                    "start": { "line": 1, "character": 16 },
+                    "end": { "line": 1, "character": 20 },
+                },
+                "severity": 1,
+                "code": "R0203",
+                "source": "raven",
+                "message": "expected `</p>` but found `</span>`",
+                "relatedInformation": [],
+            }])
+        );
+        assert_eq!(replies[1]["params"]["diagnostics"], json!([]));
+        assert_eq!(replies[2]["params"]["diagnostics"], json!([]));
+    }
+
+    #[test]
+    fn counts_columns_in_utf16_code_units() {
+        let source = "é😀x\ny";
+        assert_eq!(position(source, 7), json!({ "line": 0, "character": 4 }));
+        assert_eq!(offset(source, 0, 3), 6);
+        assert_eq!(offset(source, 0, 99), 7);
+        assert_eq!(offset(source, 1, 0), 8);
+        assert_eq!(offset(source, 5, 0), source.len());
+    }
+
+    #[test]
+    fn hovers_over_component_tags_and_props() {
+        let replies = session(&[
+            open(CARD_URI, CARD),
+            open(INDEX_URI, INDEX),
+            request(1, "textDocument/hover", INDEX_URI, INDEX, "<Local", 0),
+            request(2, "textDocument/hover", INDEX_URI, INDEX, "label=", 0),
+            request(3, "textDocument/hover", INDEX_URI, INDEX, "Card", 2),
+            request(4, "textDocument/hover", INDEX_URI, INDEX, "main", 0),
+            request(5, "textDocument/hover", INDEX_URI, INDEX, "Index", 0),
+        ]);
+        assert_eq!(
+            result(&replies, 1)["contents"]["value"],
+            "```raven\ncomponent Local(label: string)\n```"
+        );
+        assert_eq!(
+            result(&replies, 1)["range"],
+            json!({
+                "start": { "line": 8, "character": 16 },
+                "end": { "line": 8, "character": 21 },
+            })
+        );
+        assert_eq!(
+            result(&replies, 2)["contents"]["value"],
+            "```raven\nlabel: string\n```\nProp of `Local`"
+        );
+        assert_eq!(
+            result(&replies, 3)["contents"]["value"],
+            "```raven\ncomponent Card(title: string, count?: int = 0)\n```"
+        );
+        assert_eq!(result(&replies, 4), &Value::Null);
+        assert_eq!(
+            result(&replies, 5)["contents"]["value"],
+            "```raven\npage Index()\n```"
+        );
+    }
+
+    #[test]
+    fn goes_to_the_definitions_of_components_and_props() {
+        let dir = std::env::temp_dir().join(format!("raven-lsp-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("components")).unwrap();
+        std::fs::create_dir_all(dir.join("pages")).unwrap();
+        std::fs::write(dir.join("components/Card.raven"), CARD).unwrap();
+        let index_uri = path_to_uri(&dir.join("pages/index.raven"));
+        let card_uri = path_to_uri(&dir.join("components/Card.raven"));
+
+        let replies = session(&[
+            open(&index_uri, INDEX),
+            request(1, "textDocument/definition", &index_uri, INDEX, "<Local", 0),
+            request(2, "textDocument/definition", &index_uri, INDEX, "/Card>", 0),
+            request(3, "textDocument/definition", &index_uri, INDEX, "title", 0),
+            request(4, "textDocument/definition", &index_uri, INDEX, "<Icon", 0),
+            request(5, "textDocument/definition", &index_uri, INDEX, "return", 1),
+        ]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(result(&replies, 1), &location(&index_uri, (3, 10), (3, 15)));
+        assert_eq!(result(&replies, 2), &location(&card_uri, (0, 10), (0, 14)));
+        assert_eq!(result(&replies, 3), &location(&card_uri, (0, 15), (0, 20)));
+        // Package imports are not followed.
+        assert_eq!(result(&replies, 4), &location(&index_uri, (1, 9), (1, 13)));
+        assert_eq!(result(&replies, 5), &Value::Null);
+    }
+
+    #[test]
+    fn completes_props_of_imported_components() {
+        let source = "import { Card } from \"../components/Card\"\n\n\
+                      component App() {\n  return <Card c";
+        let mut completion = request(1, "textDocument/completion", INDEX_URI, source, "c", 0);
+        // At the end, right after the `c`.
+        completion["params"]["position"] = position(source, source.len());
+        let replies = session(&[open(CARD_URI, CARD), open(INDEX_URI, source), completion]);
+        assert_eq!(
+            result(&replies, 1),
+            &json!([{
+                "label": "count",
+                "kind": PROPERTY_ITEM,
+                "detail": "count?: int = 0",
+                "textEdit": {
+                    "range": {
+                        "start": { "line": 3, "character": 15 },
+                        "end": { "line": 3, "character": 16 },
+                    },
+                    "newText": "count",
+                },
+            }])
+        );
+    }
+
+    #[test]
+    fn keeps_answering_in_files_with_syntax_errors() {
+        let source = "component Local(label: string) {\n  return <span>{label}</span>\n}\n\
+                      component Broken() {\n  return <div><Local label=\"x\" /><p class=></div>\n}\n";
+        let replies = session(&[
+            open(INDEX_URI, source),
+            request(1, "textDocument/hover", INDEX_URI, source, "<Local", 0),
+        ]);
+        assert_eq!(replies[0]["params"]["diagnostics"][0]["code"], "R0201");
+        assert_eq!(
+            result(&replies, 1)["contents"]["value"],
+            "```raven\ncomponent Local(label: string)\n```"
+        );
+    }
+
+    #[test]
+    fn lists_document_symbols() {
+        let source = "type Todo {\n  id: uuid\n  done: bool\n}\n\
+                      server fn listTodos(limit: int) -> [Todo] {\n  return db.todo.findMany({ take: limit })\n}\n\
+                      component TodoList(todos: [Todo]) {\n  return <ul />\n}\n";
+        let replies = session(&[
+            open(INDEX_URI, source),
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "textDocument/documentSymbol",
+                "params": { "textDocument": { "uri": INDEX_URI } },
+            }),
+        ]);
+        // One line per symbol: its name, kind and `child: detail` children.
+        let outline: Vec<String> = result(&replies, 1)
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|symbol| {
+                let children: Vec<String> = symbol["children"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|child| format!("{}: {}", child["name"], child["detail"]))
+                    .collect();
+                format!(
+                    "{} {} {} ({})",
+                    symbol["name"],
+                    symbol["kind"],
+                    symbol["detail"],
+                    children.join(", ")
+                )
+            })
+            .collect();
+        assert_eq!(
+            outline,
+            [
+                r#""Todo" 23 "type" ("id": "uuid", "done": "bool")"#,
+                r#""listTodos" 12 "server fn" ("limit": "int")"#,
+                r#""TodoList" 5 "component" ("todos": "[Todo]")"#,
+            ]
+        );
+        assert_eq!(
+            result(&replies, 1)[2]["selectionRange"]["start"],
+            json!({ "line": 7, "character": 10 })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_requests_and_bad_json() {
+        let mut input = String::new();
+        for body in [
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "workspace/symbol", "params": {} })
+                .to_string(),
+            "{not json".to_string(),
+            json!({ "jsonrpc": "2.0", "method": "exit" }).to_string(),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "shutdown" }).to_string(),
+        ] {
+            input.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        }
+        let mut output = Vec::new();
+        serve_lsp(input.as_bytes(), &mut output).unwrap();
+        let mut reader = output.as_slice();
+        let mut replies = Vec::new();
+        while let Some(body) = read_message(&mut reader).unwrap() {
+            replies.push(serde_json::from_slice::<Value>(&body).unwrap());
+        }
+        // Nothing after `exit` is read.
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[0]["error"]["code"], METHOD_NOT_FOUND);
+        assert_eq!(replies[1]["error"]["code"], PARSE_ERROR);
+        assert_eq!(replies[1]["id"], Value::Null);
+    }
+}
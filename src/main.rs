@@ -1,16 +1,31 @@
 use std::fs;
-use std::path::PathBuf;
+use std::io::{self, IsTerminal};
+use std::path::{Component, Path, PathBuf};
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use thiserror::Error;
 
-use ravensone::{compile_component, CompileError};
+use ravensone::{
+    compile_component, compile_component_pretty, compile_rpc, compile_with_source_map, explain,
+    format_source, serve_lsp, CompileError, Diagnostic,
+};
 
 #[derive(Parser)]
 #[command(author, version, about = "RavensOne compiler", long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    /// How to report errors
+    #[arg(long, global = true, value_enum, default_value_t = MessageFormat::Human)]
+    message_format: MessageFormat,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum MessageFormat {
+    /// Source snippets on stderr
+    Human,
+    /// One JSON object per diagnostic and line on stdout
+    Json,
 }
 
 #[derive(Subcommand)]
@@ -22,88 +37,315 @@ enum Commands {
         /// Output path for generated TSX
         #[arg(short, long)]
         output: Option<PathBuf>,
+        /// Indent markup across lines instead of printing it on one
+        #[arg(long)]
+        pretty: bool,
+        /// Also write a `.map` source map next to the output
+        #[arg(long)]
+        source_map: bool,
+    },
+    /// Explain an error code, e.g. `raven explain R0203`
+    Explain {
+        /// The code from an `error[...]` header
+        code: String,
     },
+    /// Format .raven files in place
+    Fmt {
+        /// Files, or directories to search for .raven files; defaults to the
+        /// current directory
+        paths: Vec<PathBuf>,
+        /// List the files that need formatting instead of writing them, and
+        /// fail if there are any
+        #[arg(long)]
+        check: bool,
+    },
+    /// Run the language server over stdin and stdout
+    Lsp,
 }
 
 #[derive(Debug, Error)]
 enum CliError {
-    #[error(transparent)]
-    Compile(#[from] CompileError),
+    /// A compile error in `contents`, which was read from `path`.
+    #[error("{error}")]
+    Compile {
+        path: PathBuf,
+        contents: String,
+        error: Box<CompileError>,
+    },
+    #[error("no error has the code `{0}`")]
+    UnknownCode(String),
+    #[error("{} not formatted", files(*.0))]
+    Unformatted(usize),
+    #[error("could not format {}", files(*.0))]
+    Unformattable(usize),
     #[error("io error: {0}")]
     Io(#[from] std::io::Error),
 }
 
 fn main() {
     let cli = Cli::parse();
+    let format = cli.message_format;
     if let Err(err) = run(cli) {
-        report_error(&err);
+        report_error(&err, format);
         std::process::exit(1);
     }
 }
 
 fn run(cli: Cli) -> Result<(), CliError> {
+    // JSON output leaves stdout to the diagnostics.
+    let verbose = cli.message_format == MessageFormat::Human;
     match cli.command {
-        Commands::Build { input, output } => build(input, output),
+        Commands::Build {
+            input,
+            output,
+            pretty,
+            source_map,
+        } => build(input, output, pretty, source_map, verbose),
+        Commands::Explain { code } => {
+            let text = explain(&code).ok_or(CliError::UnknownCode(code))?;
+            print!("{}", text);
+            Ok(())
+        }
+        Commands::Fmt { paths, check } => fmt(paths, check, cli.message_format),
+        Commands::Lsp => Ok(serve_lsp(
+            std::io::stdin().lock(),
+            std::io::stdout().lock(),
+        )?),
     }
 }
 
-fn build(input: PathBuf, output: Option<PathBuf>) -> Result<(), CliError> {
+fn build(
+    input: PathBuf,
+    output: Option<PathBuf>,
+    pretty: bool,
+    source_map: bool,
+    verbose: bool,
+) -> Result<(), CliError> {
+    let status = |message: String| {
+        if verbose {
+            println!("{}", message);
+        }
+    };
     let source = fs::read_to_string(&input)?;
-    let generated = compile_component(&source)?;
+    let located = |error| CliError::Compile {
+        path: input.clone(),
+        contents: source.clone(),
+        error: Box::new(error),
+    };
     let out_path = output.unwrap_or_else(|| input.with_extension("tsx"));
     if let Some(parent) = out_path.parent() {
         if !parent.as_os_str().is_empty() {
             fs::create_dir_all(parent)?;
         }
     }
-    fs::write(&out_path, generated)?;
-    println!("Compiled {} -> {}", input.display(), out_path.display());
+    if source_map {
+        let file = out_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let map_path = out_path.with_file_name(format!("{}.map", file));
+        let out_dir = out_path.parent().unwrap_or(Path::new(""));
+        let source_name = relative_path(out_dir, &input)?;
+        let mapped =
+            compile_with_source_map(&source, pretty, &file, &source_name).map_err(located)?;
+        fs::write(
+            &out_path,
+            format!("{}//# sourceMappingURL={}.map\n", mapped.code, file),
+        )?;
+        fs::write(&map_path, mapped.map)?;
+        status(format!(
+            "Compiled {} -> {}",
+            input.display(),
+            out_path.display()
+        ));
+        status(format!("Generated {}", map_path.display()));
+    } else {
+        let generated = if pretty {
+            compile_component_pretty(&source)
+        } else {
+            compile_component(&source)
+        }
+        .map_err(located)?;
+        fs::write(&out_path, generated)?;
+        status(format!(
+            "Compiled {} -> {}",
+            input.display(),
+            out_path.display()
+        ));
+    }
+
+    let stem = out_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    if let Some(rpc) = compile_rpc(&source, &format!("./{}", stem)).map_err(located)? {
+        for (suffix, code) in [("server", rpc.server), ("client", rpc.client)] {
+            let path = out_path.with_file_name(format!("{}.{}.ts", stem, suffix));
+            fs::write(&path, code)?;
+            status(format!("Generated {}", path.display()));
+        }
+    }
     Ok(())
 }
 
-fn report_error(err: &CliError) {
-    match err {
-        CliError::Compile(comp) => match comp {
-            CompileError::Component(msg) => eprintln!("error: {}", msg),
-            CompileError::Lex(lex) => {
-                use ravensone::LexError;
-                match lex {
-                    LexError::Invalid(span) => {
-                        eprintln!(
-                            "lexer error at line {}, column {}: invalid token",
-                            span.line, span.col
-                        );
-                    }
-                }
+fn fmt(paths: Vec<PathBuf>, check: bool, format: MessageFormat) -> Result<(), CliError> {
+    let verbose = format == MessageFormat::Human;
+    let paths = if paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        paths
+    };
+    let mut files = Vec::new();
+    for path in &paths {
+        raven_files(path, &mut files)?;
+    }
+    let mut unformatted = 0;
+    let mut failed = 0;
+    for path in files {
+        let source = fs::read_to_string(&path)?;
+        let formatted = match format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(error) => {
+                let error = CliError::Compile {
+                    path,
+                    contents: source,
+                    error: Box::new(error),
+                };
+                report_error(&error, format);
+                failed += 1;
+                continue;
             }
-            CompileError::Parse(parse) => {
-                use ravensone::ParseError;
-                match parse {
-                    ParseError::Unexpected { span, expected, .. } => {
-                        eprintln!(
-                            "parse error at line {}, column {}: expected {}",
-                            span.line, span.col, expected
-                        );
-                    }
-                    ParseError::UnclosedTag(name, span) => {
-                        eprintln!(
-                            "parse error at line {}, column {}: unclosed <{}> tag",
-                            span.line, span.col, name
-                        );
-                    }
-                    ParseError::MismatchedClose {
-                        expected,
-                        found,
-                        span,
-                    } => {
-                        eprintln!(
-                            "parse error at line {}, column {}: expected </{}> but found </{}>",
-                            span.line, span.col, expected, found
-                        );
-                    }
+        };
+        if formatted == source {
+            continue;
+        }
+        unformatted += 1;
+        if check {
+            match format {
+                MessageFormat::Human => println!("Would reformat {}", path.display()),
+                MessageFormat::Json => {
+                    let diagnostic = Diagnostic::error("file is not formatted");
+                    println!(
+                        "{}",
+                        diagnostic.to_json(&path.display().to_string(), &source)
+                    );
                 }
             }
-        },
+        } else {
+            fs::write(&path, formatted)?;
+            if verbose {
+                println!("Formatted {}", path.display());
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(CliError::Unformattable(failed));
+    }
+    if check && unformatted > 0 {
+        return Err(CliError::Unformatted(unformatted));
+    }
+    Ok(())
+}
+
+/// Adds `path` if it is a file, or else the `.raven` files below it, leaving
+/// out hidden directories and `node_modules`.
+fn raven_files(path: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return Ok(());
+    }
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<io::Result<Vec<_>>>()?;
+    entries.sort();
+    for entry in entries {
+        let name = entry.file_name().unwrap_or_default().to_string_lossy();
+        if entry.is_dir() {
+            if !name.starts_with('.') && name != "node_modules" {
+                raven_files(&entry, files)?;
+            }
+        } else if entry.extension().is_some_and(|ext| ext == "raven") {
+            files.push(entry);
+        }
+    }
+    Ok(())
+}
+
+/// "1 file" or "`count` files".
+fn files(count: usize) -> String {
+    if count == 1 {
+        "1 file".to_string()
+    } else {
+        format!("{} files", count)
+    }
+}
+
+/// The path of `target` as seen from directory `from`, with `/` separators as
+/// source maps expect.
+fn relative_path(from: &Path, target: &Path) -> Result<String, CliError> {
+    let from = std::path::absolute(from)?;
+    let target = std::path::absolute(target)?;
+    let from: Vec<Component> = from.components().collect();
+    let target: Vec<Component> = target.components().collect();
+    let common = from.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let parts: Vec<String> = std::iter::repeat_n("..".to_string(), from.len() - common)
+        .chain(
+            target[common..]
+                .iter()
+                .map(|part| part.as_os_str().to_string_lossy().into_owned()),
+        )
+        .collect();
+    Ok(parts.join("/"))
+}
+
+fn report_error(err: &CliError, format: MessageFormat) {
+    match err {
+        CliError::Compile {
+            path,
+            contents,
+            error,
+        } if format == MessageFormat::Json => {
+            let path = path.display().to_string();
+            for diagnostic in error.diagnostics() {
+                println!("{}", diagnostic.to_json(&path, contents));
+            }
+        }
+        CliError::Compile {
+            path,
+            contents,
+            error,
+        } => {
+            let color = std::io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+            let path = path.display().to_string();
+            let diagnostics = error.diagnostics();
+            let rendered: Vec<String> = diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.render(&path, contents, color))
+                .collect();
+            eprint!("{}", rendered.join("\n"));
+            if diagnostics.len() > 1 {
+                eprintln!("\nerror: aborting due to {} errors", diagnostics.len());
+            }
+            let mut codes: Vec<&str> = diagnostics.iter().filter_map(|d| d.code).collect();
+            codes.sort_unstable();
+            codes.dedup();
+            match codes.as_slice() {
+                [] => {}
+                [code] => eprintln!(
+                    "\nFor more information about this error, try `raven explain {}`.",
+                    code
+                ),
+                [first, ..] => eprintln!(
+                    "\nSome errors have detailed explanations: {}.\n\
+                     For more information about an error, try `raven explain {}`.",
+                    codes.join(", "),
+                    first
+                ),
+            }
+        }
+        CliError::UnknownCode(_) | CliError::Unformatted(_) | CliError::Unformattable(_) => {
+            eprintln!("error: {}", err)
+        }
         CliError::Io(io_err) => eprintln!("io error: {}", io_err),
     }
 }
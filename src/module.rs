@@ -1,6 +1,160 @@
-//! Placeholder module for RavensOne.
+//! Module-level view of a source file: the names it declares and imports.
 
-/// Initializes module-specific resources.
-pub fn init() -> &'static str {
-    "initialized"
+use crate::ast::{Item, SourceFile};
+use crate::token::Span;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ModuleError {
+    #[error("`{name}` is defined more than once (first at {first:?}, again at {second:?})")]
+    Duplicate {
+        name: String,
+        first: Span,
+        second: Span,
+    },
+    #[error("only one `page` is allowed per file (first at {first:?}, again at {second:?})")]
+    MultiplePages { first: Span, second: Span },
+}
+
+impl ModuleError {
+    /// The stable code `raven explain` knows this error by.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ModuleError::Duplicate { .. } => "R0301",
+            ModuleError::MultiplePages { .. } => "R0302",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Component,
+    Page,
+    ServerFn,
+    Type,
+    Import,
+}
+
+/// A name bound at the top level of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// Where the name itself is written.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Module {
+    pub file: SourceFile,
+    pub symbols: Vec<Symbol>,
+}
+
+impl Module {
+    /// Collects the file's top-level names, rejecting duplicates and files that
+    /// declare more than one page (each page is its module's default export).
+    pub fn new(file: SourceFile) -> Result<Self, ModuleError> {
+        let mut symbols: Vec<Symbol> = Vec::new();
+        let mut page: Option<Span> = None;
+        for item in &file.items {
+            let names = match item {
+                Item::Component(component) => vec![(&component.name, SymbolKind::Component)],
+                Item::Page(component) => {
+                    if let Some(first) = page {
+                        return Err(ModuleError::MultiplePages {
+                            first,
+                            second: component.keyword,
+                        });
+                    }
+                    page = Some(component.keyword);
+                    vec![(&component.name, SymbolKind::Page)]
+                }
+                Item::ServerFn(func) => vec![(&func.name, SymbolKind::ServerFn)],
+                Item::TypeDecl(decl) => vec![(&decl.name, SymbolKind::Type)],
+                Item::Import(import) => import
+                    .default
+                    .iter()
+                    .chain(&import.names)
+                    .map(|name| (name, SymbolKind::Import))
+                    .collect(),
+            };
+            for (ident, kind) in names {
+                if let Some(first) = symbols.iter().find(|s| s.name == ident.name) {
+                    return Err(ModuleError::Duplicate {
+                        name: ident.name.clone(),
+                        first: first.span,
+                        second: ident.span,
+                    });
+                }
+                symbols.push(Symbol {
+                    name: ident.name.clone(),
+                    kind,
+                    span: ident.span,
+                });
+            }
+        }
+        Ok(Self { file, symbols })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex_source;
+    use crate::parser::parse_source;
+
+    fn module(src: &str) -> Result<Module, ModuleError> {
+        let tokens = lex_source(src).unwrap();
+        Module::new(parse_source(&tokens, src).unwrap())
+    }
+
+    #[test]
+    fn collects_top_level_symbols() {
+        let module = module(
+            "import { TodoList } from \"./TodoList\"\n\
+             type Todo { id: uuid }\n\
+             server fn listTodos() -> [Todo] { return db.todo.findMany({}) }\n\
+             page Index() { return <TodoList /> }",
+        )
+        .unwrap();
+        let kinds: Vec<_> = module
+            .symbols
+            .iter()
+            .map(|s| (s.name.as_str(), s.kind))
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                ("TodoList", SymbolKind::Import),
+                ("Todo", SymbolKind::Type),
+                ("listTodos", SymbolKind::ServerFn),
+                ("Index", SymbolKind::Page),
+            ]
+        );
+        assert_eq!(module.symbols[1].span.line, 2);
+    }
+
+    #[test]
+    fn rejects_duplicate_names() {
+        let err =
+            module("import { Button } from \"./Button\"\ncomponent Button() { return <button /> }")
+                .unwrap_err();
+        match err {
+            ModuleError::Duplicate {
+                name,
+                first,
+                second,
+            } => {
+                assert_eq!(name, "Button");
+                assert_eq!((first.line, second.line), (1, 2));
+            }
+            other => panic!("expected duplicate error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_second_page() {
+        let err = module("page A() { return <a /> }\npage B() { return <b /> }").unwrap_err();
+        assert!(matches!(err, ModuleError::MultiplePages { .. }));
+    }
 }
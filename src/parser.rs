@@ -1,10 +1,45 @@
-use crate::ast::{Attr, AttrValue, Document, Node};
+use crate::ast::{
+    ArrowBody, Attr, AttrValue, Block, Case, Catch, Code, Component, Directive, DirectiveKind,
+    Document, Else, Expr, ExprKind, Field, ForHead, ForStmt, Function, Ident, IfStmt, Import, Item,
+    Node, Param, Prop, PropKey, Return, ServerFn, SourceFile, Stmt, SwitchStmt, TemplatePart,
+    Throw, TryStmt, Type, TypeDecl, TypeDeclBody, TypeKind, VarDecl, WhileStmt,
+};
+use crate::entities;
+use crate::lexer::lex_source;
 use crate::token::{Span, Token, TokenKind};
 use thiserror::Error;
 
 pub fn parse(tokens: &[Token], src: &str) -> Result<Document, ParseError> {
+    first_error(parse_recovering(tokens, src))
+}
+
+/// Parses the tokens of a whole `.raven` file (see [`crate::lex_source`]).
+pub fn parse_source(tokens: &[Token], src: &str) -> Result<SourceFile, ParseError> {
+    first_error(parse_source_recovering(tokens, src))
+}
+
+/// Like [`parse`], but keeps going after an error. Returns the partial document,
+/// with [`Node::Error`] wherever markup could not be parsed, and every error
+/// in the order it was found.
+pub fn parse_recovering(tokens: &[Token], src: &str) -> (Document, Vec<ParseError>) {
+    let mut parser = Parser::new(tokens, src);
+    let doc = parser.parse_document();
+    (doc, parser.errors)
+}
+
+/// Like [`parse_source`], but keeps going after an error. Items that cannot be
+/// parsed are left out of the returned file.
+pub fn parse_source_recovering(tokens: &[Token], src: &str) -> (SourceFile, Vec<ParseError>) {
     let mut parser = Parser::new(tokens, src);
-    parser.parse_document()
+    let file = parser.parse_source_file();
+    (file, parser.errors)
+}
+
+fn first_error<T>((parsed, errors): (T, Vec<ParseError>)) -> Result<T, ParseError> {
+    match errors.into_iter().next() {
+        Some(err) => Err(err),
+        None => Ok(parsed),
+    }
 }
 
 #[derive(Error, Debug)]
@@ -23,12 +58,52 @@ pub enum ParseError {
         found: String,
         span: Span,
     },
+    #[error("unclosed block `{{` at {0:?}")]
+    UnclosedBlock(Span),
+    #[error("unknown directive `{namespace}:` at {span:?}, expected `on:`, `class:` or `bind:`")]
+    UnknownDirective { namespace: String, span: Span },
+    #[error("`bind:` needs a state variable name at {0:?}")]
+    InvalidBindTarget(Span),
+    #[error("directive `{name}` needs a value at {span:?}: its name is not a variable name")]
+    ShorthandDirective { name: String, span: Span },
+}
+
+impl ParseError {
+    /// The stable code `raven explain` knows this error by.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseError::Unexpected { .. } => "R0201",
+            ParseError::UnclosedTag(..) => "R0202",
+            ParseError::MismatchedClose { .. } => "R0203",
+            ParseError::UnclosedBlock(_) => "R0204",
+            ParseError::UnknownDirective { .. } => "R0205",
+            ParseError::InvalidBindTarget(_) => "R0206",
+            ParseError::ShorthandDirective { .. } => "R0207",
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            ParseError::Unexpected { span, .. }
+            | ParseError::UnclosedTag(_, span)
+            | ParseError::MismatchedClose { span, .. }
+            | ParseError::UnclosedBlock(span)
+            | ParseError::UnknownDirective { span, .. }
+            | ParseError::InvalidBindTarget(span)
+            | ParseError::ShorthandDirective { span, .. } => *span,
+        }
+    }
 }
 
 struct Parser<'a> {
     tokens: &'a [Token],
     src: &'a str,
     pos: usize,
+    /// Errors recovered from so far.
+    errors: Vec<ParseError>,
+    /// Names of the elements being parsed, outermost first; empty for a
+    /// fragment.
+    open_tags: Vec<String>,
 }
 
 impl<'a> Parser<'a> {
@@ -37,34 +112,1570 @@ impl<'a> Parser<'a> {
             tokens,
             src,
             pos: 0,
+            errors: Vec::new(),
+            open_tags: Vec::new(),
         }
     }
 
-    fn parse_document(&mut self) -> Result<Document, ParseError> {
+    fn parse_document(&mut self) -> Document {
+        let start = self.current().span;
         let mut children = Vec::new();
         while !self.current_is(TokenKind::Eof) {
-            children.push(self.parse_node()?);
+            children.extend(self.parse_node_recovering());
+        }
+        let span = start.join(self.current().span);
+        Document { children, span }
+    }
+
+    fn parse_source_file(&mut self) -> SourceFile {
+        let start = self.current().span;
+        let mut items = Vec::new();
+        while !self.current_is(TokenKind::Eof) {
+            let first = self.pos;
+            match self.parse_item() {
+                Ok(item) => items.push(item),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.skip_to_item(first);
+                }
+            }
+        }
+        let span = start.join(self.current().span);
+        SourceFile { items, span }
+    }
+
+    fn parse_item(&mut self) -> Result<Item, ParseError> {
+        if self.at_keyword("component") {
+            return Ok(Item::Component(self.parse_component("component")?));
+        }
+        if self.at_keyword("page") {
+            return Ok(Item::Page(self.parse_component("page")?));
+        }
+        if self.at_keyword("server") {
+            return Ok(Item::ServerFn(self.parse_server_fn()?));
+        }
+        if self.at_keyword("import") {
+            return Ok(Item::Import(self.parse_import()?));
+        }
+        if self.at_keyword("type") {
+            return Ok(Item::TypeDecl(self.parse_type_decl()?));
+        }
+        Err(self.unexpected(
+            self.current(),
+            "`component`, `page`, `server fn`, `type` or `import`",
+        ))
+    }
+
+    fn parse_import(&mut self) -> Result<Import, ParseError> {
+        let keyword = self.expect_keyword("import")?.span;
+        let default = match self.current().kind {
+            TokenKind::Ident(_) => Some(self.parse_name("imported name")?),
+            _ => None,
+        };
+        let mut names = Vec::new();
+        if default.is_none() || self.eat(TokenKind::Comma) {
+            self.expect(TokenKind::LBrace, "`{`")?;
+            while !self.current_is(TokenKind::RBrace) {
+                names.push(self.parse_name("imported name")?);
+                if !self.eat(TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RBrace, "`,` or `}`")?;
+        }
+        self.expect_keyword("from")?;
+        let source_token = self.expect(TokenKind::Str(String::new()), "module path string")?;
+        let TokenKind::Str(source) = source_token.kind else {
+            unreachable!()
+        };
+        let span = keyword.join(source_token.span);
+        self.eat(TokenKind::Semi);
+        Ok(Import {
+            default,
+            names,
+            source,
+            source_span: source_token.span,
+            span,
+        })
+    }
+
+    fn parse_type_decl(&mut self) -> Result<TypeDecl, ParseError> {
+        let keyword = self.expect_keyword("type")?.span;
+        let name = self.parse_name("type name")?;
+        let body = if self.eat(TokenKind::Equals) {
+            TypeDeclBody::Alias(self.parse_type()?)
+        } else {
+            let open = self.expect(TokenKind::LBrace, "`{` or `=`")?;
+            let mut fields = Vec::new();
+            loop {
+                match self.current().kind {
+                    TokenKind::RBrace => break,
+                    TokenKind::Eof => return Err(ParseError::UnclosedBlock(open.span)),
+                    _ => {}
+                }
+                let name = self.parse_name("field name")?;
+                self.expect(TokenKind::Colon, "`:`")?;
+                let ty = self.parse_type()?;
+                fields.push(Field {
+                    span: name.span.join(ty.span),
+                    name,
+                    ty,
+                });
+                if !self.eat(TokenKind::Comma) {
+                    self.eat(TokenKind::Semi);
+                }
+            }
+            self.expect(TokenKind::RBrace, "`}`")?;
+            TypeDeclBody::Fields(fields)
+        };
+        let span = keyword.join(self.previous().span);
+        self.eat(TokenKind::Semi);
+        Ok(TypeDecl {
+            keyword,
+            name,
+            body,
+            span,
+        })
+    }
+
+    /// Parses a `component` or `page` declaration; both share one shape.
+    fn parse_component(&mut self, keyword: &'static str) -> Result<Component, ParseError> {
+        let keyword = self.expect_keyword(keyword)?.span;
+        let name = self.parse_name("component name")?;
+        let params = self.parse_params()?;
+        let body = self.parse_block()?;
+        Ok(Component {
+            keyword,
+            name,
+            params,
+            span: keyword.join(body.span),
+            body,
+        })
+    }
+
+    fn parse_server_fn(&mut self) -> Result<ServerFn, ParseError> {
+        let start = self.expect_keyword("server")?.span;
+        let keyword = start.join(self.expect_keyword("fn")?.span);
+        let name = self.parse_name("function name")?;
+        let params = self.parse_params()?;
+        let ret = if self.eat(TokenKind::Arrow) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let body = self.parse_block()?;
+        Ok(ServerFn {
+            keyword,
+            name,
+            params,
+            ret,
+            span: keyword.join(body.span),
+            body,
+        })
+    }
+
+    fn parse_params(&mut self) -> Result<Vec<Param>, ParseError> {
+        self.expect(TokenKind::LParen, "`(`")?;
+        let mut params = Vec::new();
+        while !self.current_is(TokenKind::RParen) {
+            params.push(self.parse_param()?);
+            if !self.eat(TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(TokenKind::RParen, "`,` or `)`")?;
+        Ok(params)
+    }
+
+    fn parse_param(&mut self) -> Result<Param, ParseError> {
+        let name = self.parse_name("parameter name")?;
+        let optional = if self.eat(TokenKind::Question) {
+            Some(self.previous().span)
+        } else {
+            None
+        };
+        let ty = if self.eat(TokenKind::Colon) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let default = if self.eat(TokenKind::Equals) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(Param {
+            span: name.span.join(self.previous().span),
+            name,
+            optional,
+            ty,
+            default,
+        })
+    }
+
+    fn parse_type(&mut self) -> Result<Type, ParseError> {
+        let start = self.current().span;
+        let kind = match self.current().kind {
+            TokenKind::LBracket => {
+                self.pos += 1;
+                let inner = self.parse_type()?;
+                self.expect(TokenKind::RBracket, "`]`")?;
+                TypeKind::Array(Box::new(inner))
+            }
+            TokenKind::Ident(ref name) if name == "fn" => {
+                self.pos += 1;
+                self.expect(TokenKind::LParen, "`(`")?;
+                let mut params = Vec::new();
+                while !self.current_is(TokenKind::RParen) {
+                    params.push(self.parse_type()?);
+                    if !self.eat(TokenKind::Comma) {
+                        break;
+                    }
+                }
+                self.expect(TokenKind::RParen, "`,` or `)`")?;
+                let ret = if self.eat(TokenKind::Arrow) {
+                    Some(Box::new(self.parse_type()?))
+                } else {
+                    None
+                };
+                TypeKind::Function { params, ret }
+            }
+            TokenKind::Ident(_) => {
+                let name = self.parse_name("type name")?.name;
+                let mut args = Vec::new();
+                if self.eat_op("<") {
+                    loop {
+                        args.push(self.parse_type()?);
+                        if !self.eat(TokenKind::Comma) {
+                            break;
+                        }
+                    }
+                    if !self.eat_op(">") {
+                        return Err(self.unexpected(self.current(), "`,` or `>`"));
+                    }
+                }
+                TypeKind::Named { name, args }
+            }
+            _ => return Err(self.unexpected(self.current(), "type")),
+        };
+        let mut ty = Type {
+            kind,
+            span: start.join(self.previous().span),
+        };
+        while self.eat(TokenKind::Question) {
+            ty = Type {
+                kind: TypeKind::Optional(Box::new(ty)),
+                span: start.join(self.previous().span),
+            };
+        }
+        Ok(ty)
+    }
+
+    fn parse_block(&mut self) -> Result<Block, ParseError> {
+        let open = self.expect(TokenKind::LBrace, "`{`")?;
+        let mut stmts = Vec::new();
+        loop {
+            match self.current().kind {
+                TokenKind::RBrace => break,
+                TokenKind::Eof => return Err(ParseError::UnclosedBlock(open.span)),
+                TokenKind::Semi => self.pos += 1,
+                _ => stmts.push(self.parse_stmt()?),
+            }
+        }
+        let close = self.expect(TokenKind::RBrace, "`}`")?;
+        Ok(Block {
+            stmts,
+            span: open.span.join(close.span),
+        })
+    }
+
+    fn parse_stmt(&mut self) -> Result<Stmt, ParseError> {
+        let start = self.current().span;
+        let stmt = if self.at_keyword("return") {
+            self.pos += 1;
+            let value = if self.at_statement_end() {
+                None
+            } else {
+                Some(self.parse_expr()?)
+            };
+            let span = start.join(self.previous().span);
+            Stmt::Return(Return { value, span })
+        } else if ["const", "let", "var"].iter().any(|k| self.at_keyword(k)) {
+            Stmt::VarDecl(Box::new(self.parse_var_decl()?))
+        } else if self.at_keyword("if") {
+            // Statements ending in a block need no `;` or line break after it.
+            return Ok(Stmt::If(Box::new(self.parse_if_stmt()?)));
+        } else if self.at_keyword("for") {
+            return Ok(Stmt::For(Box::new(self.parse_for_stmt()?)));
+        } else if self.at_keyword("while") {
+            let keyword = self.expect_keyword("while")?.span;
+            let cond = self.parse_condition()?;
+            let body = self.parse_body()?;
+            return Ok(Stmt::While(Box::new(WhileStmt {
+                is_do: false,
+                cond,
+                body,
+                span: keyword.join(self.previous().span),
+            })));
+        } else if self.at_keyword("do") {
+            self.pos += 1;
+            let body = self.parse_body()?;
+            self.expect_keyword("while")?;
+            let cond = self.parse_condition()?;
+            Stmt::While(Box::new(WhileStmt {
+                is_do: true,
+                cond,
+                body,
+                span: start.join(self.previous().span),
+            }))
+        } else if self.at_keyword("switch") {
+            return Ok(Stmt::Switch(Box::new(self.parse_switch()?)));
+        } else if self.at_keyword("try") {
+            return Ok(Stmt::Try(Box::new(self.parse_try()?)));
+        } else if self.at_keyword("throw") {
+            self.pos += 1;
+            let value = self.parse_expr()?;
+            Stmt::Throw(Throw {
+                value,
+                span: start.join(self.previous().span),
+            })
+        } else if RAW_STATEMENT_KEYWORDS.iter().any(|k| self.at_keyword(k))
+            || self.at_keyword("async")
+                && matches!(self.kind_at(self.pos + 1), TokenKind::Ident(word) if word == "function")
+        {
+            Stmt::Code(self.parse_code()?)
+        } else {
+            Stmt::Expr(self.parse_expr()?)
+        };
+        if !self.eat(TokenKind::Semi) && !self.at_statement_end() {
+            return Err(self.unexpected(self.current(), "`;` or end of line"));
+        }
+        Ok(stmt)
+    }
+
+    /// The body of a compound statement. One written without braces is parsed
+    /// as a block of its one statement.
+    fn parse_body(&mut self) -> Result<Block, ParseError> {
+        if self.current_is(TokenKind::LBrace) {
+            return self.parse_block();
+        }
+        let stmt = self.parse_stmt()?;
+        Ok(Block {
+            span: stmt.span(),
+            stmts: vec![stmt],
+        })
+    }
+
+    /// The parenthesized `(cond)` after `if`, `while` or `switch`.
+    fn parse_condition(&mut self) -> Result<Expr, ParseError> {
+        self.expect(TokenKind::LParen, "`(`")?;
+        let cond = self.parse_expr()?;
+        self.expect(TokenKind::RParen, "`)`")?;
+        Ok(cond)
+    }
+
+    fn parse_if_stmt(&mut self) -> Result<IfStmt, ParseError> {
+        let keyword = self.expect_keyword("if")?.span;
+        let cond = self.parse_condition()?;
+        let then = self.parse_body()?;
+        let otherwise = if self.at_keyword("else") {
+            self.pos += 1;
+            if self.at_keyword("if") {
+                Some(Else::If(Box::new(self.parse_if_stmt()?)))
+            } else {
+                Some(Else::Block(self.parse_body()?))
+            }
+        } else {
+            None
+        };
+        Ok(IfStmt {
+            cond,
+            then,
+            otherwise,
+            span: keyword.join(self.previous().span),
+        })
+    }
+
+    fn parse_for_stmt(&mut self) -> Result<ForStmt, ParseError> {
+        let keyword = self.expect_keyword("for")?.span;
+        self.expect(TokenKind::LParen, "`(`")?;
+        let head = match self.parse_for_each()? {
+            Some(head) => head,
+            None => {
+                let init = if self.current_is(TokenKind::Semi) {
+                    None
+                } else if ["const", "let", "var"].iter().any(|k| self.at_keyword(k)) {
+                    Some(Box::new(Stmt::VarDecl(Box::new(self.parse_var_decl()?))))
+                } else {
+                    Some(Box::new(Stmt::Expr(self.parse_expr()?)))
+                };
+                self.expect(TokenKind::Semi, "`;`")?;
+                let test = if self.current_is(TokenKind::Semi) {
+                    None
+                } else {
+                    Some(self.parse_expr()?)
+                };
+                self.expect(TokenKind::Semi, "`;`")?;
+                let update = if self.current_is(TokenKind::RParen) {
+                    None
+                } else {
+                    Some(self.parse_expr()?)
+                };
+                ForHead::Steps { init, test, update }
+            }
+        };
+        self.expect(TokenKind::RParen, "`)`")?;
+        let body = self.parse_body()?;
+        Ok(ForStmt {
+            head,
+            body,
+            span: keyword.join(self.previous().span),
+        })
+    }
+
+    /// Parses `const item of items` or `key in object` at the start of a `for`
+    /// head. Returns `None`, having consumed nothing, for any other head.
+    fn parse_for_each(&mut self) -> Result<Option<ForHead>, ParseError> {
+        let first = self.pos;
+        let keyword = if ["const", "let", "var"].iter().any(|k| self.at_keyword(k)) {
+            Some(self.parse_name("`const`, `let` or `var`")?)
+        } else {
+            None
+        };
+        let binding = match self.current().kind {
+            TokenKind::Ident(_) | TokenKind::LBrace | TokenKind::LBracket => {
+                self.parse_primary().ok()
+            }
+            _ => None,
+        };
+        let (Some(binding), true) = (binding, self.at_keyword("of") || self.at_keyword("in"))
+        else {
+            self.pos = first;
+            return Ok(None);
+        };
+        let of = self.at_keyword("of");
+        self.pos += 1;
+        let iterable = self.parse_expr()?;
+        Ok(Some(ForHead::Each {
+            keyword,
+            binding,
+            of,
+            iterable,
+        }))
+    }
+
+    fn parse_switch(&mut self) -> Result<SwitchStmt, ParseError> {
+        let keyword = self.expect_keyword("switch")?.span;
+        let value = self.parse_condition()?;
+        let open = self.expect(TokenKind::LBrace, "`{`")?;
+        let mut cases = Vec::new();
+        loop {
+            match self.current().kind {
+                TokenKind::RBrace => break,
+                TokenKind::Eof => return Err(ParseError::UnclosedBlock(open.span)),
+                _ => cases.push(self.parse_case()?),
+            }
+        }
+        self.pos += 1;
+        Ok(SwitchStmt {
+            value,
+            cases,
+            span: keyword.join(self.previous().span),
+        })
+    }
+
+    /// One `case test:` or `default:` and the statements up to the next one.
+    fn parse_case(&mut self) -> Result<Case, ParseError> {
+        let start = self.current().span;
+        let test = if self.at_keyword("default") {
+            self.pos += 1;
+            None
+        } else {
+            self.expect_keyword("case")?;
+            Some(self.parse_expr()?)
+        };
+        self.expect(TokenKind::Colon, "`:`")?;
+        let mut body = Vec::new();
+        loop {
+            match self.current().kind {
+                TokenKind::RBrace | TokenKind::Eof => break,
+                TokenKind::Semi => self.pos += 1,
+                _ if self.at_keyword("case") || self.at_keyword("default") => break,
+                _ => body.push(self.parse_stmt()?),
+            }
+        }
+        Ok(Case {
+            test,
+            body,
+            span: start.join(self.previous().span),
+        })
+    }
+
+    fn parse_try(&mut self) -> Result<TryStmt, ParseError> {
+        let keyword = self.expect_keyword("try")?.span;
+        let block = self.parse_block()?;
+        let catch = if self.at_keyword("catch") {
+            self.pos += 1;
+            let param = if self.eat(TokenKind::LParen) {
+                let param = match self.current().kind {
+                    TokenKind::Ident(_) | TokenKind::LBrace | TokenKind::LBracket => {
+                        self.parse_primary()?
+                    }
+                    _ => return Err(self.unexpected(self.current(), "error variable")),
+                };
+                self.expect(TokenKind::RParen, "`)`")?;
+                Some(param)
+            } else {
+                None
+            };
+            let body = self.parse_block()?;
+            Some(Catch { param, body })
+        } else {
+            None
+        };
+        let finally = if self.at_keyword("finally") {
+            self.pos += 1;
+            Some(self.parse_block()?)
+        } else {
+            None
+        };
+        if catch.is_none() && finally.is_none() {
+            return Err(self.unexpected(self.current(), "`catch` or `finally`"));
         }
-        Ok(Document { children })
+        Ok(TryStmt {
+            block,
+            catch,
+            finally,
+            span: keyword.join(self.previous().span),
+        })
     }
 
-    fn parse_node(&mut self) -> Result<Node, ParseError> {
+    fn parse_var_decl(&mut self) -> Result<VarDecl, ParseError> {
+        let keyword = self.parse_name("`const`, `let` or `var`")?;
+        let target = match self.current().kind {
+            TokenKind::Ident(_) | TokenKind::LBrace | TokenKind::LBracket => {
+                self.parse_primary()?
+            }
+            _ => return Err(self.unexpected(self.current(), "variable name or pattern")),
+        };
+        let ty = if self.eat(TokenKind::Colon) {
+            Some(self.parse_type()?)
+        } else {
+            None
+        };
+        let value = if self.eat(TokenKind::Equals) {
+            Some(self.parse_expr()?)
+        } else {
+            None
+        };
+        Ok(VarDecl {
+            span: keyword.span.join(self.previous().span),
+            keyword,
+            target,
+            ty,
+            value,
+        })
+    }
+
+    /// Parses an expression, including assignments and arrow functions.
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        if self.at_arrow() {
+            return self.parse_arrow();
+        }
+        let start = self.current().span;
+        let target = self.parse_conditional()?;
+        let op = match &self.current().kind {
+            TokenKind::Equals => "=".to_string(),
+            TokenKind::Op(op) if ASSIGN_OPS.contains(&op.as_str()) => op.clone(),
+            _ => return Ok(target),
+        };
+        self.pos += 1;
+        let value = self.parse_expr()?;
+        Ok(Expr {
+            kind: ExprKind::Assign {
+                op,
+                target: Box::new(target),
+                value: Box::new(value),
+            },
+            span: start.join(self.previous().span),
+        })
+    }
+
+    /// Whether an arrow function starts here: `x =>`, `(...) =>` or
+    /// `(...): Type =>`, each optionally preceded by `async`.
+    fn at_arrow(&self) -> bool {
+        let mut pos = self.pos;
+        if matches!(self.kind_at(pos), TokenKind::Ident(word) if word == "async")
+            && !matches!(self.kind_at(pos + 1), TokenKind::FatArrow)
+        {
+            pos += 1;
+        }
+        match self.kind_at(pos) {
+            TokenKind::Ident(_) => matches!(self.kind_at(pos + 1), TokenKind::FatArrow),
+            TokenKind::LParen => {
+                let mut depth = 0usize;
+                loop {
+                    match self.kind_at(pos) {
+                        TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => depth += 1,
+                        TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => {
+                            depth -= 1;
+                            if depth == 0 {
+                                break;
+                            }
+                        }
+                        TokenKind::Eof => return false,
+                        _ => {}
+                    }
+                    pos += 1;
+                }
+                match self.kind_at(pos + 1) {
+                    TokenKind::FatArrow => true,
+                    TokenKind::Colon => self.at_return_type_arrow(pos + 2),
+                    _ => false,
+                }
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the tokens from `pos` look like a return type followed by `=>`
+    /// on the same line, telling `(x): T => x` from the `: b` of `c ? (a) : b`.
+    fn at_return_type_arrow(&self, mut pos: usize) -> bool {
+        let line = self.tokens[pos.min(self.tokens.len() - 1)].span.line;
+        let mut depth = 0usize;
+        loop {
+            let token = &self.tokens[pos.min(self.tokens.len() - 1)];
+            if token.span.line != line {
+                return false;
+            }
+            match &token.kind {
+                TokenKind::FatArrow if depth == 0 => return true,
+                TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => depth += 1,
+                TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace | TokenKind::Comma
+                    if depth == 0 =>
+                {
+                    return false
+                }
+                TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => depth -= 1,
+                TokenKind::Op(op) if !matches!(op.as_str(), "|" | "&" | "<" | ">") => return false,
+                TokenKind::Semi | TokenKind::Equals | TokenKind::Eof => return false,
+                _ => {}
+            }
+            pos += 1;
+        }
+    }
+
+    fn parse_arrow(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current().span;
+        let is_async =
+            self.at_keyword("async") && !matches!(self.kind_at(self.pos + 1), TokenKind::FatArrow);
+        if is_async {
+            self.pos += 1;
+        }
+        let (params, ret) = if self.eat(TokenKind::LParen) {
+            (self.parse_fn_params()?, self.parse_return_type()?)
+        } else {
+            let name = self.parse_name("parameter name")?;
+            let param = Expr {
+                span: name.span,
+                kind: ExprKind::Ident(name.name),
+            };
+            (vec![param], None)
+        };
+        self.expect(TokenKind::FatArrow, "`=>`")?;
+        let body = if self.current_is(TokenKind::LBrace) {
+            ArrowBody::Block(self.parse_block()?)
+        } else {
+            ArrowBody::Expr(Box::new(self.parse_expr()?))
+        };
+        Ok(Expr {
+            kind: ExprKind::Arrow {
+                is_async,
+                params,
+                ret,
+                body,
+            },
+            span: start.join(self.previous().span),
+        })
+    }
+
+    /// Parses a `function` expression, possibly `async`.
+    fn parse_function(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current().span;
+        let is_async = self.at_keyword("async");
+        if is_async {
+            self.pos += 1;
+        }
+        self.expect_keyword("function")?;
+        let name = if self.current_is(TokenKind::LParen) {
+            None
+        } else {
+            Some(self.parse_name("function name")?)
+        };
+        self.expect(TokenKind::LParen, "`(`")?;
+        let params = self.parse_fn_params()?;
+        let ret = self.parse_return_type()?;
+        let body = self.parse_block()?;
+        Ok(Expr {
+            kind: ExprKind::Function(Box::new(Function {
+                is_async,
+                name,
+                params,
+                ret,
+                body,
+            })),
+            span: start.join(self.previous().span),
+        })
+    }
+
+    /// Parses function parameters up to and including the closing `)`.
+    fn parse_fn_params(&mut self) -> Result<Vec<Expr>, ParseError> {
+        let mut params = Vec::new();
+        while !self.current_is(TokenKind::RParen) {
+            params.push(self.parse_fn_param()?);
+            if !self.eat(TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(TokenKind::RParen, "`,` or `)`")?;
+        Ok(params)
+    }
+
+    /// One parameter: a name or destructuring pattern, possibly a `...rest`,
+    /// with an optional `?`, type annotation and default value.
+    fn parse_fn_param(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current().span;
+        let is_rest = self.eat_op("...");
+        let target_start = self.current().span;
+        let target = self.parse_primary()?;
+        let optional = self.eat(TokenKind::Question);
+        let ty = if self.eat(TokenKind::Colon) {
+            Some(self.parse_ts_type()?)
+        } else {
+            None
+        };
+        let mut param = if optional || ty.is_some() {
+            Expr {
+                kind: ExprKind::Param {
+                    target: Box::new(target),
+                    optional,
+                    ty,
+                },
+                span: target_start.join(self.previous().span),
+            }
+        } else {
+            target
+        };
+        if self.eat(TokenKind::Equals) {
+            let value = self.parse_expr()?;
+            param = Expr {
+                kind: ExprKind::Assign {
+                    op: "=".to_string(),
+                    target: Box::new(param),
+                    value: Box::new(value),
+                },
+                span: target_start.join(self.previous().span),
+            };
+        }
+        if is_rest {
+            param = Expr {
+                kind: ExprKind::Spread(Box::new(param)),
+                span: start.join(self.previous().span),
+            };
+        }
+        Ok(param)
+    }
+
+    /// The `: Type` after a parameter list, if there is one.
+    fn parse_return_type(&mut self) -> Result<Option<String>, ParseError> {
+        if self.eat(TokenKind::Colon) {
+            Ok(Some(self.parse_ts_type()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Parses a TypeScript type and returns it as written.
+    fn parse_ts_type(&mut self) -> Result<String, ParseError> {
+        let start = self.pos;
+        self.skip_ts_type()?;
+        Ok(self.code_since(start).text)
+    }
+
+    /// Skips a union or intersection of types.
+    fn skip_ts_type(&mut self) -> Result<(), ParseError> {
+        let _ = self.eat_op("|") || self.eat_op("&");
+        loop {
+            self.skip_ts_type_operand()?;
+            while self.current_is(TokenKind::LBracket)
+                && *self.kind_at(self.pos + 1) == TokenKind::RBracket
+            {
+                self.pos += 2;
+            }
+            if !(self.eat_op("|") || self.eat_op("&")) {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Skips one type such as `string`, `Map<K, V>`, `"a"`, `{ id: number }`,
+    /// `[A, B]` or `(x: A) => B`, possibly after `keyof`, `typeof` or
+    /// `readonly`.
+    fn skip_ts_type_operand(&mut self) -> Result<(), ParseError> {
+        while ["keyof", "typeof", "readonly"]
+            .iter()
+            .any(|k| self.at_keyword(k))
+        {
+            self.pos += 1;
+        }
+        let token = self.current().clone();
+        match token.kind {
+            TokenKind::Ident(_) => {
+                self.pos += 1;
+                while self.eat(TokenKind::Dot) {
+                    self.parse_name("type name")?;
+                }
+                if matches!(&self.current().kind, TokenKind::Op(op) if op == "<") {
+                    self.skip_type_args()?;
+                }
+            }
+            TokenKind::Str(_) | TokenKind::Number(_) | TokenKind::Template(_) => self.pos += 1,
+            TokenKind::LParen => {
+                self.skip_group()?;
+                if self.eat(TokenKind::FatArrow) {
+                    self.skip_ts_type()?;
+                }
+            }
+            TokenKind::LBracket | TokenKind::LBrace => self.skip_group()?,
+            _ => return Err(self.unexpected(&token, "type")),
+        }
+        Ok(())
+    }
+
+    /// Skips from a `<` to its matching `>`.
+    fn skip_type_args(&mut self) -> Result<(), ParseError> {
+        self.pos += 1;
+        self.skip_type_args_from(1)
+    }
+
+    /// Skips to the `>` closing `depth` open `<`s.
+    fn skip_type_args_from(&mut self, mut depth: usize) -> Result<(), ParseError> {
+        loop {
+            match &self.current().kind {
+                TokenKind::Op(op) if op == "<" => depth += 1,
+                TokenKind::Op(op) if op == ">" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                }
+                TokenKind::Semi | TokenKind::Eof => {
+                    return Err(self.unexpected(self.current(), "`>`"))
+                }
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Skips from a `(`, `[` or `{` to its matching closer.
+    fn skip_group(&mut self) -> Result<(), ParseError> {
+        let mut depth = 0usize;
+        loop {
+            match self.current().kind {
+                TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => depth += 1,
+                TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos += 1;
+                        return Ok(());
+                    }
+                }
+                TokenKind::Eof => return Err(self.unexpected(self.current(), "closing bracket")),
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Whether the `<` here opens type arguments followed by a call, as in
+    /// `useState<string>("")`, rather than comparing.
+    fn at_type_args_call(&self) -> bool {
+        let mut pos = self.pos;
+        let mut depth = 0usize;
+        loop {
+            match self.kind_at(pos) {
+                TokenKind::Op(op) if op == "<" => depth += 1,
+                TokenKind::Op(op) if op == ">" => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return matches!(self.kind_at(pos + 1), TokenKind::LParen);
+                    }
+                }
+                TokenKind::Op(op) if matches!(op.as_str(), "|" | "&") => {}
+                TokenKind::Ident(_)
+                | TokenKind::Str(_)
+                | TokenKind::Number(_)
+                | TokenKind::Dot
+                | TokenKind::Comma
+                | TokenKind::Colon
+                | TokenKind::Question
+                | TokenKind::FatArrow
+                | TokenKind::LParen
+                | TokenKind::RParen
+                | TokenKind::LBracket
+                | TokenKind::RBracket
+                | TokenKind::LBrace
+                | TokenKind::RBrace => {}
+                _ => return false,
+            }
+            pos += 1;
+        }
+    }
+
+    fn parse_conditional(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current().span;
+        let test = self.parse_binary(1)?;
+        if !self.eat(TokenKind::Question) {
+            return Ok(test);
+        }
+        let consequent = self.parse_expr()?;
+        self.expect(TokenKind::Colon, "`:`")?;
+        let alternate = self.parse_expr()?;
+        Ok(Expr {
+            kind: ExprKind::Conditional {
+                test: Box::new(test),
+                consequent: Box::new(consequent),
+                alternate: Box::new(alternate),
+            },
+            span: start.join(self.previous().span),
+        })
+    }
+
+    /// Precedence climbing over [`BINARY_OPS`]; only operators binding at least
+    /// as tightly as `min` are consumed.
+    fn parse_binary(&mut self, min: u8) -> Result<Expr, ParseError> {
+        let start = self.current().span;
+        let mut left = self.parse_unary()?;
+        loop {
+            if self.at_keyword("as") && !self.starts_new_line() {
+                if AS_PRECEDENCE < min {
+                    break;
+                }
+                self.pos += 1;
+                let ty = self.parse_ts_type()?;
+                left = Expr {
+                    kind: ExprKind::As {
+                        value: Box::new(left),
+                        ty,
+                    },
+                    span: start.join(self.previous().span),
+                };
+                continue;
+            }
+            let op = match &self.current().kind {
+                TokenKind::Op(op) => op.as_str(),
+                TokenKind::Ident(word) if word == "in" || word == "instanceof" => word.as_str(),
+                _ => break,
+            };
+            let Some(&(op, prec)) = BINARY_OPS.iter().find(|(candidate, _)| *candidate == op)
+            else {
+                break;
+            };
+            if prec < min {
+                break;
+            }
+            self.pos += 1;
+            // `**` is the only right-associative binary operator.
+            let right = self.parse_binary(if op == "**" { prec } else { prec + 1 })?;
+            left = Expr {
+                kind: ExprKind::Binary {
+                    op: op.to_string(),
+                    left: Box::new(left),
+                    right: Box::new(right),
+                },
+                span: start.join(self.previous().span),
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current().span;
+        let op = match &self.current().kind {
+            TokenKind::Op(op) if matches!(op.as_str(), "!" | "-" | "+" | "~" | "++" | "--") => {
+                op.clone()
+            }
+            TokenKind::Ident(word) if UNARY_KEYWORDS.contains(&word.as_str()) => word.clone(),
+            _ => return self.parse_postfix(),
+        };
+        self.pos += 1;
+        let operand = self.parse_unary()?;
+        Ok(Expr {
+            kind: ExprKind::Unary {
+                op,
+                operand: Box::new(operand),
+            },
+            span: start.join(self.previous().span),
+        })
+    }
+
+    /// Member access, indexing, calls, type arguments, `++`/`--` and the
+    /// non-null `!` after a primary expression.
+    /// A `(` or `[` on a new line starts a new statement instead of a call or
+    /// index.
+    fn parse_postfix(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current().span;
+        let mut expr = self.parse_primary()?;
+        loop {
+            let kind = if self.eat(TokenKind::Dot) {
+                ExprKind::Member {
+                    object: Box::new(expr),
+                    property: self.parse_name("property name")?,
+                    optional: false,
+                }
+            } else if self.eat_op("?.") {
+                if self.eat(TokenKind::LParen) {
+                    ExprKind::Call {
+                        callee: Box::new(expr),
+                        args: self.parse_expr_list(TokenKind::RParen, "`,` or `)`")?,
+                        optional: true,
+                    }
+                } else if self.eat(TokenKind::LBracket) {
+                    let index = self.parse_expr()?;
+                    self.expect(TokenKind::RBracket, "`]`")?;
+                    ExprKind::Index {
+                        object: Box::new(expr),
+                        index: Box::new(index),
+                        optional: true,
+                    }
+                } else {
+                    ExprKind::Member {
+                        object: Box::new(expr),
+                        property: self.parse_name("property name")?,
+                        optional: true,
+                    }
+                }
+            } else if self.starts_new_line() {
+                break;
+            } else if self.eat(TokenKind::LParen) {
+                ExprKind::Call {
+                    callee: Box::new(expr),
+                    args: self.parse_expr_list(TokenKind::RParen, "`,` or `)`")?,
+                    optional: false,
+                }
+            } else if self.eat(TokenKind::LBracket) {
+                let index = self.parse_expr()?;
+                self.expect(TokenKind::RBracket, "`]`")?;
+                ExprKind::Index {
+                    object: Box::new(expr),
+                    index: Box::new(index),
+                    optional: false,
+                }
+            } else if matches!(&self.current().kind, TokenKind::Op(op) if op == "<")
+                && self.at_type_args_call()
+            {
+                self.pos += 1;
+                let first = self.pos;
+                self.skip_type_args_from(1)?;
+                let types = self.src[self.tokens[first].span.offset..self.previous().span.offset]
+                    .trim()
+                    .to_string();
+                ExprKind::TypeArgs {
+                    callee: Box::new(expr),
+                    types,
+                }
+            } else if self.eat_op("++") || self.eat_op("--") || self.eat_op("!") {
+                let TokenKind::Op(op) = self.previous().kind.clone() else {
+                    unreachable!()
+                };
+                ExprKind::Postfix {
+                    op,
+                    operand: Box::new(expr),
+                }
+            } else {
+                break;
+            };
+            expr = Expr {
+                kind,
+                span: start.join(self.previous().span),
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        let token = self.current().clone();
+        let kind = match token.kind {
+            TokenKind::Ident(name)
+                if name == "function"
+                    || name == "async"
+                        && matches!(self.kind_at(self.pos + 1), TokenKind::Ident(word) if word == "function") =>
+            {
+                return self.parse_function();
+            }
+            TokenKind::Ident(name) => {
+                self.pos += 1;
+                match name.as_str() {
+                    "true" => ExprKind::Bool(true),
+                    "false" => ExprKind::Bool(false),
+                    "null" => ExprKind::Null,
+                    _ => ExprKind::Ident(name),
+                }
+            }
+            TokenKind::Str(value) => {
+                self.pos += 1;
+                ExprKind::Str(value)
+            }
+            TokenKind::Number(value) => {
+                self.pos += 1;
+                ExprKind::Number(value)
+            }
+            TokenKind::Template(raw) => {
+                self.pos += 1;
+                ExprKind::Template(self.parse_template(&raw, token.span)?)
+            }
+            TokenKind::Regex(raw) => {
+                self.pos += 1;
+                ExprKind::Regex(raw)
+            }
+            TokenKind::LParen => {
+                self.pos += 1;
+                let inner = self.parse_expr()?;
+                self.expect(TokenKind::RParen, "`)`")?;
+                ExprKind::Paren(Box::new(inner))
+            }
+            TokenKind::LBracket => {
+                self.pos += 1;
+                ExprKind::Array(self.parse_expr_list(TokenKind::RBracket, "`,` or `]`")?)
+            }
+            TokenKind::LBrace => ExprKind::Object(self.parse_object()?),
+            TokenKind::LAngle => ExprKind::Markup(Box::new(self.parse_element()?)),
+            _ => return Err(self.unexpected(&token, "expression")),
+        };
+        Ok(Expr {
+            kind,
+            span: token.span.join(self.previous().span),
+        })
+    }
+
+    /// Parses comma-separated expressions, each possibly a `...spread`, up to
+    /// and including `close`.
+    fn parse_expr_list(
+        &mut self,
+        close: TokenKind,
+        expected: &'static str,
+    ) -> Result<Vec<Expr>, ParseError> {
+        let mut items = Vec::new();
+        while !self.current_is(close.clone()) {
+            items.push(self.parse_spread_or_expr()?);
+            if !self.eat(TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(close, expected)?;
+        Ok(items)
+    }
+
+    fn parse_spread_or_expr(&mut self) -> Result<Expr, ParseError> {
+        let start = self.current().span;
+        if !self.eat_op("...") {
+            return self.parse_expr();
+        }
+        let inner = self.parse_expr()?;
+        Ok(Expr {
+            kind: ExprKind::Spread(Box::new(inner)),
+            span: start.join(self.previous().span),
+        })
+    }
+
+    fn parse_object(&mut self) -> Result<Vec<Prop>, ParseError> {
+        self.expect(TokenKind::LBrace, "`{`")?;
+        let mut props = Vec::new();
+        while !self.current_is(TokenKind::RBrace) {
+            props.push(self.parse_prop()?);
+            if !self.eat(TokenKind::Comma) {
+                break;
+            }
+        }
+        self.expect(TokenKind::RBrace, "`,` or `}`")?;
+        Ok(props)
+    }
+
+    fn parse_prop(&mut self) -> Result<Prop, ParseError> {
+        if self.eat_op("...") {
+            return Ok(Prop::Spread(self.parse_expr()?));
+        }
+        let token = self.current().clone();
+        let key = match token.kind {
+            TokenKind::Ident(name) => {
+                self.pos += 1;
+                let ident = Ident {
+                    name,
+                    span: token.span,
+                };
+                if !self.current_is(TokenKind::Colon) {
+                    return Ok(Prop::Shorthand(ident));
+                }
+                PropKey::Ident(ident)
+            }
+            TokenKind::Str(value) => {
+                self.pos += 1;
+                PropKey::Str(value)
+            }
+            TokenKind::Number(value) => {
+                self.pos += 1;
+                PropKey::Number(value)
+            }
+            TokenKind::LBracket => {
+                self.pos += 1;
+                let key = self.parse_expr()?;
+                self.expect(TokenKind::RBracket, "`]`")?;
+                PropKey::Computed(Box::new(key))
+            }
+            _ => return Err(self.unexpected(&token, "property name")),
+        };
+        self.expect(TokenKind::Colon, "`:`")?;
+        let value = self.parse_expr()?;
+        Ok(Prop::KeyValue { key, value })
+    }
+
+    /// Splits the raw contents of the template literal at `span` into text and
+    /// `${}` placeholders, parsing each placeholder as an expression.
+    fn parse_template(&self, raw: &str, span: Span) -> Result<Vec<TemplatePart>, ParseError> {
+        let mut parts = Vec::new();
+        let bytes = raw.as_bytes();
+        let mut text_start = 0;
+        let mut idx = 0;
+        while idx < bytes.len() {
+            match bytes[idx] {
+                b'\\' => idx += 1,
+                b'$' if bytes.get(idx + 1) == Some(&b'{') => {
+                    if text_start < idx {
+                        parts.push(TemplatePart::Text(raw[text_start..idx].to_string()));
+                    }
+                    let end = placeholder_end(raw, idx + 2).ok_or_else(|| {
+                        self.unexpected(self.previous(), "`}` closing the placeholder")
+                    })?;
+                    // +1 skips the opening backtick.
+                    let offset = span.offset + 1 + idx + 2;
+                    parts.push(TemplatePart::Expr(self.parse_placeholder(
+                        &raw[idx + 2..end],
+                        offset,
+                        span,
+                    )?));
+                    idx = end;
+                    text_start = end + 1;
+                }
+                _ => {}
+            }
+            idx += 1;
+        }
+        if text_start < raw.len() {
+            parts.push(TemplatePart::Text(raw[text_start..].to_string()));
+        }
+        Ok(parts)
+    }
+
+    /// Parses the source of one placeholder found at `offset` inside the
+    /// `template` literal, keeping spans relative to the whole file.
+    fn parse_placeholder(
+        &self,
+        text: &str,
+        offset: usize,
+        template: Span,
+    ) -> Result<Expr, ParseError> {
+        let mut tokens = lex_source(text)
+            .map_err(|_| self.unexpected(self.previous(), "expression in template placeholder"))?;
+        let before = &self.src[template.offset..offset];
+        let (line, col) = match before.rfind('\n') {
+            Some(newline) => (
+                template.line + before.matches('\n').count(),
+                before[newline + 1..].chars().count() + 1,
+            ),
+            None => (template.line, template.col + before.chars().count()),
+        };
+        for token in &mut tokens {
+            if token.span.line == 1 {
+                token.span.col += col - 1;
+            }
+            if token.span.end_line == 1 {
+                token.span.end_col += col - 1;
+            }
+            token.span.line += line - 1;
+            token.span.end_line += line - 1;
+            token.span.offset += offset;
+        }
+        let mut parser = Parser::new(&tokens, self.src);
+        let expr = parser.parse_expr()?;
+        parser.expect(TokenKind::Eof, "`}`")?;
+        first_error((expr, parser.errors))
+    }
+
+    /// Consumes one statement or expression worth of code. It ends at `;`, at the
+    /// `}` closing the enclosing block, or at a line break that cannot continue
+    /// the expression.
+    fn parse_code(&mut self) -> Result<Code, ParseError> {
+        let start = self.pos;
+        let mut depth = 0usize;
+        loop {
+            match self.current().kind {
+                TokenKind::Eof => break,
+                TokenKind::Semi | TokenKind::RBrace if depth == 0 => break,
+                TokenKind::LAngle => {
+                    self.parse_element()?;
+                }
+                TokenKind::LParen | TokenKind::LBracket | TokenKind::LBrace => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                TokenKind::RParen | TokenKind::RBracket | TokenKind::RBrace => {
+                    depth = depth.saturating_sub(1);
+                    self.pos += 1;
+                }
+                _ => self.pos += 1,
+            }
+            if depth == 0 && self.at_line_break() {
+                break;
+            }
+        }
+        if self.pos == start {
+            return Err(self.unexpected(self.current(), "expression"));
+        }
+        Ok(self.code_since(start))
+    }
+
+    fn at_statement_end(&self) -> bool {
+        matches!(
+            self.current().kind,
+            TokenKind::Semi | TokenKind::RBrace | TokenKind::Eof
+        ) || self.at_line_break()
+    }
+
+    /// True when the current token starts a new line and neither it nor the
+    /// previous token can continue an expression across that line.
+    fn at_line_break(&self) -> bool {
+        if !self.starts_new_line() {
+            return false;
+        }
+        let prev = self.previous();
+        // A `!`, `++` or `--` ending a line is postfix, as in `maybe!`.
+        let continues_prev = match &prev.kind {
+            TokenKind::Op(op) => !matches!(op.as_str(), "!" | "++" | "--"),
+            _ => matches!(
+                prev.kind,
+                TokenKind::Dot
+                    | TokenKind::Comma
+                    | TokenKind::Equals
+                    | TokenKind::FatArrow
+                    | TokenKind::Arrow
+                    | TokenKind::Colon
+                    | TokenKind::Question
+                    | TokenKind::LParen
+                    | TokenKind::LBracket
+                    | TokenKind::LBrace
+            ),
+        };
+        let continues_next = match &self.current().kind {
+            TokenKind::Dot | TokenKind::Question | TokenKind::Colon | TokenKind::FatArrow => true,
+            TokenKind::Op(op) => !matches!(op.as_str(), "!" | "++" | "--" | "..."),
+            TokenKind::Ident(word) => matches!(word.as_str(), "else" | "catch" | "finally"),
+            _ => false,
+        };
+        !continues_prev && !continues_next
+    }
+
+    /// True when the current token is on a later line than the end of the
+    /// previous one.
+    fn starts_new_line(&self) -> bool {
+        if self.pos == 0 {
+            return false;
+        }
+        let prev = self.previous();
+        let prev_text = self
+            .src
+            .get(prev.span.offset..prev.span.end_offset())
+            .unwrap_or_default();
+        let prev_line = prev.span.line + prev_text.matches('\n').count();
+        self.current().span.line > prev_line
+    }
+
+    fn code_since(&self, start: usize) -> Code {
+        let first = self.tokens[start].span;
+        let last = self.previous().span;
+        let span = first.join(last);
+        let text = self.src[span.offset..span.end_offset()].to_string();
+        Code { text, span }
+    }
+
+    fn parse_name(&mut self, description: &'static str) -> Result<Ident, ParseError> {
+        let token = self.expect_ident(description)?;
+        match token.kind {
+            TokenKind::Ident(name) => Ok(Ident {
+                name,
+                span: token.span,
+            }),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Parses one child of an element like [`Self::parse_node`]. On an error
+    /// it records it, skips to the next tag or past the enclosing `}` and
+    /// returns a [`Node::Error`] covering the skipped tokens.
+    fn parse_node_recovering(&mut self) -> Option<Node> {
+        let first = self.pos;
+        match self.parse_node() {
+            Ok(node) => node,
+            Err(err) => {
+                self.errors.push(err);
+                if self.pos == first {
+                    self.pos += 1;
+                }
+                let mut depth = 0usize;
+                loop {
+                    match self.current().kind {
+                        TokenKind::Eof => break,
+                        TokenKind::LAngle if depth == 0 => break,
+                        TokenKind::LBrace => depth += 1,
+                        TokenKind::RBrace if depth == 0 => {
+                            self.pos += 1;
+                            break;
+                        }
+                        TokenKind::RBrace => depth -= 1,
+                        _ => {}
+                    }
+                    self.pos += 1;
+                }
+                Some(Node::Error {
+                    span: self.tokens[first].span.join(self.previous().span),
+                })
+            }
+        }
+    }
+
+    /// Parses one child of an element; `None` for an empty `{}`, such as one
+    /// that only held a comment.
+    fn parse_node(&mut self) -> Result<Option<Node>, ParseError> {
         match self.current().kind.clone() {
-            TokenKind::LAngle => self.parse_element(),
+            TokenKind::LAngle => self.parse_element().map(Some),
             TokenKind::Text(text) => {
+                let span = self.current().span;
                 self.pos += 1;
-                Ok(Node::Text(text))
+                Ok(Some(Node::Text {
+                    text: entities::decode(&text),
+                    span,
+                }))
             }
             TokenKind::LBrace => {
-                let expr = self.parse_expr_string()?;
-                Ok(Node::Expr(expr))
+                let open = self.current().span;
+                self.pos += 1;
+                if self.eat(TokenKind::RBrace) {
+                    return Ok(None);
+                }
+                let node = if self.at_keyword("if") {
+                    self.parse_if()?
+                } else if self.at_keyword("for") {
+                    self.parse_for()?
+                } else {
+                    Node::Expr(self.parse_expr()?)
+                };
+                // The lexer ends an expression missing its `}` at the next
+                // closing tag.
+                if self.next_is_closing_tag() {
+                    return Err(ParseError::UnclosedBlock(open));
+                }
+                self.expect(TokenKind::RBrace, "`}`")?;
+                Ok(Some(node))
             }
             _ => Err(self.unexpected(self.current(), "element, text, or expression")),
         }
     }
 
+    /// `if cond { ... } else { ... }` inside a markup `{}`.
+    fn parse_if(&mut self) -> Result<Node, ParseError> {
+        let keyword = self.expect_keyword("if")?.span;
+        let cond = self.parse_expr()?;
+        let then = self.parse_markup_body()?;
+        let otherwise = if self.at_keyword("else") {
+            self.pos += 1;
+            if self.at_keyword("if") {
+                Some(vec![self.parse_if()?])
+            } else {
+                Some(self.parse_markup_body()?)
+            }
+        } else {
+            None
+        };
+        Ok(Node::If {
+            cond,
+            then,
+            otherwise,
+            span: keyword.join(self.previous().span),
+        })
+    }
+
+    /// `for item in items { ... }` inside a markup `{}`.
+    fn parse_for(&mut self) -> Result<Node, ParseError> {
+        let keyword = self.expect_keyword("for")?.span;
+        let binding = match self.current().kind {
+            TokenKind::Ident(_) | TokenKind::LBrace | TokenKind::LBracket => {
+                self.parse_primary()?
+            }
+            _ => return Err(self.unexpected(self.current(), "loop variable")),
+        };
+        self.expect_keyword("in")?;
+        let iterable = self.parse_expr()?;
+        let body = self.parse_markup_body()?;
+        Ok(Node::For {
+            binding,
+            iterable,
+            body,
+            span: keyword.join(self.previous().span),
+        })
+    }
+
+    /// The `{ ... }` body of a markup `if` or `for`: elements, nested `{}`
+    /// expressions and string literals.
+    fn parse_markup_body(&mut self) -> Result<Vec<Node>, ParseError> {
+        let open = self.expect(TokenKind::LBrace, "`{`")?;
+        let mut nodes = Vec::new();
+        loop {
+            match self.current().kind.clone() {
+                TokenKind::RBrace => break,
+                TokenKind::Eof => return Err(ParseError::UnclosedBlock(open.span)),
+                TokenKind::Str(text) => {
+                    let span = self.current().span;
+                    self.pos += 1;
+                    nodes.push(Node::Text {
+                        text: entities::decode(&text),
+                        span,
+                    });
+                }
+                TokenKind::LAngle | TokenKind::LBrace => nodes.extend(self.parse_node_recovering()),
+                _ => {
+                    let err = self.unexpected(self.current(), "element, string or `}`");
+                    self.errors.push(err);
+                    nodes.push(Node::Error {
+                        span: self.current().span,
+                    });
+                    self.pos += 1;
+                }
+            }
+        }
+        self.pos += 1;
+        Ok(nodes)
+    }
+
     fn parse_element(&mut self) -> Result<Node, ParseError> {
         let open = self.expect(TokenKind::LAngle, "<")?;
+        if self.eat(TokenKind::RAngle) {
+            let children = self.parse_children("", open.span);
+            return Ok(Node::Fragment {
+                children,
+                span: open.span.join(self.previous().span),
+            });
+        }
         let name_token = self.expect_ident("tag name")?;
         let name = match &name_token.kind {
             TokenKind::Ident(v) => v.clone(),
@@ -72,94 +1683,191 @@ impl<'a> Parser<'a> {
         };
 
         let mut attrs = Vec::new();
-        loop {
-            match self.current().kind {
-                TokenKind::Ident(_) => attrs.push(self.parse_attr()?),
-                _ => break,
+        while let TokenKind::Ident(_) | TokenKind::LBrace = self.current().kind {
+            let first = self.pos;
+            match self.parse_attr() {
+                Ok(attr) => attrs.push(attr),
+                Err(err) => {
+                    self.errors.push(err);
+                    self.skip_to_attr(first);
+                }
             }
         }
 
-        match self.current().kind {
+        if !matches!(self.current().kind, TokenKind::Slash | TokenKind::RAngle) {
+            let err = self.unexpected(self.current(), "`>` or `/>`");
+            self.errors.push(err);
+            self.skip_to_tag_end();
+        }
+        let children = match self.current().kind {
             TokenKind::Slash => {
-                self.pos += 1;
-                self.expect(TokenKind::RAngle, ">")?;
-                Ok(Node::Element {
-                    name,
-                    attrs,
-                    children: Vec::new(),
-                })
-            }
-            TokenKind::RAngle => {
-                self.pos += 1;
-                let mut children = Vec::new();
-                while !self.next_is_closing_tag() {
-                    if self.current_is(TokenKind::Eof) {
-                        return Err(ParseError::UnclosedTag(name.clone(), open.span));
-                    }
-                    children.push(self.parse_node()?);
-                }
-                self.expect(TokenKind::LAngle, "<")?;
-                self.expect(TokenKind::Slash, "/")?;
-                let closing_name = self.expect_ident("closing tag name")?;
-                let found_name = match closing_name.kind {
-                    TokenKind::Ident(ref v) => v.clone(),
-                    _ => unreachable!(),
-                };
-                if found_name != name {
-                    return Err(ParseError::MismatchedClose {
-                        expected: name,
-                        found: found_name,
-                        span: closing_name.span,
-                    });
-                }
-                self.expect(TokenKind::RAngle, ">")?;
-                Ok(Node::Element {
-                    name,
-                    attrs,
-                    children,
-                })
+                self.pos += 1;
+                if let Err(err) = self.expect(TokenKind::RAngle, ">") {
+                    self.errors.push(err);
+                }
+                Vec::new()
+            }
+            TokenKind::RAngle => {
+                self.pos += 1;
+                self.parse_children(&name, open.span)
+            }
+            // The tag never ended; keep what was parsed of it.
+            _ => Vec::new(),
+        };
+        Ok(Node::Element {
+            name,
+            attrs,
+            children,
+            span: open.span.join(self.previous().span),
+        })
+    }
+
+    /// Parses an element's children and its closing tag, which must match
+    /// `name` (empty for a fragment's `</>`).
+    ///
+    /// A closing tag that belongs to an enclosing element ends this one as
+    /// unclosed and is left for its owner; any other mismatched closing tag is
+    /// reported and taken as this element's.
+    fn parse_children(&mut self, name: &str, open: Span) -> Vec<Node> {
+        let mut children = Vec::new();
+        self.open_tags.push(name.to_string());
+        while !self.next_is_closing_tag() && !self.current_is(TokenKind::Eof) {
+            children.extend(self.parse_node_recovering());
+        }
+        self.open_tags.pop();
+        if self.current_is(TokenKind::Eof) {
+            self.errors
+                .push(ParseError::UnclosedTag(name.to_string(), open));
+            return children;
+        }
+        let closing = self.tokens[self.pos + 2].clone();
+        let found_name = match closing.kind {
+            TokenKind::Ident(ref v) => v.clone(),
+            _ => String::new(),
+        };
+        if found_name != name {
+            if self.open_tags.contains(&found_name) {
+                self.errors
+                    .push(ParseError::UnclosedTag(name.to_string(), open));
+                return children;
             }
-            _ => Err(self.unexpected(self.current(), "`>` or `/>`")),
+            self.errors.push(ParseError::MismatchedClose {
+                expected: name.to_string(),
+                found: found_name.clone(),
+                span: closing.span,
+            });
+        }
+        self.pos += 2;
+        if !found_name.is_empty() {
+            self.pos += 1;
+        }
+        if let Err(err) = self.expect(TokenKind::RAngle, ">") {
+            self.errors.push(err);
         }
+        children
     }
 
     fn parse_attr(&mut self) -> Result<Attr, ParseError> {
+        let start = self.current().span;
+        if self.eat(TokenKind::LBrace) {
+            if !self.eat_op("...") {
+                return Err(self.unexpected(self.current(), "`...` spread"));
+            }
+            let value = self.parse_expr()?;
+            self.expect(TokenKind::RBrace, "`}`")?;
+            return Ok(Attr::Spread {
+                value,
+                span: start.join(self.previous().span),
+            });
+        }
         let name_token = self.expect_ident("attribute name")?;
-        let name = if let TokenKind::Ident(v) = name_token.kind.clone() {
+        let mut name = if let TokenKind::Ident(v) = name_token.kind.clone() {
             v
         } else {
             unreachable!()
         };
-        self.expect(TokenKind::Equals, "=")?;
+        if self.eat(TokenKind::Colon) {
+            if XML_NAMESPACES.contains(&name.as_str()) {
+                // `xlink:href` is an ordinary attribute with a namespaced name.
+                let local = self.parse_name("attribute name")?;
+                name = format!("{}:{}", name, local.name);
+            } else {
+                let namespace = Ident {
+                    name,
+                    span: name_token.span,
+                };
+                return Ok(Attr::Directive(self.parse_directive(namespace)?));
+            }
+        }
+        if !self.eat(TokenKind::Equals) {
+            return Ok(Attr::Named {
+                name,
+                value: AttrValue::Bool,
+                span: start.join(self.previous().span),
+            });
+        }
         let value = match self.current().kind.clone() {
             TokenKind::Str(s) => {
                 self.pos += 1;
-                AttrValue::Str(s)
+                AttrValue::Str(entities::decode(&s))
             }
             TokenKind::LBrace => {
-                let expr = self.parse_expr_string()?;
+                self.pos += 1;
+                let expr = self.parse_expr()?;
+                self.expect(TokenKind::RBrace, "`}`")?;
                 AttrValue::Expr(expr)
             }
             _ => return Err(self.unexpected(self.current(), "string literal or expression")),
         };
-        Ok(Attr { name, value })
+        Ok(Attr::Named {
+            name,
+            value,
+            span: start.join(self.previous().span),
+        })
     }
 
-    fn parse_expr_string(&mut self) -> Result<String, ParseError> {
-        let lbrace = self.expect(TokenKind::LBrace, "{")?;
-        // Compute start/end directly from Span fields (no end_offset method on Span)
-        let start = lbrace.span.offset + lbrace.span.len;
-        let rbrace = self.expect(TokenKind::RBrace, "}")?;
-        let end = rbrace.span.offset;
-        let slice = self
-            .src
-            .get(start..end)
-            .ok_or_else(|| ParseError::Unexpected {
-                found: TokenKind::RBrace,
-                span: rbrace.span,
-                expected: "expression contents",
-            })?;
-        Ok(slice.trim().to_string())
+    /// Parses the rest of a `namespace:name` directive after its namespace.
+    fn parse_directive(&mut self, namespace: Ident) -> Result<Directive, ParseError> {
+        let kind = match namespace.name.as_str() {
+            "on" => DirectiveKind::On,
+            "class" => DirectiveKind::Class,
+            "bind" => DirectiveKind::Bind,
+            _ => {
+                return Err(ParseError::UnknownDirective {
+                    namespace: namespace.name,
+                    span: namespace.span,
+                })
+            }
+        };
+        let name = self.parse_name("directive name")?;
+        let value = if self.eat(TokenKind::Equals) {
+            self.expect(TokenKind::LBrace, "`{` expression")?;
+            let expr = self.parse_expr()?;
+            self.expect(TokenKind::RBrace, "`}`")?;
+            expr
+        } else if kind == DirectiveKind::On {
+            return Err(self.unexpected(self.current(), "`=` and an event handler"));
+        } else if !is_js_identifier(&name.name) {
+            // The shorthand reads a variable of the same name.
+            return Err(ParseError::ShorthandDirective {
+                name: format!("{}:{}", namespace.name, name.name),
+                span: namespace.span.join(name.span),
+            });
+        } else {
+            Expr {
+                kind: ExprKind::Ident(name.name.clone()),
+                span: name.span,
+            }
+        };
+        if kind == DirectiveKind::Bind && !matches!(value.kind, ExprKind::Ident(_)) {
+            return Err(ParseError::InvalidBindTarget(value.span));
+        }
+        Ok(Directive {
+            kind,
+            name,
+            value,
+            span: namespace.span.join(self.previous().span),
+        })
     }
 
     fn expect(
@@ -186,6 +1894,119 @@ impl<'a> Parser<'a> {
         }
     }
 
+    fn eat(&mut self, kind: TokenKind) -> bool {
+        if self.current_is(kind) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_op(&mut self, op: &str) -> bool {
+        if matches!(&self.current().kind, TokenKind::Op(found) if found == op) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn at_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.current().kind, TokenKind::Ident(name) if name == keyword)
+    }
+
+    fn expect_keyword(&mut self, keyword: &'static str) -> Result<Token, ParseError> {
+        if self.at_keyword(keyword) {
+            self.pos += 1;
+            Ok(self.previous().clone())
+        } else {
+            Err(self.unexpected(self.current(), keyword))
+        }
+    }
+
+    /// Skips the rest of an attribute that failed to parse, stopping at the
+    /// next attribute name or the end of the tag.
+    fn skip_to_attr(&mut self, first: usize) {
+        if self.pos == first {
+            self.pos += 1;
+        }
+        // Braces the attribute opened before it failed, as in `{b}`.
+        let mut depth =
+            self.tokens[first..self.pos]
+                .iter()
+                .fold(0usize, |depth, token| match token.kind {
+                    TokenKind::LBrace => depth + 1,
+                    TokenKind::RBrace => depth.saturating_sub(1),
+                    _ => depth,
+                });
+        loop {
+            match self.current().kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => depth = depth.saturating_sub(1),
+                TokenKind::Ident(_)
+                | TokenKind::RAngle
+                | TokenKind::Slash
+                | TokenKind::LAngle
+                | TokenKind::Eof
+                    if depth == 0 =>
+                {
+                    break
+                }
+                TokenKind::Eof => break,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Skips to the `>` or `/>` ending a malformed tag, or to the next `<` if
+    /// it has none.
+    fn skip_to_tag_end(&mut self) {
+        let mut depth = 0usize;
+        loop {
+            match self.current().kind {
+                TokenKind::LBrace => depth += 1,
+                TokenKind::RBrace => depth = depth.saturating_sub(1),
+                TokenKind::RAngle | TokenKind::LAngle if depth == 0 => break,
+                TokenKind::Slash
+                    if depth == 0 && *self.kind_at(self.pos + 1) == TokenKind::RAngle =>
+                {
+                    break
+                }
+                TokenKind::Eof => break,
+                _ => {}
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Skips a top-level item that failed to parse, up to the next declaration
+    /// keyword at the start of a line.
+    fn skip_to_item(&mut self, first: usize) {
+        if self.pos == first {
+            self.pos += 1;
+        }
+        while !self.current_is(TokenKind::Eof) {
+            let token = self.current();
+            if token.span.col == 1
+                && matches!(&token.kind, TokenKind::Ident(name) if ITEM_KEYWORDS.contains(&name.as_str()))
+            {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// The kind of the token at `pos`, or `Eof` past the end.
+    fn kind_at(&self, pos: usize) -> &TokenKind {
+        &self.tokens[pos.min(self.tokens.len() - 1)].kind
+    }
+
+    fn previous(&self) -> &Token {
+        &self.tokens[self.pos.saturating_sub(1)]
+    }
+
     fn next_is_closing_tag(&self) -> bool {
         matches!(
             self.tokens.get(self.pos),
@@ -221,10 +2042,108 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// Whether `name` can be used as a JavaScript variable name.
+fn is_js_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_' || first == '$')
+        && chars.all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '$')
+}
+
+/// Keywords that start a top-level item.
+const ITEM_KEYWORDS: &[&str] = &["component", "page", "server", "import", "type"];
+
+/// Namespaces of XML attributes such as `xlink:href`, which are not
+/// directives.
+const XML_NAMESPACES: &[&str] = &["xlink", "xml", "xmlns"];
+
+/// Statements passed through as raw [`Code`] rather than parsed.
+const RAW_STATEMENT_KEYWORDS: &[&str] = &["function", "class", "break", "continue"];
+
+/// Binary operators and their precedence; higher binds tighter.
+const BINARY_OPS: &[(&str, u8)] = &[
+    ("??", 1),
+    ("||", 2),
+    ("&&", 3),
+    ("|", 4),
+    ("^", 5),
+    ("&", 6),
+    ("==", 7),
+    ("!=", 7),
+    ("===", 7),
+    ("!==", 7),
+    ("<", 8),
+    (">", 8),
+    ("<=", 8),
+    (">=", 8),
+    ("in", 8),
+    ("instanceof", 8),
+    ("+", 9),
+    ("-", 9),
+    ("*", 10),
+    ("/", 10),
+    ("%", 10),
+    ("**", 11),
+];
+
+/// `as` binds like the relational operators.
+const AS_PRECEDENCE: u8 = 8;
+
+const ASSIGN_OPS: &[&str] = &["+=", "-=", "*=", "/=", "%="];
+
+/// Keywords used as prefix operators.
+const UNARY_KEYWORDS: &[&str] = &["typeof", "void", "delete", "await", "new"];
+
+/// Returns the index of the `}` closing a template placeholder whose contents
+/// start at `start`, skipping over strings and nested templates.
+fn placeholder_end(raw: &str, start: usize) -> Option<usize> {
+    let bytes = raw.as_bytes();
+    let mut depth = 0usize;
+    let mut idx = start;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return Some(idx),
+            b'}' => depth -= 1,
+            quote @ (b'"' | b'\'') => {
+                idx += 1;
+                while idx < bytes.len() && bytes[idx] != quote {
+                    if bytes[idx] == b'\\' {
+                        idx += 1;
+                    }
+                    idx += 1;
+                }
+            }
+            b'`' => idx = template_end(raw, idx + 1)?,
+            _ => {}
+        }
+        idx += 1;
+    }
+    None
+}
+
+/// Returns the index of the backtick closing a template literal whose contents
+/// start at `start`.
+fn template_end(raw: &str, start: usize) -> Option<usize> {
+    let bytes = raw.as_bytes();
+    let mut idx = start;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'\\' => idx += 1,
+            b'`' => return Some(idx),
+            b'$' if bytes.get(idx + 1) == Some(&b'{') => idx = placeholder_end(raw, idx + 2)?,
+            _ => {}
+        }
+        idx += 1;
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::lexer::lex;
+    use crate::lexer::{lex, lex_source};
 
     #[test]
     fn parses_simple_element() {
@@ -240,4 +2159,642 @@ mod tests {
             _ => panic!("expected element"),
         }
     }
+
+    #[test]
+    fn parses_component_declaration() {
+        let src = "component Link(returnUrl: string, label) {\n  // no return here\n  const text = \"}\"\n  return <a>{label}</a>\n}";
+        let tokens = lex_source(src).unwrap();
+        let file = parse_source(&tokens, src).unwrap();
+        let Item::Component(component) = &file.items[0] else {
+            panic!("expected component");
+        };
+        assert_eq!(component.name.name, "Link");
+        assert_eq!(component.params[0].name.name, "returnUrl");
+        assert_eq!(
+            component.params[0].ty.as_ref().unwrap().kind,
+            TypeKind::Named {
+                name: "string".into(),
+                args: vec![]
+            }
+        );
+        assert!(component.params[1].ty.is_none());
+        assert_eq!(component.body.stmts.len(), 2);
+        match &component.body.stmts[0] {
+            Stmt::VarDecl(decl) => {
+                assert_eq!(decl.keyword.name, "const");
+                assert_eq!(decl.target.kind, ExprKind::Ident("text".into()));
+                assert_eq!(decl.value.as_ref().unwrap().kind, ExprKind::Str("}".into()));
+                assert_eq!(decl.span.line, 3);
+            }
+            other => panic!("expected declaration, got {:?}", other),
+        }
+        match &component.body.stmts[1] {
+            Stmt::Return(Return {
+                value:
+                    Some(Expr {
+                        kind: ExprKind::Markup(node),
+                        span,
+                    }),
+                ..
+            }) => {
+                assert!(matches!(**node, Node::Element { ref name, .. } if name == "a"));
+                assert_eq!(&src[span.offset..span.end_offset()], "<a>{label}</a>");
+            }
+            other => panic!("expected markup return, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn keeps_generic_parameter_types_together() {
+        let src = "component List(items: Result<[Todo], Error>, onPick: fn(a, b) -> void) { return <ul /> }";
+        let tokens = lex_source(src).unwrap();
+        let file = parse_source(&tokens, src).unwrap();
+        let Item::Component(component) = &file.items[0] else {
+            panic!("expected component");
+        };
+        let types: Vec<_> = component
+            .params
+            .iter()
+            .map(|p| {
+                let span = p.ty.as_ref().unwrap().span;
+                &src[span.offset..span.end_offset()]
+            })
+            .collect();
+        assert_eq!(types, ["Result<[Todo], Error>", "fn(a, b) -> void"]);
+    }
+
+    #[test]
+    fn parses_page_declaration() {
+        let src = "page Index() {\n  return <TodoList />\n}";
+        let tokens = lex_source(src).unwrap();
+        let file = parse_source(&tokens, src).unwrap();
+        match &file.items[0] {
+            Item::Page(page) => {
+                assert_eq!(page.name.name, "Index");
+                assert_eq!(&src[page.keyword.offset..page.keyword.end_offset()], "page");
+            }
+            other => panic!("expected page, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_server_fn_signature() {
+        let src = "server fn listTodos(owner: uuid) -> [Todo] {\n  return db.todo.findMany({})\n}";
+        let tokens = lex_source(src).unwrap();
+        let file = parse_source(&tokens, src).unwrap();
+        let Item::ServerFn(func) = &file.items[0] else {
+            panic!("expected server fn");
+        };
+        assert_eq!(func.name.name, "listTodos");
+        assert_eq!(
+            &src[func.keyword.offset..func.keyword.end_offset()],
+            "server fn"
+        );
+        assert_eq!(func.params[0].name.name, "owner");
+        let ret = func.ret.as_ref().unwrap();
+        assert!(matches!(&ret.kind, TypeKind::Array(inner)
+            if matches!(&inner.kind, TypeKind::Named { name, .. } if name == "Todo")));
+        match &func.body.stmts[0] {
+            Stmt::Return(Return {
+                value: Some(value), ..
+            }) => {
+                assert!(matches!(value.kind, ExprKind::Call { .. }));
+                assert_eq!(
+                    &src[value.span.offset..value.span.end_offset()],
+                    "db.todo.findMany({})"
+                );
+            }
+            other => panic!("expected return, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_defaults_and_optional_markers() {
+        let src = "component Badge(label: string, tone: string = \"neutral\", icon?: string, size = fn(1, 2)) { return <span /> }";
+        let tokens = lex_source(src).unwrap();
+        let file = parse_source(&tokens, src).unwrap();
+        let Item::Component(component) = &file.items[0] else {
+            panic!("expected component");
+        };
+        let params = &component.params;
+        assert!(!params[0].is_optional());
+        assert_eq!(
+            params[1].default.as_ref().unwrap().kind,
+            ExprKind::Str("neutral".into())
+        );
+        assert!(params[1].is_optional());
+        assert!(params[2].optional.is_some() && params[2].default.is_none());
+        assert!(params[3].ty.is_none());
+        let size = params[3].default.as_ref().unwrap();
+        assert!(matches!(size.kind, ExprKind::Call { .. }));
+        assert_eq!(&src[size.span.offset..size.span.end_offset()], "fn(1, 2)");
+        let span = params[1].span;
+        assert_eq!(
+            &src[span.offset..span.end_offset()],
+            "tone: string = \"neutral\""
+        );
+    }
+
+    #[test]
+    fn parses_optional_and_function_types() {
+        let src = "component C(icon: string?, onPick: fn(int) -> void) { return <i /> }";
+        let tokens = lex_source(src).unwrap();
+        let file = parse_source(&tokens, src).unwrap();
+        let Item::Component(component) = &file.items[0] else {
+            panic!("expected component");
+        };
+        let icon = component.params[0].ty.as_ref().unwrap();
+        assert!(matches!(icon.kind, TypeKind::Optional(_)));
+        assert_eq!(&src[icon.span.offset..icon.span.end_offset()], "string?");
+        let on_pick = component.params[1].ty.as_ref().unwrap();
+        assert!(matches!(
+            &on_pick.kind,
+            TypeKind::Function { params, ret: Some(_) } if params.len() == 1
+        ));
+    }
+
+    #[test]
+    fn parses_imports_and_type_declarations() {
+        let src = "import { TodoList, TodoItem } from \"../components/TodoList\"\nimport Index from \"./pages/index\";\ntype Todo {\n  id: uuid,\n  tags: [string]\n}\ntype Id = uuid";
+        let tokens = lex_source(src).unwrap();
+        let file = parse_source(&tokens, src).unwrap();
+        assert_eq!(file.items.len(), 4);
+        let Item::Import(import) = &file.items[0] else {
+            panic!("expected import");
+        };
+        let names: Vec<_> = import.names.iter().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, ["TodoList", "TodoItem"]);
+        assert_eq!(import.source, "../components/TodoList");
+        let Item::Import(import) = &file.items[1] else {
+            panic!("expected import");
+        };
+        assert_eq!(import.default.as_ref().unwrap().name, "Index");
+        let Item::TypeDecl(decl) = &file.items[2] else {
+            panic!("expected type declaration");
+        };
+        match &decl.body {
+            TypeDeclBody::Fields(fields) => assert_eq!(fields.len(), 2),
+            other => panic!("expected fields, got {:?}", other),
+        }
+        let Item::TypeDecl(decl) = &file.items[3] else {
+            panic!("expected type declaration");
+        };
+        assert!(matches!(decl.body, TypeDeclBody::Alias(_)));
+    }
+
+    fn markup_expr(src: &str) -> Expr {
+        let tokens = lex(src).unwrap();
+        let doc = parse(&tokens, src).unwrap();
+        let Node::Element { children, .. } = &doc.children[0] else {
+            panic!("expected element");
+        };
+        match &children[0] {
+            Node::Expr(expr) => expr.clone(),
+            other => panic!("expected expression, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_markup_expressions_with_precedence() {
+        let expr = markup_expr("<p>{a + b * c > 1 && !done ? \"x\" : y}</p>");
+        let ExprKind::Conditional { test, .. } = expr.kind else {
+            panic!("expected conditional, got {:?}", expr.kind);
+        };
+        let ExprKind::Binary { op, left, right } = test.kind else {
+            panic!("expected binary");
+        };
+        assert_eq!(op, "&&");
+        assert!(matches!(right.kind, ExprKind::Unary { ref op, .. } if op == "!"));
+        let ExprKind::Binary { op, left, .. } = left.kind else {
+            panic!("expected comparison");
+        };
+        assert_eq!(op, ">");
+        assert!(
+            matches!(left.kind, ExprKind::Binary { ref op, ref right, .. }
+            if op == "+" && matches!(right.kind, ExprKind::Binary { ref op, .. } if op == "*"))
+        );
+    }
+
+    #[test]
+    fn parses_calls_members_and_arrows() {
+        let src =
+            "<ul>{items.filter((item) => item.done).map(item => <li>{item?.title}</li>)}</ul>";
+        let expr = markup_expr(src);
+        let ExprKind::Call { callee, args, .. } = expr.kind else {
+            panic!("expected call");
+        };
+        assert!(
+            matches!(callee.kind, ExprKind::Member { ref property, .. } if property.name == "map")
+        );
+        let ExprKind::Arrow { params, body, .. } = &args[0].kind else {
+            panic!("expected arrow");
+        };
+        assert_eq!(params[0].kind, ExprKind::Ident("item".into()));
+        assert!(matches!(body, ArrowBody::Expr(body) if matches!(body.kind, ExprKind::Markup(_))));
+        let span = args[0].span;
+        assert_eq!(
+            &src[span.offset..span.end_offset()],
+            "item => <li>{item?.title}</li>"
+        );
+    }
+
+    #[test]
+    fn parses_object_array_and_template_literals() {
+        let src = "<p>{f({ id, \"a-b\": [1, ...rest], [key]: `n=${n + 1}` })}</p>";
+        let expr = markup_expr(src);
+        let ExprKind::Call { args, .. } = expr.kind else {
+            panic!("expected call");
+        };
+        let ExprKind::Object(props) = &args[0].kind else {
+            panic!("expected object");
+        };
+        assert!(matches!(&props[0], Prop::Shorthand(ident) if ident.name == "id"));
+        assert!(
+            matches!(&props[1], Prop::KeyValue { key: PropKey::Str(key), value }
+            if key == "a-b" && matches!(&value.kind, ExprKind::Array(items)
+                if matches!(items[1].kind, ExprKind::Spread(_))))
+        );
+        let Prop::KeyValue {
+            key: PropKey::Computed(_),
+            value,
+        } = &props[2]
+        else {
+            panic!("expected computed key");
+        };
+        let ExprKind::Template(parts) = &value.kind else {
+            panic!("expected template");
+        };
+        assert_eq!(parts[0], TemplatePart::Text("n=".into()));
+        let TemplatePart::Expr(inner) = &parts[1] else {
+            panic!("expected placeholder");
+        };
+        assert_eq!(&src[inner.span.offset..inner.span.end_offset()], "n + 1");
+        assert!(matches!(inner.kind, ExprKind::Binary { .. }));
+    }
+
+    #[test]
+    fn parses_statements() {
+        let src = "component C() {\n  let count = 0\n  count += 1; log(count)\n  if (count > 1) {\n    reset()\n  } else {\n    bump()\n  }\n  return <p />\n}";
+        let tokens = lex_source(src).unwrap();
+        let file = parse_source(&tokens, src).unwrap();
+        let Item::Component(component) = &file.items[0] else {
+            panic!("expected component");
+        };
+        let stmts = &component.body.stmts;
+        assert_eq!(stmts.len(), 5);
+        assert!(matches!(stmts[0], Stmt::VarDecl(_)));
+        assert!(
+            matches!(&stmts[1], Stmt::Expr(Expr { kind: ExprKind::Assign { op, .. }, .. }) if op == "+=")
+        );
+        assert!(matches!(
+            &stmts[2],
+            Stmt::Expr(Expr {
+                kind: ExprKind::Call { .. },
+                ..
+            })
+        ));
+        let Stmt::If(stmt) = &stmts[3] else {
+            panic!("expected if statement");
+        };
+        assert!(matches!(stmt.cond.kind, ExprKind::Binary { .. }));
+        assert!(matches!(stmt.then.stmts[..], [Stmt::Expr(_)]));
+        assert!(matches!(&stmt.otherwise, Some(Else::Block(block)) if block.stmts.len() == 1));
+        assert!(matches!(stmts[4], Stmt::Return(_)));
+    }
+
+    fn stmts(body: &str) -> Vec<Stmt> {
+        let src = format!("component C() {{\n{}\n  return <p />\n}}", body);
+        let tokens = lex_source(&src).unwrap();
+        let file = parse_source(&tokens, &src).unwrap();
+        let Item::Component(mut component) = file.items.into_iter().next().unwrap() else {
+            panic!("expected component");
+        };
+        component.body.stmts.pop();
+        component.body.stmts
+    }
+
+    #[test]
+    fn parses_control_flow_statements() {
+        let parsed = stmts(
+            "  if (a) return <b />; else if (b) x = 1; else { y() }\n\
+             \x20 for (let i = 0; i < n; i++) total += i\n\
+             \x20 for (const { id } of items) { seen.add(id) }\n\
+             \x20 for (key in table) {}\n\
+             \x20 while (busy) { wait() }\n\
+             \x20 do { step() } while (more)\n\
+             \x20 switch (kind) {\n    case \"a\": return <i />\n    default:\n      skip()\n      break\n  }\n\
+             \x20 try { risky() } catch (err) { throw err } finally { done() }",
+        );
+        assert_eq!(parsed.len(), 8);
+        let Stmt::If(stmt) = &parsed[0] else {
+            panic!("expected if, got {:?}", parsed[0]);
+        };
+        assert!(matches!(stmt.then.stmts[..], [Stmt::Return(_)]));
+        let Some(Else::If(next)) = &stmt.otherwise else {
+            panic!("expected else if");
+        };
+        assert!(matches!(next.otherwise, Some(Else::Block(_))));
+
+        let Stmt::For(steps) = &parsed[1] else {
+            panic!("expected for");
+        };
+        assert!(matches!(
+            &steps.head,
+            ForHead::Steps { init: Some(init), test: Some(_), update: Some(_) }
+                if matches!(**init, Stmt::VarDecl(_))
+        ));
+        assert!(matches!(steps.body.stmts[..], [Stmt::Expr(_)]));
+        assert!(matches!(
+            &parsed[2],
+            Stmt::For(each) if matches!(
+                &each.head,
+                ForHead::Each { keyword: Some(_), binding: Expr { kind: ExprKind::Object(_), .. }, of: true, .. }
+            )
+        ));
+        assert!(matches!(
+            &parsed[3],
+            Stmt::For(each) if matches!(each.head, ForHead::Each { keyword: None, of: false, .. })
+        ));
+        assert!(matches!(&parsed[4], Stmt::While(stmt) if !stmt.is_do));
+        assert!(matches!(&parsed[5], Stmt::While(stmt) if stmt.is_do));
+        let Stmt::Switch(switch) = &parsed[6] else {
+            panic!("expected switch");
+        };
+        assert_eq!(switch.cases.len(), 2);
+        assert!(matches!(switch.cases[0].body[..], [Stmt::Return(_)]));
+        assert!(switch.cases[1].test.is_none());
+        assert!(matches!(
+            switch.cases[1].body[..],
+            [Stmt::Expr(_), Stmt::Code(_)]
+        ));
+        let Stmt::Try(stmt) = &parsed[7] else {
+            panic!("expected try");
+        };
+        assert!(matches!(
+            stmt.catch.as_ref().unwrap().body.stmts[..],
+            [Stmt::Throw(_)]
+        ));
+        assert!(stmt.finally.is_some());
+    }
+
+    #[test]
+    fn rejects_unfinished_expression() {
+        let src = "<p>{a +}</p>";
+        let tokens = lex(src).unwrap();
+        let err = parse(&tokens, src).unwrap_err();
+        assert!(matches!(
+            err,
+            ParseError::Unexpected {
+                found: TokenKind::RBrace,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn parses_fragments_and_attribute_forms() {
+        let src = "<><input disabled type=\"text\" {...props} /></>";
+        let tokens = lex(src).unwrap();
+        let doc = parse(&tokens, src).unwrap();
+        let Node::Fragment { children, .. } = &doc.children[0] else {
+            panic!("expected fragment, got {:?}", doc.children[0]);
+        };
+        let Node::Element { attrs, .. } = &children[0] else {
+            panic!("expected element");
+        };
+        assert!(
+            matches!(&attrs[0], Attr::Named { name, value: AttrValue::Bool, .. } if name == "disabled")
+        );
+        assert!(matches!(&attrs[1], Attr::Named { value: AttrValue::Str(v), .. } if v == "text"));
+        assert!(
+            matches!(&attrs[2], Attr::Spread { value: Expr { kind: ExprKind::Ident(v), .. }, .. } if v == "props")
+        );
+    }
+
+    #[test]
+    fn records_spans_of_whole_elements_and_attributes() {
+        let src = "<div class=\"a\">\n  <b {...rest} on:click={go}>hi</b>\n</div>";
+        let tokens = lex(src).unwrap();
+        let doc = parse(&tokens, src).unwrap();
+        let div = &doc.children[0];
+        let span = div.span();
+        assert_eq!(
+            (span.line, span.col, span.end_line, span.end_col),
+            (1, 1, 3, 7)
+        );
+        assert_eq!(span.offset, 0);
+        assert_eq!(span.len, src.len());
+        assert_eq!(doc.span.end_offset(), src.len());
+
+        let Node::Element {
+            attrs, children, ..
+        } = div
+        else {
+            panic!("expected element");
+        };
+        let class = attrs[0].span();
+        assert_eq!(&src[class.offset..class.end_offset()], "class=\"a\"");
+        assert_eq!((class.end_line, class.end_col), (1, 15));
+
+        let b = children
+            .iter()
+            .find(|child| matches!(child, Node::Element { .. }))
+            .expect("nested element");
+        let Node::Element {
+            attrs, children, ..
+        } = b
+        else {
+            unreachable!()
+        };
+        let b = b.span();
+        assert_eq!(
+            &src[b.offset..b.end_offset()],
+            "<b {...rest} on:click={go}>hi</b>"
+        );
+        assert_eq!((b.line, b.col, b.end_line, b.end_col), (2, 3, 2, 36));
+        let texts: Vec<_> = attrs
+            .iter()
+            .map(|attr| &src[attr.span().offset..attr.span().end_offset()])
+            .collect();
+        assert_eq!(texts, ["{...rest}", "on:click={go}"]);
+        let hi = children[0].span();
+        assert_eq!((hi.line, hi.col, hi.end_col), (2, 30, 32));
+    }
+
+    #[test]
+    fn parses_directives() {
+        let src = "<input on:input={log} class:active bind:value={name} />";
+        let tokens = lex(src).unwrap();
+        let doc = parse(&tokens, src).unwrap();
+        let Node::Element { attrs, .. } = &doc.children[0] else {
+            panic!("expected element");
+        };
+        let directives: Vec<_> = attrs
+            .iter()
+            .map(|attr| match attr {
+                Attr::Directive(d) => (d.kind, d.name.name.as_str(), d.value.kind.clone()),
+                other => panic!("expected directive, got {:?}", other),
+            })
+            .collect();
+        assert_eq!(
+            directives,
+            [
+                (DirectiveKind::On, "input", ExprKind::Ident("log".into())),
+                (
+                    DirectiveKind::Class,
+                    "active",
+                    ExprKind::Ident("active".into())
+                ),
+                (DirectiveKind::Bind, "value", ExprKind::Ident("name".into())),
+            ]
+        );
+        let Attr::Directive(first) = &attrs[0] else {
+            unreachable!()
+        };
+        assert_eq!(
+            &src[first.span.offset..first.span.end_offset()],
+            "on:input={log}"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_directives() {
+        let cases = [
+            "<p use:tooltip={t} />",
+            "<p on:click />",
+            "<input bind:value={form.name} />",
+            "<li class:is-done />",
+            "<input bind:aria-label />",
+        ];
+        let errors: Vec<_> = cases
+            .iter()
+            .map(|src| parse(&lex(src).unwrap(), src).unwrap_err())
+            .collect();
+        assert!(
+            matches!(&errors[0], ParseError::UnknownDirective { namespace, .. } if namespace == "use")
+        );
+        assert!(matches!(errors[1], ParseError::Unexpected { .. }));
+        assert!(matches!(errors[2], ParseError::InvalidBindTarget(_)));
+        assert!(
+            matches!(&errors[3], ParseError::ShorthandDirective { name, .. } if name == "class:is-done")
+        );
+        assert!(
+            matches!(&errors[4], ParseError::ShorthandDirective { name, .. } if name == "bind:aria-label")
+        );
+    }
+
+    #[test]
+    fn rejects_mismatched_fragment_close() {
+        let src = "<><p>hi</p></div>";
+        let tokens = lex(src).unwrap();
+        match parse(&tokens, src).unwrap_err() {
+            ParseError::MismatchedClose {
+                expected, found, ..
+            } => assert_eq!((expected.as_str(), found.as_str()), ("", "div")),
+            other => panic!("expected mismatched close, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_top_level_item() {
+        let src = "widget App() {}";
+        let tokens = lex_source(src).unwrap();
+        let err = parse_source(&tokens, src).unwrap_err();
+        assert!(matches!(err, ParseError::Unexpected { .. }));
+    }
+
+    #[test]
+    fn recovers_at_attribute_and_tag_boundaries() {
+        let src =
+            "<div>\n  <img alt= src={a} />\n  <p use:tip={t} {b}>{c +}</p>\n  <i>ok</i>\n</div>";
+        let tokens = lex(src).unwrap();
+        let (doc, errors) = parse_recovering(&tokens, src);
+        assert_eq!(errors.len(), 4, "{:#?}", errors);
+        assert!(
+            matches!(&errors[0], ParseError::Unexpected { found: TokenKind::Ident(name), .. } if name == "src")
+        );
+        assert!(matches!(errors[1], ParseError::UnknownDirective { .. }));
+        assert!(
+            matches!(&errors[2], ParseError::Unexpected { found: TokenKind::Ident(name), expected, .. } if name == "b" && expected.contains("spread"))
+        );
+        assert!(matches!(
+            errors[3],
+            ParseError::Unexpected {
+                found: TokenKind::RBrace,
+                ..
+            }
+        ));
+
+        let Node::Element { children, .. } = &doc.children[0] else {
+            panic!("expected element");
+        };
+        let elements: Vec<_> = children
+            .iter()
+            .filter_map(|child| match child {
+                Node::Element {
+                    name,
+                    attrs,
+                    children,
+                    ..
+                } => Some((name.as_str(), attrs.len(), children)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(elements.len(), 3);
+        // `src` is kept; `alt=` is dropped.
+        assert_eq!((elements[0].0, elements[0].1), ("img", 1));
+        assert_eq!(elements[1].0, "p");
+        let Node::Error { span } = &elements[1].2[0] else {
+            panic!("expected an error node, got {:?}", elements[1].2);
+        };
+        assert_eq!(&src[span.offset..span.end_offset()], "{c +}");
+        assert_eq!(elements[2].0, "i");
+        assert_eq!(parse(&tokens, src).unwrap_err().span(), errors[0].span());
+    }
+
+    #[test]
+    fn closing_tags_of_enclosing_elements_end_unclosed_ones() {
+        let src = "<ul><li>one<li>two</ul><p>ok</b>";
+        let tokens = lex(src).unwrap();
+        let (doc, errors) = parse_recovering(&tokens, src);
+        let messages: Vec<_> = errors.iter().map(|err| err.to_string()).collect();
+        assert_eq!(errors.len(), 3, "{:#?}", messages);
+        assert!(
+            matches!(&errors[0], ParseError::UnclosedTag(name, span) if name == "li" && span.offset == 11)
+        );
+        assert!(
+            matches!(&errors[1], ParseError::UnclosedTag(name, span) if name == "li" && span.offset == 4)
+        );
+        assert!(
+            matches!(&errors[2], ParseError::MismatchedClose { expected, found, .. } if expected == "p" && found == "b")
+        );
+        // The `</ul>` still closed the list and `</b>` stood in for `</p>`.
+        assert_eq!(doc.children.len(), 2);
+        assert!(matches!(&doc.children[1], Node::Element { name, .. } if name == "p"));
+    }
+
+    #[test]
+    fn reports_a_missing_brace_before_a_closing_tag() {
+        let src = "<p>\n  {items.map((i) => { i })</p>";
+        let tokens = lex(src).unwrap();
+        match parse(&tokens, src).unwrap_err() {
+            ParseError::UnclosedBlock(span) => assert_eq!((span.line, span.col), (2, 3)),
+            other => panic!("expected unclosed block, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn skips_items_that_fail_to_parse() {
+        let src = "widget App() {}\ncomponent A() { return <a>{x +}</a> }\ncomponent B( { }\ntype T { id: string }\n";
+        let tokens = lex_source(src).unwrap();
+        let (file, errors) = parse_source_recovering(&tokens, src);
+        assert_eq!(errors.len(), 3, "{:#?}", errors);
+        assert_eq!(
+            errors.iter().map(|err| err.span().line).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(file.items.len(), 2);
+        assert!(matches!(&file.items[0], Item::Component(component) if component.name.name == "A"));
+        assert!(matches!(file.items[1], Item::TypeDecl(_)));
+    }
 }
@@ -0,0 +1,342 @@
+//! RPC glue for `server fn` declarations.
+//!
+//! Each server function is reachable at `POST /_rpc/<name>`. The request body is
+//! a JSON object keyed by parameter name and the response body is the JSON
+//! encoded return value. Values are encoded with plain `JSON.stringify`, so the
+//! only thing either side has to undo is the `Date` to ISO string conversion.
+
+use crate::ast::{Import, ServerFn, Type, TypeKind};
+use crate::codegen::{promise_type, render_ts_param, PRIMITIVE_TYPES};
+
+/// Path prefix every server function is served under.
+pub const RPC_PATH: &str = "/_rpc/";
+
+/// Renders the server-side module: a dispatch table from function name to
+/// handler plus a `handleRpc` entry point for any `fetch`-style server.
+/// `module` is the import path of the compiled server functions.
+pub fn render_server(funcs: &[&ServerFn], module: &str) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("import * as api from \"{}\";\n\n", module));
+    out.push_str("// eslint-disable-next-line @typescript-eslint/no-explicit-any\n");
+    out.push_str("type Handler = (args: any) => Promise<unknown>;\n\n");
+    out.push_str("export const handlers: Record<string, Handler> = {\n");
+    for func in funcs {
+        let args: Vec<String> = func
+            .params
+            .iter()
+            .map(|param| {
+                let value = format!("args.{}", param.name.name);
+                match &param.ty {
+                    Some(ty) => decode(ty, &value),
+                    None => value,
+                }
+            })
+            .collect();
+        let binding = if args.is_empty() { "()" } else { "(args)" };
+        out.push_str(&format!(
+            "  {}: {} => api.{}({}),\n",
+            func.name.name,
+            binding,
+            func.name.name,
+            args.join(", ")
+        ));
+    }
+    out.push_str("};\n\n");
+    out.push_str("export async function handleRpc(request: Request): Promise<Response> {\n");
+    out.push_str("  if (request.method !== \"POST\") {\n");
+    out.push_str(
+        "    return new Response(\"Method Not Allowed\", { status: 405, headers: { Allow: \"POST\" } });\n",
+    );
+    out.push_str("  }\n");
+    out.push_str(&format!(
+        "  const name = new URL(request.url).pathname.slice(\"{}\".length);\n",
+        RPC_PATH
+    ));
+    out.push_str("  if (!Object.hasOwn(handlers, name)) {\n");
+    out.push_str(
+        "    return Response.json({ error: `unknown server function: ${name}` }, { status: 404 });\n",
+    );
+    out.push_str("  }\n");
+    out.push_str("  const args = await request.json();\n");
+    out.push_str("  return Response.json(await handlers[name](args));\n");
+    out.push_str("}\n");
+    out
+}
+
+/// Renders the client-side module: one stub per server function with the same
+/// signature and return type, each posting its arguments to the matching endpoint.
+/// The types in those signatures are imported from wherever the file imported
+/// them, or else from its compiled output at `module`.
+pub fn render_client(funcs: &[&ServerFn], imports: &[&Import], module: &str) -> String {
+    let mut out = type_imports(funcs, imports, module);
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str("async function call(name: string, args: Record<string, unknown>) {\n");
+    out.push_str(&format!(
+        "  const response = await fetch(`{}${{name}}`, {{\n",
+        RPC_PATH
+    ));
+    out.push_str("    method: \"POST\",\n");
+    out.push_str("    headers: { \"Content-Type\": \"application/json\" },\n");
+    out.push_str("    body: JSON.stringify(args),\n");
+    out.push_str("  });\n");
+    out.push_str("  if (!response.ok) {\n");
+    out.push_str(
+        "    throw new Error(`server function ${name} failed with status ${response.status}`);\n",
+    );
+    out.push_str("  }\n");
+    out.push_str("  return response.json();\n");
+    out.push_str("}\n");
+    for func in funcs {
+        let params: Vec<String> = func.params.iter().map(render_ts_param).collect();
+        let names: Vec<&str> = func
+            .params
+            .iter()
+            .map(|param| param.name.name.as_str())
+            .collect();
+        let args = if names.is_empty() {
+            "{}".to_string()
+        } else {
+            format!("{{ {} }}", names.join(", "))
+        };
+        let call = format!("await call(\"{}\", {})", func.name.name, args);
+        let value = match &func.ret {
+            Some(ty) => decode(ty, &call),
+            None => call,
+        };
+        out.push_str(&format!(
+            "\nexport async function {}({}){} {{\n  return {};\n}}\n",
+            func.name.name,
+            params.join(", "),
+            promise_type(func),
+            value
+        ));
+    }
+    out
+}
+
+/// One `import type` line per module the user-declared types in `funcs`'
+/// signatures come from.
+fn type_imports(funcs: &[&ServerFn], imports: &[&Import], module: &str) -> String {
+    let mut names = Vec::new();
+    for func in funcs {
+        let types = func.params.iter().filter_map(|param| param.ty.as_ref());
+        for ty in types.chain(&func.ret) {
+            named_types(ty, &mut names);
+        }
+    }
+    let mut sources: Vec<(&str, Vec<&str>)> = Vec::new();
+    for name in names {
+        let source = imports
+            .iter()
+            .find(|import| import.names.iter().any(|ident| ident.name == name))
+            .map_or(module, |import| import.source.as_str());
+        match sources.iter_mut().find(|(known, _)| *known == source) {
+            Some((_, group)) => group.push(name),
+            None => sources.push((source, vec![name])),
+        }
+    }
+    sources
+        .iter()
+        .map(|(source, names)| {
+            format!(
+                "import type {{ {} }} from \"{}\";\n",
+                names.join(", "),
+                source
+            )
+        })
+        .collect()
+}
+
+/// Collects the names in `ty` that are neither primitives nor `Result`, which
+/// compiles to an object type of its own.
+fn named_types<'a>(ty: &'a Type, names: &mut Vec<&'a str>) {
+    match &ty.kind {
+        TypeKind::Named { name, args } => {
+            let structural = name == "Result" && args.len() == 2;
+            let primitive = PRIMITIVE_TYPES.iter().any(|(raven, _)| raven == name);
+            if !structural && !primitive && !names.contains(&name.as_str()) {
+                names.push(name);
+            }
+            for arg in args {
+                named_types(arg, names);
+            }
+        }
+        TypeKind::Array(inner) | TypeKind::Optional(inner) => named_types(inner, names),
+        TypeKind::Function { params, ret } => {
+            for param in params {
+                named_types(param, names);
+            }
+            if let Some(ret) = ret {
+                named_types(ret, names);
+            }
+        }
+    }
+}
+
+/// Returns the TypeScript expression turning the JSON-decoded `value` back into
+/// a value of type `ty`. Only dates need reviving; everything else that
+/// `JSON.stringify` produces already has the right shape.
+fn decode(ty: &Type, value: &str) -> String {
+    if !contains_date(ty) {
+        return value.to_string();
+    }
+    match &ty.kind {
+        TypeKind::Array(inner) => format!("({}).map((item) => {})", value, decode(inner, "item")),
+        TypeKind::Optional(inner) => format!(
+            "((item) => item == null ? undefined : {})({})",
+            decode(inner, "item"),
+            value
+        ),
+        _ => format!("new Date({})", value),
+    }
+}
+
+fn contains_date(ty: &Type) -> bool {
+    match &ty.kind {
+        TypeKind::Named { name, .. } => name == "datetime",
+        TypeKind::Array(inner) | TypeKind::Optional(inner) => contains_date(inner),
+        TypeKind::Function { .. } => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::Item;
+    use crate::lexer::lex_source;
+    use crate::parser::parse_source;
+
+    fn server_fns(src: &str) -> Vec<ServerFn> {
+        let tokens = lex_source(src).unwrap();
+        parse_source(&tokens, src)
+            .unwrap()
+            .items
+            .into_iter()
+            .filter_map(|item| match item {
+                Item::ServerFn(func) => Some(func),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn renders_single_dispatch_table() {
+        let funcs = server_fns(
+            "server fn getUser(id: uuid) -> User { return db.users.find(id) }\n\
+             server fn since(at: datetime) -> [Todo] { return db.todo.since(at) }\n\
+             server fn stats() -> Stats { return db.stats() }",
+        );
+        let refs: Vec<&ServerFn> = funcs.iter().collect();
+        let server = render_server(&refs, "./api");
+        assert!(server.starts_with("import * as api from \"./api\";\n"));
+        assert!(server.contains(
+            "export const handlers: Record<string, Handler> = {\n  getUser: (args) => api.getUser(args.id),\n  since: (args) => api.since(new Date(args.at)),\n  stats: () => api.stats(),\n};\n"
+        ));
+        assert!(server.contains("if (request.method !== \"POST\")"));
+        assert!(server.contains("pathname.slice(\"/_rpc/\".length)"));
+        assert!(server.contains("Object.hasOwn(handlers, name)"));
+    }
+
+    #[test]
+    fn renders_client_stub_with_server_signature() {
+        let funcs = server_fns(
+            "server fn getUser(id: uuid) -> User { return db.users.find(id) }\n\
+             server fn listTodos() -> [Todo] { return db.todo.findMany({}) }",
+        );
+        let refs: Vec<&ServerFn> = funcs.iter().collect();
+        let client = render_client(&refs, &[], "./api");
+        assert!(client.starts_with("import type { User, Todo } from \"./api\";\n\n"));
+        assert!(client.contains("fetch(`/_rpc/${name}`"));
+        assert!(client.contains("method: \"POST\""));
+        assert!(client.contains(
+            "export async function getUser(id: string): Promise<User> {\n  return await call(\"getUser\", { id });\n}\n"
+        ));
+        assert!(client.contains(
+            "export async function listTodos(): Promise<Todo[]> {\n  return await call(\"listTodos\", {});\n}\n"
+        ));
+    }
+
+    #[test]
+    fn imports_signature_types_from_where_the_file_got_them() {
+        let src = "import { Team } from \"./teams\"\n\
+                   server fn members(team: Team, since: datetime?) -> Result<[User], Error> { return db.users.of(team) }";
+        let tokens = lex_source(src).unwrap();
+        let file = parse_source(&tokens, src).unwrap();
+        let imports: Vec<&Import> = file
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                Item::Import(import) => Some(import),
+                _ => None,
+            })
+            .collect();
+        let funcs = server_fns(src);
+        let refs: Vec<&ServerFn> = funcs.iter().collect();
+        let client = render_client(&refs, &imports, "./members");
+        assert!(client.starts_with(
+            "import type { Team } from \"./teams\";\nimport type { User, Error } from \"./members\";\n\n"
+        ));
+        // Primitives need no import.
+        let funcs = server_fns("server fn ping(at: datetime) -> string { return \"pong\" }");
+        let refs: Vec<&ServerFn> = funcs.iter().collect();
+        assert!(render_client(&refs, &[], "./api").starts_with("async function call"));
+    }
+
+    #[test]
+    fn json_rules_round_trip_each_param_type() {
+        // (param type, TS parameter on both ends, how the server revives `args.x`)
+        let cases = [
+            ("string", ": string", "args.x"),
+            ("uuid", ": string", "args.x"),
+            ("int", ": number", "args.x"),
+            ("float", ": number", "args.x"),
+            ("bool", ": boolean", "args.x"),
+            ("datetime", ": Date", "new Date(args.x)"),
+            ("[string]", ": string[]", "args.x"),
+            (
+                "[datetime]",
+                ": Date[]",
+                "(args.x).map((item) => new Date(item))",
+            ),
+            (
+                "[[datetime]]",
+                ": Date[][]",
+                "(args.x).map((item) => (item).map((item) => new Date(item)))",
+            ),
+            (
+                "datetime?",
+                "?: Date",
+                "((item) => item == null ? undefined : new Date(item))(args.x)",
+            ),
+            ("Todo", ": Todo", "args.x"),
+        ];
+        for (raven, ts, revived) in cases {
+            let src = format!("server fn f(x: {}) -> {} {{ return x }}", raven, raven);
+            let funcs = server_fns(&src);
+            let refs: Vec<&ServerFn> = funcs.iter().collect();
+
+            let client = render_client(&refs, &[], "./api");
+            assert!(
+                client.contains(&format!("export async function f(x{})", ts)),
+                "client signature for {}",
+                raven
+            );
+            let server = render_server(&refs, "./api");
+            assert!(
+                server.contains(&format!("f: (args) => api.f({}),", revived)),
+                "server decoding for {}",
+                raven
+            );
+            // The client revives the return value with the same rule the server
+            // applies to arguments.
+            let returned = revived.replace("args.x", "await call(\"f\", { x })");
+            assert!(
+                client.contains(&format!("return {};", returned)),
+                "client decoding for {}",
+                raven
+            );
+        }
+    }
+}
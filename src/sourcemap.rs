@@ -0,0 +1,352 @@
+//! Source Map v3 support for generated TSX.
+//!
+//! Codegen writes into an [`Output`], which records the source [`Span`] each
+//! piece of generated text came from. [`render`] turns those records into a
+//! standard `.map` file whose `mappings` field uses the base64 VLQ encoding
+//! described at <https://sourcemaps.info/spec.html>. Columns on both sides are
+//! counted in UTF-16 code units, as JavaScript tooling expects.
+
+use serde_json::json;
+use thiserror::Error;
+
+use crate::token::Span;
+
+/// Generated text together with the source positions it was produced from.
+#[derive(Debug, Clone, Default)]
+pub struct Output {
+    text: String,
+    /// Zero-based line and UTF-16 column where the next character will go.
+    line: u32,
+    col: u32,
+    mappings: Vec<Mapping>,
+}
+
+/// The generated text starting at `line:col` comes from `source`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Mapping {
+    line: u32,
+    col: u32,
+    source: Span,
+}
+
+impl Output {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, ch: char) {
+        self.text.push(ch);
+        self.advance(ch);
+    }
+
+    pub fn push_str(&mut self, text: &str) {
+        self.text.push_str(text);
+        for ch in text.chars() {
+            self.advance(ch);
+        }
+    }
+
+    /// Records that the text pushed next was generated from `span`.
+    pub fn map(&mut self, span: Span) {
+        self.mappings.push(Mapping {
+            line: self.line,
+            col: self.col,
+            source: span,
+        });
+    }
+
+    /// Appends `other`, moving its mappings to where its text ends up.
+    pub fn append(&mut self, other: Output) {
+        for mapping in other.mappings {
+            self.mappings.push(Mapping {
+                line: self.line + mapping.line,
+                col: if mapping.line == 0 {
+                    self.col + mapping.col
+                } else {
+                    mapping.col
+                },
+                source: mapping.source,
+            });
+        }
+        self.push_str(&other.text);
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn into_string(self) -> String {
+        self.text
+    }
+
+    fn advance(&mut self, ch: char) {
+        if ch == '\n' {
+            self.line += 1;
+            self.col = 0;
+        } else {
+            self.col += ch.len_utf16() as u32;
+        }
+    }
+}
+
+/// Renders the source map for `output`, which was compiled from `source`.
+/// `file` is the generated file's name and `source_name` the path of the
+/// `.raven` file relative to the map.
+pub fn render(output: &Output, source: &str, file: &str, source_name: &str) -> String {
+    let map = json!({
+        "version": 3,
+        "file": file,
+        "sources": [source_name],
+        "sourcesContent": [source],
+        "names": [],
+        "mappings": encode_mappings(output, source),
+    });
+    map.to_string()
+}
+
+/// One decoded mapping segment, with every field absolute and zero-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    pub generated_line: u32,
+    pub generated_col: u32,
+    pub source: u32,
+    pub source_line: u32,
+    pub source_col: u32,
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum SourceMapError {
+    #[error("invalid base64 VLQ character `{0}`")]
+    InvalidChar(char),
+    #[error("VLQ value is cut off at the end of a segment")]
+    Truncated,
+    #[error("mapping segment has {0} fields; expected 4")]
+    SegmentLength(usize),
+}
+
+/// Decodes a `mappings` string back into absolute segments.
+pub fn decode_mappings(mappings: &str) -> Result<Vec<Segment>, SourceMapError> {
+    let mut segments = Vec::new();
+    let (mut source, mut source_line, mut source_col) = (0i64, 0i64, 0i64);
+    for (line, groups) in mappings.split(';').enumerate() {
+        let mut col = 0i64;
+        for group in groups.split(',').filter(|group| !group.is_empty()) {
+            let fields = decode_vlq(group)?;
+            if fields.len() != 4 {
+                return Err(SourceMapError::SegmentLength(fields.len()));
+            }
+            col += fields[0];
+            source += fields[1];
+            source_line += fields[2];
+            source_col += fields[3];
+            segments.push(Segment {
+                generated_line: line as u32,
+                generated_col: col as u32,
+                source: source as u32,
+                source_line: source_line as u32,
+                source_col: source_col as u32,
+            });
+        }
+    }
+    Ok(segments)
+}
+
+fn encode_mappings(output: &Output, source: &str) -> String {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+        .collect();
+    let mut mappings = output.mappings.clone();
+    // Several spans can start at one generated position, such as a statement
+    // and its first expression; the innermost, recorded last, wins.
+    mappings.sort_by_key(|mapping| (mapping.line, mapping.col));
+    mappings.reverse();
+    mappings.dedup_by_key(|mapping| (mapping.line, mapping.col));
+    mappings.reverse();
+
+    let mut out = String::new();
+    let (mut line, mut first_on_line) = (0, true);
+    let (mut prev_col, mut prev_source_line, mut prev_source_col) = (0i64, 0i64, 0i64);
+    for mapping in mappings {
+        while line < mapping.line {
+            out.push(';');
+            line += 1;
+            // Generated columns restart on every line; source fields do not.
+            prev_col = 0;
+            first_on_line = true;
+        }
+        if !first_on_line {
+            out.push(',');
+        }
+        first_on_line = false;
+        let offset = mapping.source.offset.min(source.len());
+        let source_line = line_starts.partition_point(|&start| start <= offset) - 1;
+        let source_col = source[line_starts[source_line]..offset]
+            .encode_utf16()
+            .count();
+
+        encode_vlq(mapping.col as i64 - prev_col, &mut out);
+        encode_vlq(0, &mut out);
+        encode_vlq(source_line as i64 - prev_source_line, &mut out);
+        encode_vlq(source_col as i64 - prev_source_col, &mut out);
+        prev_col = mapping.col as i64;
+        prev_source_line = source_line as i64;
+        prev_source_col = source_col as i64;
+    }
+    out
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Appends `value` as base64 VLQ: the sign goes in the lowest bit, then five
+/// bits per digit with the sixth bit set while more digits follow.
+fn encode_vlq(value: i64, out: &mut String) {
+    let mut rest = if value < 0 {
+        ((-value) << 1) | 1
+    } else {
+        value << 1
+    };
+    loop {
+        let mut digit = rest & 0b11111;
+        rest >>= 5;
+        if rest > 0 {
+            digit |= 0b100000;
+        }
+        out.push(BASE64[digit as usize] as char);
+        if rest == 0 {
+            break;
+        }
+    }
+}
+
+fn decode_vlq(group: &str) -> Result<Vec<i64>, SourceMapError> {
+    let mut values = Vec::new();
+    let (mut value, mut shift) = (0i64, 0);
+    let mut pending = false;
+    for ch in group.chars() {
+        let digit = BASE64
+            .iter()
+            .position(|&b| b as char == ch)
+            .ok_or(SourceMapError::InvalidChar(ch))? as i64;
+        value |= (digit & 0b11111) << shift;
+        if digit & 0b100000 != 0 {
+            shift += 5;
+            pending = true;
+        } else {
+            let magnitude = value >> 1;
+            values.push(if value & 1 == 1 {
+                -magnitude
+            } else {
+                magnitude
+            });
+            (value, shift, pending) = (0, 0, false);
+        }
+    }
+    if pending {
+        return Err(SourceMapError::Truncated);
+    }
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(offset: usize) -> Span {
+        Span {
+            line: 0,
+            col: 0,
+            offset,
+            len: 1,
+            end_line: 0,
+            end_col: 1,
+        }
+    }
+
+    #[test]
+    fn encodes_vlq_like_the_spec() {
+        let cases = [
+            (0, "A"),
+            (1, "C"),
+            (-1, "D"),
+            (15, "e"),
+            (16, "gB"),
+            (-16, "hB"),
+            (123, "2H"),
+            (1000, "w+B"),
+        ];
+        for (value, encoded) in cases {
+            let mut out = String::new();
+            encode_vlq(value, &mut out);
+            assert_eq!(out, encoded, "encoding {}", value);
+            assert_eq!(decode_vlq(encoded).unwrap(), vec![value]);
+        }
+        assert_eq!(decode_vlq("AAgBC").unwrap(), vec![0, 0, 16, 1]);
+        assert_eq!(decode_vlq("g"), Err(SourceMapError::Truncated));
+        assert_eq!(decode_vlq("A!"), Err(SourceMapError::InvalidChar('!')));
+    }
+
+    #[test]
+    fn shifts_appended_mappings() {
+        let mut inner = Output::new();
+        inner.push_str("ab");
+        inner.map(span(3));
+        inner.push_str("c\n");
+        inner.map(span(5));
+        inner.push('d');
+
+        let mut out = Output::new();
+        out.push_str("é🙂 ");
+        out.append(inner);
+        assert_eq!(out.as_str(), "é🙂 abc\nd");
+        // `é` is one UTF-16 unit, the emoji two.
+        assert_eq!(
+            out.mappings,
+            vec![
+                Mapping {
+                    line: 0,
+                    col: 6,
+                    source: span(3)
+                },
+                Mapping {
+                    line: 1,
+                    col: 0,
+                    source: span(5)
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trips_mappings_through_the_encoding() {
+        let source = "ab\ncd🙂ef\n";
+        let mut out = Output::new();
+        out.map(span(0));
+        out.push_str("x = ");
+        out.map(span(3));
+        out.push_str("y;\n\n  ");
+        out.map(span(9));
+        out.push_str("z;");
+        // Same position as the first: the later, inner span wins.
+        let mut outer = Output::new();
+        outer.map(span(1));
+        outer.append(out);
+
+        let map: serde_json::Value =
+            serde_json::from_str(&render(&outer, source, "a.tsx", "a.raven")).unwrap();
+        assert_eq!(map["version"], 3);
+        assert_eq!(map["sources"][0], "a.raven");
+        let segments = decode_mappings(map["mappings"].as_str().unwrap()).unwrap();
+        let positions: Vec<_> = segments
+            .iter()
+            .map(|s| {
+                (
+                    s.generated_line,
+                    s.generated_col,
+                    s.source_line,
+                    s.source_col,
+                )
+            })
+            .collect();
+        assert_eq!(positions, vec![(0, 0, 0, 0), (0, 4, 1, 0), (2, 2, 1, 4)]);
+    }
+}
@@ -9,9 +9,29 @@ pub enum TokenKind {
     Equals,
     LBrace,
     RBrace,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Colon,
+    Comma,
+    Semi,
+    Dot,
+    Question,
+    /// `->`
+    Arrow,
+    /// `=>`
+    FatArrow,
+    /// Any other operator in code, e.g. `+`, `&&` or `===`.
+    Op(String),
     // literals / identifiers
     Ident(String),
     Str(String),
+    Number(String),
+    /// Raw contents of a backtick template literal, without the backticks.
+    Template(String),
+    /// A regular expression literal as written, slashes and flags included.
+    Regex(String),
     Text(String),
     // spacing/comments (skipped in output but tracked for spans)
     Whitespace,
@@ -20,12 +40,18 @@ pub enum TokenKind {
     Eof,
 }
 
+/// A range of source text. Lines and columns are 1-based and count
+/// characters; offsets count bytes.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Span {
+    /// Position of the first character.
     pub line: usize,
     pub col: usize,
     pub offset: usize,
     pub len: usize,
+    /// Position just past the last character.
+    pub end_line: usize,
+    pub end_col: usize,
 }
 
 impl Span {
@@ -33,6 +59,36 @@ impl Span {
     pub fn end_offset(&self) -> usize {
         self.offset + self.len
     }
+
+    /// The smallest span covering both `self` and `other`, such as an element
+    /// from its opening `<` to its closing `>`.
+    pub fn join(self, other: Span) -> Span {
+        let (start, end) = (
+            if other.offset < self.offset {
+                other
+            } else {
+                self
+            },
+            if other.end_offset() > self.end_offset() {
+                other
+            } else {
+                self
+            },
+        );
+        Span {
+            line: start.line,
+            col: start.col,
+            offset: start.offset,
+            len: end.end_offset() - start.offset,
+            end_line: end.end_line,
+            end_col: end.end_col,
+        }
+    }
+
+    /// The smallest span covering all of `spans`, or `None` if there are none.
+    pub fn merge(spans: impl IntoIterator<Item = Span>) -> Option<Span> {
+        spans.into_iter().reduce(Span::join)
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -50,8 +106,23 @@ impl fmt::Display for TokenKind {
             TokenKind::Equals => write!(f, "="),
             TokenKind::LBrace => write!(f, "{{"),
             TokenKind::RBrace => write!(f, "}}"),
+            TokenKind::LParen => write!(f, "("),
+            TokenKind::RParen => write!(f, ")"),
+            TokenKind::LBracket => write!(f, "["),
+            TokenKind::RBracket => write!(f, "]"),
+            TokenKind::Colon => write!(f, ":"),
+            TokenKind::Comma => write!(f, ","),
+            TokenKind::Semi => write!(f, ";"),
+            TokenKind::Dot => write!(f, "."),
+            TokenKind::Question => write!(f, "?"),
+            TokenKind::Arrow => write!(f, "->"),
+            TokenKind::FatArrow => write!(f, "=>"),
+            TokenKind::Op(op) => write!(f, "{}", op),
             TokenKind::Ident(name) => write!(f, "identifier `{}`", name),
             TokenKind::Str(value) => write!(f, "string \"{}\"", value),
+            TokenKind::Number(value) => write!(f, "number `{}`", value),
+            TokenKind::Template(_) => write!(f, "template literal"),
+            TokenKind::Regex(_) => write!(f, "regular expression"),
             TokenKind::Text(value) => write!(f, "text `{}`", value),
             TokenKind::Whitespace => write!(f, "whitespace"),
             TokenKind::Comment(_) => write!(f, "comment"),
@@ -59,3 +130,45 @@ impl fmt::Display for TokenKind {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn span(line: usize, col: usize, offset: usize, len: usize) -> Span {
+        Span {
+            line,
+            col,
+            offset,
+            len,
+            end_line: line,
+            end_col: col + len,
+        }
+    }
+
+    #[test]
+    fn joins_spans_in_either_order() {
+        let open = span(1, 1, 0, 5);
+        let close = Span {
+            end_line: 3,
+            end_col: 7,
+            ..span(3, 1, 20, 6)
+        };
+        let joined = open.join(close);
+        assert_eq!(joined, close.join(open));
+        assert_eq!(
+            (joined.line, joined.col, joined.offset, joined.len),
+            (1, 1, 0, 26)
+        );
+        assert_eq!((joined.end_line, joined.end_col), (3, 7));
+        // A span inside another changes nothing.
+        assert_eq!(joined.join(span(2, 3, 10, 2)), joined);
+    }
+
+    #[test]
+    fn merges_many_spans() {
+        assert_eq!(Span::merge([]), None);
+        let merged = Span::merge([span(1, 5, 4, 1), span(1, 1, 0, 2), span(1, 8, 7, 3)]);
+        assert_eq!(merged, Some(span(1, 1, 0, 10)));
+    }
+}
@@ -1,5 +1,8 @@
+#![allow(dead_code)]
+
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn fixture_path(name: &str) -> PathBuf {
     Path::new("tests/fixtures").join(name)
@@ -7,6 +10,17 @@ pub fn fixture_path(name: &str) -> PathBuf {
 
 pub fn read_fixture(name: &str) -> String {
     fs::read_to_string(fixture_path(name)).expect("fixture should exist")
+}
+
 pub fn fixture(name: &str) -> String {
     format!("Loaded fixture: {}", name)
 }
+
+/// A path in the system temp directory that no other test run uses.
+pub fn temp_path(tag: &str) -> PathBuf {
+    let unique = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("valid time")
+        .as_nanos();
+    std::env::temp_dir().join(format!("raven-{}-{}-{}", tag, std::process::id(), unique))
+}
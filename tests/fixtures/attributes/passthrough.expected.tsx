@@ -0,0 +1,3 @@
+export function Toggle() {
+  return <button data-test-id="toggle" aria-pressed="false" aria-label="Toggle menu">Menu</button>;
+}
@@ -0,0 +1,3 @@
+export function Field() {
+  return <label htmlFor="email" className="field" tabIndex="0">Email <input id="email" readOnly maxLength="40" /></label>;
+}
@@ -0,0 +1,3 @@
+export function Alert() {
+  return <p style={{ color: "red", fontSize: "12px", "--accent": "#f00" }}>Oops</p>;
+}
@@ -0,0 +1,3 @@
+export function Check() {
+  return <svg viewBox="0 0 24 24"><path strokeWidth="2" strokeLinecap="round" fillRule="evenodd" d="M5 12l5 5L20 7" /></svg>;
+}
@@ -0,0 +1,7 @@
+interface IconProps {
+  name: string;
+}
+
+export function Icon({ name }: IconProps) {
+  return <svg xmlns="http://www.w3.org/2000/svg" xmlnsXlink="http://www.w3.org/1999/xlink" xmlLang="en"><use xlinkHref={"#" + name} xmlSpace="preserve" /></svg>;
+}
@@ -1,3 +1,7 @@
-export function Button({ text }) {
-  return <button>{text}</button>;
+interface ButtonProps {
+  text: string;
+}
+
+export function Button({ text }: ButtonProps) {
+  return <button className="cta">{text}</button>;
 }
@@ -0,0 +1,15 @@
+import { TodoItem } from "./TodoItem";
+
+export interface Todo {
+  id: string;
+  title: string;
+  done: boolean;
+}
+
+export async function listTodos(): Promise<Todo[]> {
+  return await db.todo.findMany({});
+}
+
+export function TodoList() {
+  return <ul><TodoItem /></ul>;
+}
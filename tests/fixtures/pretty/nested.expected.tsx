@@ -0,0 +1,27 @@
+interface TeamListProps {
+  team: Team;
+  onSelect: (arg0: Member) => void;
+}
+
+export function TeamList({ team, onSelect }: TeamListProps) {
+  const header = (
+    <header className="team-header">
+      <h1>{team.name}</h1>
+      <p>{team.members.length} members</p>
+    </header>
+  );
+  const rows = team.members.map((member) => (
+    <li key={member.id} onClick={() => onSelect(member)}>{member.name}</li>
+  ));
+  return (
+    <section className="team">
+      {header}
+      <ul className="members">
+        {team.members.filter((member) => member.active).map((member) => (
+          <li key={member.id}><b>{member.name}</b> ({member.role})</li>
+        ))}
+      </ul>
+      <ol>{rows}</ol>
+    </section>
+  );
+}
@@ -0,0 +1,24 @@
+interface ProfileCardProps {
+  user: User;
+  onFollow: () => void;
+}
+
+export function ProfileCard({ user, onFollow }: ProfileCardProps) {
+  return (
+    <article
+      className="profile-card"
+      data-user-id={user.id}
+      aria-labelledby="profile-name"
+    >
+      <img src={user.avatar} alt="" />
+      <h2 id="profile-name">{user.name}</h2>
+      <p>Joined {user.joined} and wrote <b>{user.posts.length}</b> posts</p>
+      <ul>
+        {user.links.map((link) => (
+          <li key={link.url}><a href={link.url}>{link.label}</a></li>
+        ))}
+      </ul>
+      <button onClick={onFollow}>Follow</button>
+    </article>
+  );
+}
@@ -1,3 +1,7 @@
-export async function getUser(id: string) {
+export async function getUser(id: string): Promise<User> {
   return await db.users.findUnique({ where: { id } });
 }
+
+export async function listUsers(teamId: string): Promise<User[]> {
+  return await db.users.findMany({ where: { teamId } });
+}
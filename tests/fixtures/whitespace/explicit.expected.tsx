@@ -0,0 +1,3 @@
+export function Tags() {
+  return <span><b>a</b>{" "}<i>b</i> <u>c</u></span>;
+}
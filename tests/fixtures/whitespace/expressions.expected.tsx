@@ -0,0 +1,3 @@
+export function Inbox() {
+  return <p>Hi {name}, you have {count} new {count == 1 ? "message" : "messages"}.</p>;
+}
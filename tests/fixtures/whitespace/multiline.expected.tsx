@@ -0,0 +1,3 @@
+export function Notice() {
+  return <p>Read the<a href="/docs">docs</a>before you start.</p>;
+}
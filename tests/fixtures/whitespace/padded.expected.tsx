@@ -0,0 +1,3 @@
+export function Padded() {
+  return <code>  two  spaces  </code>;
+}
@@ -2,11 +2,16 @@ use std::time::{SystemTime, UNIX_EPOCH};
 
 use assert_cmd::Command;
 
+#[path = "../common/mod.rs"]
 mod common;
 
 #[test]
 fn prints_version() {
-    Command::cargo_bin("raven").expect("binary built").arg("--version").assert().success();
+    Command::cargo_bin("raven")
+        .expect("binary built")
+        .arg("--version")
+        .assert()
+        .success();
 }
 
 #[test]
@@ -17,21 +22,414 @@ fn builds_fixture_via_cli() {
         .duration_since(UNIX_EPOCH)
         .expect("valid time")
         .as_nanos();
-    let output = std::env::temp_dir().join(format!("raven-test-{}-{}.tsx", std::process::id(), unique));
+    let output =
+        std::env::temp_dir().join(format!("raven-test-{}-{}.tsx", std::process::id(), unique));
 
     Command::cargo_bin("raven")
         .expect("binary built")
-        .args(["build", input.to_str().unwrap(), "-o", output.to_str().unwrap()])
+        .args([
+            "build",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+        ])
         .assert()
         .success();
 
     let actual = std::fs::read_to_string(&output).expect("output file readable");
     assert_eq!(actual, expected);
     let _ = std::fs::remove_file(output);
-use assert_cmd::Command;
+}
 
 #[test]
-fn prints_version() {
-    let mut cmd = Command::cargo_bin("raven").expect("binary built");
-    cmd.arg("--version").assert().success();
+fn generates_rpc_modules_for_server_functions() {
+    let input = common::fixture_path("server_functions/getUser.raven");
+    let dir = common::temp_path("rpc");
+    let output = dir.join("getUser.ts");
+
+    Command::cargo_bin("raven")
+        .expect("binary built")
+        .args([
+            "build",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .success();
+
+    let server = std::fs::read_to_string(dir.join("getUser.server.ts")).expect("server module");
+    assert!(server.starts_with("import * as api from \"./getUser\";"));
+    assert!(server.contains("getUser: (args) => api.getUser(args.id),"));
+    let client = std::fs::read_to_string(dir.join("getUser.client.ts")).expect("client module");
+    assert!(client.starts_with("import type { User } from \"./getUser\";\n"));
+    assert!(client.contains("export async function getUser(id: string): Promise<User> {"));
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn writes_source_map_next_to_the_output() {
+    let input = common::fixture_path("components/button.raven");
+    let dir = common::temp_path("map");
+    let output = dir.join("button.tsx");
+
+    Command::cargo_bin("raven")
+        .expect("binary built")
+        .args([
+            "build",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--source-map",
+        ])
+        .assert()
+        .success();
+
+    let code = std::fs::read_to_string(&output).expect("output file readable");
+    let expected = common::read_fixture("components/button.expected.tsx");
+    assert_eq!(
+        code,
+        format!("{}//# sourceMappingURL=button.tsx.map\n", expected)
+    );
+    let map = std::fs::read_to_string(dir.join("button.tsx.map")).expect("map file readable");
+    let map: serde_json::Value = serde_json::from_str(&map).expect("valid JSON");
+    assert_eq!(map["file"], "button.tsx");
+    let source = map["sources"][0].as_str().expect("source path");
+    assert!(source.ends_with("tests/fixtures/components/button.raven"));
+    assert!(
+        dir.join(source).exists(),
+        "{} is relative to the map",
+        source
+    );
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn reports_errors_with_a_source_snippet() {
+    let input = common::fixture_path("errors/mismatched.raven");
+    let output = common::temp_path("err").with_extension("tsx");
+
+    let assert = Command::cargo_bin("raven")
+        .expect("binary built")
+        .args([
+            "build",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).expect("utf-8 stderr");
+    assert_eq!(
+        stderr,
+        format!(
+            "error[R0203]: expected `</p>` but found `</span>`\n \
+             --> {}:4:14\n  \
+             |\n\
+             4 |       <p>hi</span>\n  \
+             |              ^^^^ mismatched closing tag\n\n\
+             For more information about this error, try `raven explain R0203`.\n",
+            input.display()
+        )
+    );
+    assert!(!output.exists());
+}
+
+#[test]
+fn reports_every_syntax_error_in_a_file() {
+    let input = common::fixture_path("errors/several.raven");
+    let output = std::env::temp_dir().join(format!("raven-errs-{}.tsx", std::process::id()));
+
+    let assert = Command::cargo_bin("raven")
+        .expect("binary built")
+        .args([
+            "build",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).expect("utf-8 stderr");
+    let headers: Vec<&str> = stderr
+        .lines()
+        .filter(|line| line.starts_with("error"))
+        .collect();
+    assert_eq!(
+        headers,
+        [
+            "error[R0201]: expected string literal or expression, found `/`",
+            "error[R0205]: unknown directive `use:`",
+            "error[R0204]: unclosed `{`",
+            "error[R0203]: expected `</li>` but found `</b>`",
+            "error[R0201]: expected `component`, `page`, `server fn`, `type` or `import`, found identifier `widget`",
+            "error: aborting due to 5 errors",
+        ]
+    );
+    for location in [
+        "several.raven:4:35",
+        "several.raven:6:13",
+        "several.raven:14:1",
+    ] {
+        assert!(stderr.contains(location), "{} in {}", location, stderr);
+    }
+    assert!(stderr.ends_with(
+        "Some errors have detailed explanations: R0201, R0203, R0204, R0205.\n\
+         For more information about an error, try `raven explain R0201`.\n"
+    ));
+}
+
+#[test]
+fn explains_error_codes() {
+    let assert = Command::cargo_bin("raven")
+        .expect("binary built")
+        .args(["explain", "r0203"])
+        .assert()
+        .success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).expect("utf-8 stdout");
+    assert!(stdout.starts_with("A closing tag does not match the element it closes.\n"));
+    assert!(stdout.contains("  return <p>Saved</span>\n"));
+
+    let assert = Command::cargo_bin("raven")
+        .expect("binary built")
+        .args(["explain", "R9999"])
+        .assert()
+        .failure();
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).expect("utf-8 stderr");
+    assert_eq!(stderr, "error: no error has the code `R9999`\n");
+}
+
+#[test]
+fn prints_json_diagnostics_one_per_line() {
+    let input = common::fixture_path("errors/several.raven");
+    let output = std::env::temp_dir().join(format!("raven-json-{}.tsx", std::process::id()));
+
+    let assert = Command::cargo_bin("raven")
+        .expect("binary built")
+        .args([
+            "build",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--message-format",
+            "json",
+        ])
+        .assert()
+        .failure();
+    let out = assert.get_output();
+    assert!(out.stderr.is_empty());
+    let stdout = String::from_utf8(out.stdout.clone()).expect("utf-8 stdout");
+    let diagnostics: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("each line is a JSON object"))
+        .collect();
+    assert_eq!(diagnostics.len(), 5);
+
+    let span_keys = [
+        "byte_end",
+        "byte_start",
+        "col_end",
+        "col_start",
+        "line_end",
+        "line_start",
+    ];
+    for diagnostic in &diagnostics {
+        let keys: Vec<&str> = diagnostic
+            .as_object()
+            .expect("an object")
+            .keys()
+            .map(String::as_str)
+            .collect();
+        assert_eq!(
+            keys,
+            ["code", "file", "labels", "message", "notes", "severity", "span"]
+        );
+        assert_eq!(diagnostic["severity"], "error");
+        assert_eq!(diagnostic["file"], input.to_str().unwrap());
+        let span = diagnostic["span"].as_object().expect("a primary span");
+        assert_eq!(
+            span.keys().map(String::as_str).collect::<Vec<_>>(),
+            span_keys
+        );
+        assert_eq!(diagnostic["labels"][0]["primary"], true);
+        assert_eq!(diagnostic["labels"][0]["span"], diagnostic["span"]);
+    }
+
+    let source = common::read_fixture("errors/several.raven");
+    let unclosed = &diagnostics[2];
+    assert_eq!(unclosed["message"], "unclosed `{`");
+    let start = unclosed["span"]["byte_start"].as_u64().unwrap() as usize;
+    assert_eq!(&source[start..start + 1], "{");
+    assert_eq!(unclosed["span"]["line_start"], 6);
+    assert_eq!(unclosed["span"]["col_start"], 13);
+}
+
+#[test]
+fn keeps_stdout_clean_in_json_mode() {
+    let input = common::fixture_path("basic/input.raven");
+    let output = std::env::temp_dir().join(format!("raven-json-ok-{}.tsx", std::process::id()));
+
+    Command::cargo_bin("raven")
+        .expect("binary built")
+        .args([
+            "build",
+            input.to_str().unwrap(),
+            "-o",
+            output.to_str().unwrap(),
+            "--message-format=json",
+        ])
+        .assert()
+        .success()
+        .stdout("");
+    let _ = std::fs::remove_file(output);
+}
+
+#[test]
+fn serves_the_language_server_protocol_over_stdio() {
+    let uri = "file:///app.raven";
+    let messages = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{"capabilities":{}}}"#
+            .to_string(),
+        r#"{"jsonrpc":"2.0","method":"initialized","params":{}}"#.to_string(),
+        format!(
+            r#"{{"jsonrpc":"2.0","method":"textDocument/didOpen","params":{{"textDocument":{{"uri":"{}","languageId":"raven","version":1,"text":"component App() {{\n  return <p>hi</span>\n}}\n"}}}}}}"#,
+            uri
+        ),
+        r#"{"jsonrpc":"2.0","id":2,"method":"shutdown"}"#.to_string(),
+        r#"{"jsonrpc":"2.0","method":"exit"}"#.to_string(),
+    ];
+    let input: String = messages
+        .iter()
+        .map(|body| format!("Content-Length: {}\r\n\r\n{}", body.len(), body))
+        .collect();
+
+    let assert = Command::cargo_bin("raven")
+        .expect("binary built")
+        .arg("lsp")
+        .write_stdin(input)
+        .assert()
+        .success();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).expect("utf-8 stdout");
+    let replies: Vec<serde_json::Value> = stdout
+        .split("Content-Length: ")
+        .skip(1)
+        .map(|frame| {
+            let (length, body) = frame.split_once("\r\n\r\n").expect("framed message");
+            assert_eq!(length.parse::<usize>().unwrap(), body.len());
+            serde_json::from_str(body).expect("json body")
+        })
+        .collect();
+    assert_eq!(replies.len(), 3, "{}", stdout);
+    assert_eq!(replies[0]["id"], 1);
+    assert_eq!(replies[0]["result"]["capabilities"]["hoverProvider"], true);
+    assert_eq!(replies[1]["method"], "textDocument/publishDiagnostics");
+    assert_eq!(replies[1]["params"]["diagnostics"][0]["code"], "R0203");
+    assert_eq!(
+        replies[2],
+        serde_json::json!({ "jsonrpc": "2.0", "id": 2, "result": null })
+    );
+}
+
+#[test]
+fn formats_files_and_checks_formatting() {
+    let dir = common::temp_path("fmt");
+    std::fs::create_dir_all(dir.join("pages")).expect("temp dir created");
+    let page = dir.join("pages/index.raven");
+    let clean = dir.join("clean.raven");
+    std::fs::write(&page, "page Index() { return <h1>Home</h1> }\n").unwrap();
+    std::fs::write(&clean, "component A() {\n  return <p>a</p>\n}\n").unwrap();
+
+    Command::cargo_bin("raven")
+        .expect("binary built")
+        .args(["fmt", "--check", dir.to_str().unwrap()])
+        .assert()
+        .failure()
+        .stdout(format!("Would reformat {}\n", page.display()))
+        .stderr("error: 1 file not formatted\n");
+    assert_eq!(
+        std::fs::read_to_string(&page).unwrap(),
+        "page Index() { return <h1>Home</h1> }\n"
+    );
+
+    Command::cargo_bin("raven")
+        .expect("binary built")
+        .args(["fmt", dir.to_str().unwrap()])
+        .assert()
+        .success()
+        .stdout(format!("Formatted {}\n", page.display()));
+    assert_eq!(
+        std::fs::read_to_string(&page).unwrap(),
+        "page Index() {\n  return <h1>Home</h1>\n}\n"
+    );
+
+    Command::cargo_bin("raven")
+        .expect("binary built")
+        .args([
+            "fmt",
+            "--check",
+            page.to_str().unwrap(),
+            clean.to_str().unwrap(),
+        ])
+        .assert()
+        .success()
+        .stdout("");
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn reports_files_fmt_cannot_parse_and_checks_the_rest() {
+    // The todo app's Prisma-style schema is not RavensOne source.
+    let assert = Command::cargo_bin("raven")
+        .expect("binary built")
+        .args(["fmt", "--check", "examples"])
+        .assert()
+        .failure()
+        .stdout("");
+    let stderr = String::from_utf8(assert.get_output().stderr.clone()).unwrap();
+    assert!(
+        stderr.starts_with("error[R0201]: expected `component`, `page`, `server fn`, `type` or `import`, found identifier `datasource`\n --> examples/02-todo-app/src/schema.raven:1:1\n"),
+        "{}",
+        stderr
+    );
+    assert!(
+        stderr.ends_with("error: could not format 1 file\n"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn reports_unformatted_files_as_json() {
+    let dir = common::temp_path("fmt-json");
+    std::fs::create_dir_all(&dir).expect("temp dir created");
+    let first = dir.join("a.raven");
+    let second = dir.join("b.raven");
+    std::fs::write(&first, "page A() { return <h1>A</h1> }\n").unwrap();
+    std::fs::write(&second, "page B() { return <h1>B</h1> }\n").unwrap();
+
+    let assert = Command::cargo_bin("raven")
+        .expect("binary built")
+        .args([
+            "fmt",
+            "--check",
+            "--message-format",
+            "json",
+            dir.to_str().unwrap(),
+        ])
+        .assert()
+        .failure();
+    let stdout = String::from_utf8(assert.get_output().stdout.clone()).unwrap();
+    let files: Vec<serde_json::Value> = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("JSON object"))
+        .map(|object| object["file"].clone())
+        .collect();
+    assert_eq!(
+        files,
+        vec![
+            serde_json::Value::from(first.display().to_string()),
+            serde_json::Value::from(second.display().to_string()),
+        ]
+    );
+    let _ = std::fs::remove_dir_all(dir);
 }
@@ -1,5 +1,8 @@
-use ravensone::{lex, parse, to_tsx};
+use ravensone::{
+    compile_component, compile_component_pretty, compile_with_source_map, decode_mappings,
+};
 
+#[path = "../common/mod.rs"]
 mod common;
 
 #[test]
@@ -15,93 +18,124 @@ fn compiles_button_component() {
 fn run_fixture(input: &str, expected: &str) {
     let source = common::read_fixture(input);
     let expected_tsx = common::read_fixture(expected);
-    let component = parse_component(&source);
-    let tokens = lex(&component.markup).expect("lexing succeeded");
-    let doc = parse(&tokens, &component.markup).expect("parsing succeeded");
-    let tsx_body = to_tsx(&doc);
-    let output = render_component(&component.name, &component.params, &tsx_body);
-    assert_eq!(output, expected_tsx);
+    assert_eq!(
+        compile_component(&source).expect("compilation succeeded"),
+        expected_tsx,
+        "fixture {} should compile",
+        input
+    );
 }
 
-struct Component {
-    name: String,
-    params: Vec<String>,
-    markup: String,
+#[test]
+fn loads_fixture_helper() {
+    let fixture = common::fixture("basic/input.raven");
+    assert!(fixture.contains("basic"));
 }
 
-fn parse_component(src: &str) -> Component {
-    let trimmed = src.trim();
-    let rest = trimmed.strip_prefix("component").expect("component keyword").trim_start();
-    let paren_idx = rest.find('(').expect("parameter list");
-    let name = rest[..paren_idx].trim().to_string();
-    let (params_src, after_params) = extract_parens(&rest[paren_idx..]);
-    let params = params_src
-        .split(',')
-        .filter_map(|param| {
-            let name = param.split(':').next()?.trim();
-            if name.is_empty() { None } else { Some(name.to_string()) }
-        })
-        .collect();
-    let body = extract_block(after_params.trim_start());
-    let markup = extract_markup(&body);
-    Component { name, params, markup }
+#[test]
+fn renames_html_attributes_to_react_props() {
+    run_fixture(
+        "attributes/renamed.raven",
+        "attributes/renamed.expected.tsx",
+    );
 }
 
-fn extract_parens(input: &str) -> (&str, &str) {
-    assert!(input.starts_with('('));
-    let mut depth = 0;
-    for (idx, ch) in input.char_indices() {
-        match ch {
-            '(' => depth += 1,
-            ')' => {
-                depth -= 1;
-                if depth == 0 {
-                    return (&input[1..idx], &input[idx + 1..]);
-                }
-            }
-            _ => {}
-        }
-    }
-    panic!("unclosed parens");
+#[test]
+fn camel_cases_hyphenated_svg_attributes() {
+    run_fixture("attributes/svg.raven", "attributes/svg.expected.tsx");
 }
 
-fn extract_block(input: &str) -> String {
-    assert!(input.starts_with('{'));
-    let mut depth = 0;
-    for (idx, ch) in input.char_indices() {
-        match ch {
-            '{' => depth += 1,
-            '}' => {
-                depth -= 1;
-                if depth == 0 {
-                    return input[1..idx].to_string();
-                }
-            }
-            _ => {}
-        }
-    }
-    panic!("unclosed block");
+#[test]
+fn maps_namespaced_svg_attributes() {
+    run_fixture(
+        "attributes/svg_namespaces.raven",
+        "attributes/svg_namespaces.expected.tsx",
+    );
+}
+
+#[test]
+fn converts_inline_style_strings_to_objects() {
+    run_fixture("attributes/style.raven", "attributes/style.expected.tsx");
+}
+
+#[test]
+fn passes_data_and_aria_attributes_through() {
+    run_fixture(
+        "attributes/passthrough.raven",
+        "attributes/passthrough.expected.tsx",
+    );
 }
 
-fn extract_markup(body: &str) -> String {
-    let return_idx = body.find("return").expect("return keyword");
-    let mut markup = body[return_idx + "return".len()..].trim();
-    if markup.ends_with(';') {
-        markup = markup[..markup.len() - 1].trim_end();
+#[test]
+fn preserves_meaningful_whitespace_in_text() {
+    for case in ["inline", "multiline", "expressions", "explicit", "padded"] {
+        run_fixture(
+            &format!("whitespace/{}.raven", case),
+            &format!("whitespace/{}.expected.tsx", case),
+        );
     }
-    if markup.starts_with('(') && markup.ends_with(')') {
-        markup = markup[1..markup.len() - 1].trim();
+}
+
+#[test]
+fn pretty_prints_long_markup() {
+    let source = common::read_fixture("pretty/profile.raven");
+    let expected = common::read_fixture("pretty/profile.expected.tsx");
+    assert_eq!(
+        compile_component_pretty(&source).expect("compilation succeeded"),
+        expected
+    );
+    // The compact printer keeps the same markup on one line.
+    let compact = compile_component(&source).expect("compilation succeeded");
+    assert!(compact.contains("  return <article className=\"profile-card\""));
+}
+
+#[test]
+fn pretty_prints_markup_outside_return_statements() {
+    let source = common::read_fixture("pretty/nested.raven");
+    let expected = common::read_fixture("pretty/nested.expected.tsx");
+    assert_eq!(
+        compile_component_pretty(&source).expect("compilation succeeded"),
+        expected
+    );
+}
+
+#[test]
+fn source_map_points_elements_back_to_the_raven_file() {
+    let source = common::read_fixture("pretty/profile.raven");
+    for pretty in [false, true] {
+        let mapped = compile_with_source_map(&source, pretty, "profile.tsx", "profile.raven")
+            .expect("compilation succeeded");
+        let map: serde_json::Value = serde_json::from_str(&mapped.map).expect("valid JSON");
+        assert_eq!(map["version"], 3);
+        assert_eq!(map["file"], "profile.tsx");
+        assert_eq!(map["sources"][0], "profile.raven");
+        assert_eq!(map["sourcesContent"][0], source.as_str());
+        let segments =
+            decode_mappings(map["mappings"].as_str().expect("mappings")).expect("decodable");
+
+        for tag in ["<article", "<img", "<h2", "<b>", "<li", "<button"] {
+            let generated = position(&mapped.code, tag);
+            let segment = segments
+                .iter()
+                .find(|s| (s.generated_line, s.generated_col) == generated)
+                .unwrap_or_else(|| panic!("no mapping for {} (pretty: {})", tag, pretty));
+            assert_eq!(
+                (segment.source_line, segment.source_col),
+                position(&source, tag),
+                "{} (pretty: {})",
+                tag,
+                pretty
+            );
+        }
     }
-    markup.to_string()
 }
 
-fn render_component(name: &str, params: &[String], body: &str) -> String {
-    let params_binding = if params.is_empty() {
-        "()".to_string()
-    } else {
-        format!("({{ {} }})", params.join(", "))
-    };
-    format!("export function {}{} {{\n  return {};\n}}\n", name, params_binding, body)
-    let fixture = common::fixture("basic/input.raven");
-    assert!(fixture.contains("basic"));
+/// Zero-based line and UTF-16 column of the first `needle` in `text`.
+fn position(text: &str, needle: &str) -> (u32, u32) {
+    let offset = text.find(needle).expect("needle present");
+    let line_start = text[..offset].rfind('\n').map_or(0, |idx| idx + 1);
+    (
+        text[..offset].matches('\n').count() as u32,
+        text[line_start..offset].encode_utf16().count() as u32,
+    )
 }
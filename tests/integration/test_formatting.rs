@@ -0,0 +1,69 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ravensone::{compile_component, compile_component_pretty, format_source, CompileError};
+
+fn raven_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).expect("directory readable") {
+        let path = entry.expect("entry readable").path();
+        if path.is_dir() {
+            raven_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "raven") {
+            files.push(path);
+        }
+    }
+}
+
+#[test]
+fn formats_every_example_and_fixture_idempotently() {
+    let mut files = Vec::new();
+    raven_files(Path::new("examples"), &mut files);
+    raven_files(Path::new("tests/fixtures"), &mut files);
+    files.sort();
+    assert!(files.len() > 10, "found {} files", files.len());
+
+    let mut formatted_files = 0;
+    for path in &files {
+        let source = fs::read_to_string(path).expect("file readable");
+        let formatted = match format_source(&source) {
+            Ok(formatted) => formatted,
+            Err(err) => {
+                assert!(
+                    matches!(
+                        compile_component(&source),
+                        Err(CompileError::Lex(_)
+                            | CompileError::Parse(_)
+                            | CompileError::ParseErrors(_))
+                    ),
+                    "{} compiles but does not format: {}",
+                    path.display(),
+                    err
+                );
+                continue;
+            }
+        };
+        formatted_files += 1;
+        assert_eq!(
+            format_source(&formatted).expect("formatted output parses"),
+            formatted,
+            "formatting {} again changes it",
+            path.display()
+        );
+        // Layout never changes what a file compiles to.
+        if let Ok(expected) = compile_component(&source) {
+            assert_eq!(
+                compile_component(&formatted).expect("formatted output compiles"),
+                expected,
+                "{}",
+                path.display()
+            );
+            assert_eq!(
+                compile_component_pretty(&formatted).unwrap(),
+                compile_component_pretty(&source).unwrap(),
+                "{}",
+                path.display()
+            );
+        }
+    }
+    assert!(formatted_files > 10, "formatted {} files", formatted_files);
+}
@@ -1,5 +1,6 @@
 use ravensone::compile_component;
 
+#[path = "../common/mod.rs"]
 mod common;
 
 #[test]
@@ -7,6 +8,11 @@ fn builds_all_fixture_components() {
     let fixtures = [
         ("basic/input.raven", "basic/expected.tsx"),
         ("components/button.raven", "components/button.expected.tsx"),
+        (
+            "server_functions/getUser.raven",
+            "server_functions/getUser.expected.ts",
+        ),
+        ("modules/todos.raven", "modules/todos.expected.tsx"),
     ];
 
     for (input, expected) in fixtures {
@@ -15,7 +21,7 @@ fn builds_all_fixture_components() {
         let output = compile_component(&source).expect("compilation succeeded");
         assert_eq!(output, expected_tsx, "fixture {} should compile", input);
     }
-mod common;
+}
 
 #[test]
 fn builds_example_project() {
@@ -2,8 +2,8 @@ import * as vscode from 'vscode-languageclient/node';
 
 export function createLanguageClient() {
   const serverOptions: vscode.ServerOptions = {
-    run: { module: 'server.js', transport: vscode.TransportKind.ipc },
-    debug: { module: 'server.js', transport: vscode.TransportKind.ipc }
+    run: { command: 'raven', args: ['lsp'], transport: vscode.TransportKind.stdio },
+    debug: { command: 'raven', args: ['lsp'], transport: vscode.TransportKind.stdio }
   };
 
   const clientOptions: vscode.LanguageClientOptions = {